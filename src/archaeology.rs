@@ -0,0 +1,30 @@
+use anyhow::Result;
+
+use crate::vcs::Vcs;
+
+/// Walks history to find the last commit in which the function was referenced besides its declaration.
+pub fn run(vcs: &dyn Vcs, name: &str) -> Result<()> {
+    if vcs.mentions_at(vcs.head(), name) >= 2 {
+        println!("\"{}\" is still referenced at {}", name, vcs.head());
+        return Ok(());
+    }
+
+    // commits that changed the number of mentions, newest first.
+    for commit in vcs.changes(name)? {
+        let parent = match vcs.parent(&commit) {
+            Some(parent) => parent,
+            None => continue,
+        };
+        if vcs.mentions_at(&commit, name) < 2 && vcs.mentions_at(&parent, name) >= 2 {
+            println!("last referenced in {}", vcs.describe(&parent)?);
+            println!("became unused in {}", vcs.describe(&commit)?);
+            return Ok(());
+        }
+    }
+
+    println!(
+        "\"{}\" has never been referenced besides its declaration",
+        name
+    );
+    Ok(())
+}
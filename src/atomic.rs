@@ -0,0 +1,99 @@
+use std::{
+    fs::{self, File},
+    io::Write,
+    path::Path,
+};
+
+use anyhow::{Context, Result};
+
+/// Writes a file by way of a temporary file beside it, which is renamed into place once complete, so that
+/// a crash part way through leaves the previous file, or none, rather than a truncated one.
+pub fn write_with(path: &Path, contents: impl FnOnce(&mut File) -> Result<()>) -> Result<()> {
+    let directory = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let mut file = tempfile::Builder::new()
+        .prefix(".snapbug")
+        .tempfile_in(directory)
+        .with_context(|| {
+            format!(
+                "failed to create a temporary file in {}",
+                directory.display()
+            )
+        })?;
+    contents(file.as_file_mut())?;
+    // temporary files are only readable by their owner, unlike the files they replace.
+    match fs::metadata(path) {
+        Ok(metadata) => file.as_file().set_permissions(metadata.permissions())?,
+        Err(_) => set_default_permissions(file.as_file())?,
+    }
+    file.as_file().sync_all()?;
+    file.persist(path)
+        .with_context(|| format!("failed to replace {}", path.display()))?;
+    Ok(())
+}
+
+/// Writes the contents to a file atomically.
+pub fn write(path: &Path, contents: impl AsRef<[u8]>) -> Result<()> {
+    write_with(path, |file| Ok(file.write_all(contents.as_ref())?))
+}
+
+#[cfg(unix)]
+fn set_default_permissions(file: &File) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    Ok(file.set_permissions(fs::Permissions::from_mode(0o644))?)
+}
+
+#[cfg(not(unix))]
+fn set_default_permissions(_file: &File) -> Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::anyhow;
+
+    use super::*;
+
+    #[test]
+    fn failed_writes_leave_the_previous_file() {
+        let root = tempfile::Builder::new()
+            .prefix("snapbug")
+            .tempdir()
+            .unwrap();
+        let path = root.path().join("report.json");
+        write(&path, "{}\n").unwrap();
+
+        let err = write_with(&path, |file| {
+            file.write_all(b"{\"findings\": [")?;
+            Err(anyhow!("interrupted"))
+        })
+        .unwrap_err();
+        assert_eq!(err.to_string(), "interrupted");
+        assert_eq!(fs::read_to_string(&path).unwrap(), "{}\n");
+        // the temporary file is cleaned up too.
+        assert_eq!(fs::read_dir(root.path()).unwrap().count(), 1);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn replaced_files_keep_their_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let root = tempfile::Builder::new()
+            .prefix("snapbug")
+            .tempdir()
+            .unwrap();
+        let (new, existing) = (root.path().join("new"), root.path().join("existing"));
+        write(&new, "new\n").unwrap();
+        fs::write(&existing, "old\n").unwrap();
+        fs::set_permissions(&existing, fs::Permissions::from_mode(0o600)).unwrap();
+        write(&existing, "new\n").unwrap();
+
+        let mode = |path: &Path| fs::metadata(path).unwrap().permissions().mode() & 0o777;
+        assert_eq!(mode(&new), 0o644);
+        assert_eq!(mode(&existing), 0o600);
+        assert_eq!(fs::read_to_string(&existing).unwrap(), "new\n");
+    }
+}
@@ -0,0 +1,272 @@
+use std::{fs, path::Path};
+
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use time::{Date, OffsetDateTime};
+use toml::value::Datetime;
+
+use crate::{atomic, config::parse_duration};
+
+/// Findings that have been accepted and should not fail the scan.
+#[derive(Deserialize, Default, Debug)]
+pub struct Baseline {
+    #[serde(default, rename = "suppress")]
+    entries: Vec<Entry>,
+}
+
+/// A single suppressed finding, keyed by its path relative to the root and its name.
+#[derive(Deserialize, Serialize, Debug)]
+pub struct Entry {
+    path: String,
+    name: String,
+    /// Why the finding was accepted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    reason: Option<String>,
+    /// The last day on which the suppression applies.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires: Option<Datetime>,
+}
+
+/// Entries serialized as an array of tables, as in a baseline file.
+#[derive(Serialize)]
+struct Entries<'a> {
+    suppress: &'a [Entry],
+}
+
+/// Whether a finding is covered by the baseline.
+#[derive(Debug)]
+pub enum Suppression<'a> {
+    None,
+    Active,
+    Expired(&'a Entry),
+}
+
+impl Baseline {
+    /// Parses the given baseline file.
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("failed to read baseline {}", path.display()))?;
+        toml::from_str(&contents).with_context(|| format!("invalid baseline {}", path.display()))
+    }
+
+    /// Finds the entry covering the named function in the given file, relative to the root.
+    pub fn lookup(&self, relative: &Path, name: &str) -> Suppression<'_> {
+        let path = normalize(relative);
+        let today = OffsetDateTime::now_utc().date();
+
+        match self
+            .entries
+            .iter()
+            .find(|entry| entry.path == path && entry.name == name)
+        {
+            Some(entry) if entry.has_expired(today) => Suppression::Expired(entry),
+            Some(_) => Suppression::Active,
+            None => Suppression::None,
+        }
+    }
+
+    /// The path and name of each entry, in the order they are listed.
+    pub fn entries(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.entries
+            .iter()
+            .map(|entry| (entry.path.as_str(), entry.name.as_str()))
+    }
+}
+
+/// Identifies a finding across runs by its path relative to the root and its name, e.g. `pkg/mod.py::helper`.
+pub fn fingerprint(relative: &Path, name: &str) -> String {
+    format!("{}::{}", normalize(relative), name)
+}
+
+/// Splits a fingerprint into its path and name.
+fn split_fingerprint(fingerprint: &str) -> Result<(&str, &str)> {
+    fingerprint
+        .rsplit_once("::")
+        .ok_or_else(|| anyhow!("invalid fingerprint {:?}, expected PATH::NAME", fingerprint))
+}
+
+/// Paths are always stored with forward slashes.
+pub fn normalize(relative: &Path) -> String {
+    relative.to_string_lossy().replace('\\', "/")
+}
+
+/// Parses an expiry given either as a date, e.g. "2025-06-30", or as a duration from today, e.g. "90d".
+pub fn parse_expiry(text: &str) -> Result<Datetime> {
+    if let Ok(date) = text.parse::<Datetime>() {
+        return Ok(date);
+    }
+    let duration = parse_duration(text).with_context(|| format!("invalid expiry {:?}", text))?;
+    let date = OffsetDateTime::now_utc().date() + duration;
+    Ok(date.to_string().parse()?)
+}
+
+/// Appends entries for the given fingerprints to the baseline file, creating it if needed.
+/// Fingerprints that are already suppressed are skipped.
+pub fn suppress(
+    path: &Path,
+    fingerprints: &[String],
+    reason: Option<&str>,
+    expires: Option<Datetime>,
+) -> Result<()> {
+    let existing = if path.exists() {
+        Baseline::load(path)?
+    } else {
+        Baseline::default()
+    };
+
+    let mut entries: Vec<Entry> = Vec::new();
+    for fingerprint in fingerprints {
+        let (file, name) = split_fingerprint(fingerprint)?;
+        if existing
+            .entries
+            .iter()
+            .chain(&entries)
+            .any(|entry| entry.path == file && entry.name == name)
+        {
+            eprintln!("{} is already suppressed", fingerprint);
+            continue;
+        }
+        entries.push(Entry {
+            path: file.to_owned(),
+            name: name.to_owned(),
+            reason: reason.map(str::to_owned),
+            expires,
+        });
+    }
+    if entries.is_empty() {
+        return Ok(());
+    }
+
+    // appended by rewriting the file, so that the comments and layout of existing entries are kept.
+    let mut text = if path.exists() {
+        fs::read_to_string(path)
+            .with_context(|| format!("failed to read baseline {}", path.display()))?
+    } else {
+        String::new()
+    };
+    if !text.is_empty() {
+        text.push('\n');
+    }
+    text.push_str(&toml::to_string(&Entries { suppress: &entries })?);
+    atomic::write(path, text)?;
+
+    eprintln!(
+        "suppressed {} findings in {}",
+        entries.len(),
+        path.display()
+    );
+    Ok(())
+}
+
+/// Replaces the baseline file with an entry for each of the given fingerprints, keeping the reasons and
+/// expiry dates of the entries it already had for them. Entries that have expired are renewed without an
+/// expiry, as the new baseline accepts everything found.
+pub fn write(path: &Path, fingerprints: &[String]) -> Result<()> {
+    let mut existing = if path.exists() {
+        Baseline::load(path)?.entries
+    } else {
+        Vec::new()
+    };
+
+    let mut keys = fingerprints
+        .iter()
+        .map(|fingerprint| split_fingerprint(fingerprint))
+        .collect::<Result<Vec<_>>>()?;
+    keys.sort_unstable();
+    keys.dedup();
+    let today = OffsetDateTime::now_utc().date();
+    let entries: Vec<Entry> = keys
+        .into_iter()
+        .map(|(file, name)| {
+            match existing
+                .iter()
+                .position(|entry| entry.path == file && entry.name == name)
+            {
+                Some(index) => {
+                    let mut entry = existing.swap_remove(index);
+                    if entry.has_expired(today) {
+                        entry.expires = None;
+                    }
+                    entry
+                }
+                None => Entry {
+                    path: file.to_owned(),
+                    name: name.to_owned(),
+                    reason: None,
+                    expires: None,
+                },
+            }
+        })
+        .collect();
+
+    let text = toml::to_string(&Entries { suppress: &entries })?;
+    atomic::write(path, text)
+        .with_context(|| format!("failed to write baseline {}", path.display()))?;
+    eprintln!(
+        "wrote {} findings to baseline {}",
+        entries.len(),
+        path.display()
+    );
+    Ok(())
+}
+
+impl Entry {
+    /// Entries without a valid expiry date never expire.
+    fn has_expired(&self, today: Date) -> bool {
+        self.expires
+            .and_then(|expires| expires.date)
+            .and_then(|date| {
+                Date::from_calendar_date(date.year.into(), date.month.try_into().ok()?, date.day)
+                    .ok()
+            })
+            .map(|expires| today > expires)
+            .unwrap_or(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXPIRED: &str = "[[suppress]]\npath = \"a.py\"\nname = \"old\"\nreason = \"legacy\"\nexpires = 2000-01-01\n\n[[suppress]]\npath = \"a.py\"\nname = \"kept\"\nexpires = 9999-12-31\n";
+
+    #[test]
+    fn suppressions_lapse_after_their_expiry() {
+        let baseline: Baseline = toml::from_str(EXPIRED).unwrap();
+        let relative = Path::new("a.py");
+        assert!(matches!(
+            baseline.lookup(relative, "old"),
+            Suppression::Expired(_)
+        ));
+        assert!(matches!(
+            baseline.lookup(relative, "kept"),
+            Suppression::Active
+        ));
+        assert!(matches!(
+            baseline.lookup(relative, "other"),
+            Suppression::None
+        ));
+    }
+
+    #[test]
+    fn rewriting_renews_expired_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("baseline.toml");
+        fs::write(&path, EXPIRED).unwrap();
+
+        write(&path, &["a.py::old".to_owned(), "a.py::kept".to_owned()]).unwrap();
+        let baseline = Baseline::load(&path).unwrap();
+        let relative = Path::new("a.py");
+        assert!(matches!(
+            baseline.lookup(relative, "old"),
+            Suppression::Active
+        ));
+        let old = baseline.entries.iter().find(|entry| entry.name == "old");
+        assert_eq!(
+            old.and_then(|entry| entry.reason.as_deref()),
+            Some("legacy")
+        );
+        let kept = baseline.entries.iter().find(|entry| entry.name == "kept");
+        assert!(kept.and_then(|entry| entry.expires).is_some());
+    }
+}
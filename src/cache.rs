@@ -0,0 +1,92 @@
+use std::{
+    fs,
+    io::{self, Read},
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+
+use crate::{atomic, declarations::ScannedFile, metadata::sha256_of};
+
+/// A content-addressed store of scanned files, such as a directory restored from a CI cache. Entries are
+/// keyed by a file's contents, its path relative to the root and the settings it was scanned with, so one
+/// cache can be shared by every branch.
+pub struct Cache {
+    directory: PathBuf,
+    /// Digest of the version and settings, which invalidates every entry when either changes.
+    fingerprint: String,
+    hits: AtomicUsize,
+    misses: AtomicUsize,
+}
+
+/// How often scanned files were found in the cache.
+#[derive(Serialize, Clone, Copy, Debug)]
+pub struct CacheStats {
+    pub hits: usize,
+    pub misses: usize,
+}
+
+impl Cache {
+    pub fn open(directory: &Path, fingerprint: String) -> Result<Self> {
+        fs::create_dir_all(directory)
+            .with_context(|| format!("failed to create cache {}", directory.display()))?;
+        Ok(Self {
+            directory: directory.to_owned(),
+            fingerprint,
+            hits: AtomicUsize::new(0),
+            misses: AtomicUsize::new(0),
+        })
+    }
+
+    /// The key of a file with the given contents and path relative to the root. The contents are read a
+    /// buffer at a time.
+    pub fn key(&self, relative: &str, contents: impl Read) -> io::Result<String> {
+        let keyed = self
+            .fingerprint
+            .as_bytes()
+            .chain(&b"\0"[..])
+            .chain(relative.as_bytes())
+            .chain(&b"\0"[..])
+            .chain(contents);
+        sha256_of(keyed)
+    }
+
+    /// Entries are spread over directories by their first two characters, like git's objects.
+    fn path(&self, key: &str) -> PathBuf {
+        self.directory
+            .join(&key[..2])
+            .join(format!("{}.json", &key[2..]))
+    }
+
+    /// Loads a file's scan, counting a miss if it isn't there or can't be read.
+    pub fn load(&self, key: &str) -> Option<ScannedFile> {
+        let scanned = fs::read(self.path(key))
+            .ok()
+            .and_then(|contents| serde_json::from_slice(&contents).ok());
+        let counter = if scanned.is_some() {
+            &self.hits
+        } else {
+            &self.misses
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        scanned
+    }
+
+    /// Stores a file's scan, replacing any entry atomically so that concurrent runs can share the cache.
+    pub fn store(&self, key: &str, scanned: &ScannedFile) -> Result<()> {
+        let path = self.path(key);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        atomic::write(&path, serde_json::to_vec(scanned)?)
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+        }
+    }
+}
@@ -0,0 +1,62 @@
+use anyhow::Result;
+use regex::Regex;
+
+/// Calls built into pytest that name fixtures in their string arguments.
+const PYTEST_CALLS: [&str; 3] = ["parametrize", "usefixtures", "getfixturevalue"];
+
+/// Calls of `lazy_loader`, under its usual aliases, that name the attributes a package exports lazily.
+const LAZY_IMPORT_CALLS: [&str; 2] = ["lazy.attach", "lazy_loader.attach"];
+
+/// Matches assignments and additions to a module's `__all__`, whose strings name what it exports.
+const EXPORTS: &str = r"\b__all__\s*(?:\+?=|\.(?:append|extend)\s*\()";
+
+/// Builds a pattern matching the start of any call whose string arguments name functions,
+/// such as `pytest.mark.parametrize(...)`, `lazy_loader.attach(...)` or a configured `register_handler(...)`,
+/// as well as of the `__all__` list.
+pub fn naming_pattern(extra: &[String]) -> Result<Regex> {
+    let names: Vec<String> = PYTEST_CALLS
+        .iter()
+        .chain(&LAZY_IMPORT_CALLS)
+        .map(|&name| name.to_owned())
+        .chain(extra.iter().cloned())
+        .map(|name| regex::escape(&name))
+        .collect();
+    Ok(Regex::new(&format!(
+        r"\b(?:{})\s*\(|{}",
+        names.join("|"),
+        EXPORTS
+    ))?)
+}
+
+/// Tracks calls matching a naming pattern across lines.
+pub struct NamingCalls<'a> {
+    pattern: &'a Regex,
+    depth: usize,
+}
+
+impl<'a> NamingCalls<'a> {
+    pub fn new(pattern: &'a Regex) -> Self {
+        Self { pattern, depth: 0 }
+    }
+
+    /// Whether string literals on the line with the given code are arguments to one of these calls.
+    pub fn observe(&mut self, code: &str) -> bool {
+        let rest = if self.depth > 0 {
+            code
+        } else if let Some(call) = self.pattern.find(code) {
+            &code[call.start()..]
+        } else {
+            return false;
+        };
+
+        for c in rest.chars() {
+            match c {
+                '(' | '[' | '{' => self.depth += 1,
+                ')' | ']' | '}' => self.depth = self.depth.saturating_sub(1),
+                _ => {}
+            }
+        }
+
+        true
+    }
+}
@@ -0,0 +1,97 @@
+use std::{collections::BTreeMap, path::Path};
+
+use anyhow::{anyhow, Result};
+
+/// Checks the failing findings, given by rule and path relative to the root, against the configured caps.
+/// A finding covered by a cap on its rule or on a directory containing it only fails the build once that cap is
+/// exceeded, while the rest fail it as usual.
+pub fn check(
+    by_rule: &BTreeMap<String, usize>,
+    by_path: &BTreeMap<String, usize>,
+    findings: &[(String, &Path)],
+) -> Result<()> {
+    let mut uncovered = 0;
+    for (rule, path) in findings {
+        let covered = by_rule.contains_key(rule)
+            || by_path.keys().any(|directory| path.starts_with(directory));
+        if !covered {
+            uncovered += 1;
+        }
+    }
+
+    let mut exceeded = Vec::new();
+    for (rule, &cap) in by_rule {
+        let count = findings.iter().filter(|(other, _)| other == rule).count();
+        if count > cap {
+            exceeded.push((rule, count, cap));
+        }
+    }
+    for (directory, &cap) in by_path {
+        let count = findings
+            .iter()
+            .filter(|(_, path)| path.starts_with(directory))
+            .count();
+        if count > cap {
+            exceeded.push((directory, count, cap));
+        }
+    }
+
+    for (scope, count, cap) in &exceeded {
+        eprintln!(
+            "{} has {} findings, more than the {} allowed",
+            scope, count, cap
+        );
+    }
+    if uncovered > 0 || !exceeded.is_empty() {
+        Err(anyhow!("possible unused functions were found"))
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn caps(entries: &[(&str, usize)]) -> BTreeMap<String, usize> {
+        entries
+            .iter()
+            .map(|&(scope, cap)| (scope.to_owned(), cap))
+            .collect()
+    }
+
+    #[test]
+    fn covered_findings_only_fail_past_their_cap() {
+        let by_rule = caps(&[("unused-constant", 1)]);
+        let by_path = caps(&[("legacy", 2)]);
+        let finding = |rule: &str, path| (rule.to_owned(), Path::new(path));
+
+        let within = [
+            finding("unused-constant", "app/flags.py"),
+            finding("unused-function", "legacy/a.py"),
+            finding("unused-function", "legacy/old/b.py"),
+        ];
+        assert!(check(&by_rule, &by_path, &within).is_ok());
+
+        let past_rule = [
+            finding("unused-constant", "app/flags.py"),
+            finding("unused-constant", "app/other.py"),
+        ];
+        assert!(check(&by_rule, &by_path, &past_rule).is_err());
+
+        let past_path = [
+            finding("unused-function", "legacy/a.py"),
+            finding("unused-function", "legacy/b.py"),
+            finding("unused-function", "legacy/c.py"),
+        ];
+        assert!(check(&by_rule, &by_path, &past_path).is_err());
+    }
+
+    #[test]
+    fn uncovered_findings_always_fail() {
+        let by_path = caps(&[("legacy", 5)]);
+        let findings = [("unused-function".to_owned(), Path::new("legacy_tools/a.py"))];
+        assert!(check(&BTreeMap::new(), &by_path, &findings).is_err());
+        assert!(check(&BTreeMap::new(), &by_path, &[]).is_ok());
+    }
+}
@@ -0,0 +1,138 @@
+use std::{collections::HashSet, sync::OnceLock};
+
+use anyhow::Result;
+use regex::Regex;
+
+use crate::declarations::LineShape;
+
+/// Compiles the pattern into the cell the first time, so that it is compiled once for all files.
+fn compiled(
+    cell: &'static OnceLock<Result<Regex, regex::Error>>,
+    pattern: &str,
+) -> Result<&'static Regex> {
+    cell.get_or_init(|| Regex::new(pattern))
+        .as_ref()
+        .map_err(|err| err.clone().into())
+}
+
+/// A class whose body is still open.
+struct Class {
+    name: String,
+    /// The zero-based line the class is declared on.
+    line: usize,
+    indent: usize,
+    /// Indentation of the class body, known once its first statement is seen.
+    body_indent: Option<usize>,
+    is_servicer: bool,
+}
+
+/// Tracks the classes enclosing each line of a file, as well as the gRPC servicer bases it imports.
+pub struct Classes {
+    class_pattern: &'static Regex,
+    import_pattern: &'static Regex,
+    open: Vec<Class>,
+    servicer_imports: HashSet<String>,
+}
+
+impl Classes {
+    pub fn new() -> Result<Self> {
+        static CLASS: OnceLock<Result<Regex, regex::Error>> = OnceLock::new();
+        static IMPORT: OnceLock<Result<Regex, regex::Error>> = OnceLock::new();
+        Ok(Self {
+            class_pattern: compiled(&CLASS, r"^\s*class\s+(\w+)\s*(?:\((.*)\))?\s*:")?,
+            import_pattern: compiled(&IMPORT, r"^\s*from\s+\S*_pb2_grpc\s+import\s+(.+)$")?,
+            open: Vec::new(),
+            servicer_imports: HashSet::new(),
+        })
+    }
+
+    /// Feeds the code of the given zero-based line, along with its shape.
+    pub fn observe(&mut self, code: &str, shape: LineShape, line: usize) {
+        let indent = match shape {
+            LineShape::Indented(indent) => indent,
+            _ => return,
+        };
+
+        while self
+            .open
+            .last()
+            .map(|class| class.indent >= indent)
+            .unwrap_or(false)
+        {
+            self.open.pop();
+        }
+        if let Some(class) = self.open.last_mut() {
+            class.body_indent.get_or_insert(indent);
+        }
+
+        if let Some(names) = self.import_pattern.captures(code).and_then(|c| c.get(1)) {
+            for name in names.as_str().split(',') {
+                let mut parts = name.trim_matches(|c| "() ".contains(c)).split_whitespace();
+                if let Some(original) = parts.next().filter(|name| name.ends_with("Servicer")) {
+                    let alias = parts.nth(1).unwrap_or(original);
+                    self.servicer_imports.insert(alias.to_owned());
+                }
+            }
+        }
+
+        if let Some(captures) = self.class_pattern.captures(code) {
+            let is_servicer = captures
+                .get(2)
+                .map(|bases| bases.as_str())
+                .unwrap_or_default()
+                .split(',')
+                .map(str::trim)
+                .any(|base| self.is_servicer_base(base));
+            self.open.push(Class {
+                name: captures[1].to_owned(),
+                line,
+                indent,
+                body_indent: None,
+                is_servicer,
+            });
+        }
+    }
+
+    /// The name of the class declared on the given zero-based line, once it has been observed.
+    pub fn declared(&self, line: usize) -> Option<&str> {
+        self.open
+            .last()
+            .filter(|class| class.line == line)
+            .map(|class| class.name.as_str())
+    }
+
+    /// Whether a definition at the given indentation is a method directly inside a class.
+    /// Observe the definition's line first.
+    pub fn is_method(&self, indent: usize) -> bool {
+        self.open
+            .last()
+            .map(|class| class.body_indent == Some(indent))
+            .unwrap_or(false)
+    }
+
+    /// Whether a definition at the given indentation is a method directly inside a gRPC servicer.
+    pub fn is_servicer_method(&self, indent: usize) -> bool {
+        self.is_method(indent)
+            && self
+                .open
+                .last()
+                .map(|class| class.is_servicer)
+                .unwrap_or(false)
+    }
+
+    /// The name and line of the class that a definition at the given indentation is a method of.
+    pub fn enclosing(&self, indent: usize) -> Option<(&str, usize)> {
+        self.open
+            .last()
+            .filter(|_| self.is_method(indent))
+            .map(|class| (class.name.as_str(), class.line))
+    }
+
+    /// Servicer bases are generated into `*_pb2_grpc` modules and named `*Servicer`.
+    fn is_servicer_base(&self, base: &str) -> bool {
+        match base.rsplit_once('.') {
+            Some((module, name)) => module.ends_with("_pb2_grpc") && name.ends_with("Servicer"),
+            None => self.servicer_imports.contains(base),
+        }
+    }
+}
@@ -0,0 +1,1254 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    ffi::OsStr,
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::{Component, Path, PathBuf, Prefix},
+    time::Instant,
+};
+
+use anyhow::{anyhow, Context, Result};
+use clap::{ArgEnum, Parser, Subcommand};
+use serde::Serialize;
+
+use crate::{
+    archaeology, atomic,
+    baseline::{self, Baseline, Suppression},
+    cache::Cache,
+    caps, config,
+    count::Mentions,
+    docstrings, doctor, dry_run,
+    export::{self, ExportFormat},
+    graph::ReferenceGraph,
+    ignore::IgnoreList,
+    load_config,
+    metadata::Metadata,
+    metrics::Metrics,
+    owners::{self, Owners},
+    path_map::{self, PathMap},
+    ratchet, rename, sample, sarif, scan_options, selfcheck,
+    stats::Stats,
+    suppressions,
+    telemetry::Tracer,
+    vcs,
+    walk::{self, count_skipped, Skipped},
+    BuildSystem, Category, DeclarationKind, Finding, Framework, Function, Language, Mode, Options,
+    Preset, Tree, PYTHON_EXTENSIONS,
+};
+
+/// Find potentially unused functions in a python source tree.
+#[derive(Parser)]
+#[clap(author, version, about, subcommand_negates_reqs = true)]
+pub struct Args {
+    #[clap(validator = path_exists, required = true)]
+    pub path: Option<PathBuf>,
+
+    /// Only report functions defined under this path, while still counting references across the whole tree.
+    #[clap(long, validator = path_exists)]
+    pub report_scope: Option<PathBuf>,
+
+    /// Only report functions defined in files changed since this revision, including uncommitted and new
+    /// files, such as the branch point of a pull request.
+    #[clap(long, value_name = "REVISION")]
+    pub since: Option<String>,
+
+    /// Scan and count files matching this gitignore-style pattern as usual, but don't report findings in them.
+    #[clap(long = "quiet-path")]
+    pub quiet_paths: Vec<String>,
+
+    /// Leave files and directories matching this gitignore-style pattern out of the scan entirely.
+    #[clap(long)]
+    pub exclude: Vec<String>,
+
+    /// Leave out whatever `.gitignore` files ignore, too.
+    #[clap(long)]
+    pub respect_gitignore: bool,
+
+    /// Never flag functions whose names match this regex anywhere, on top of tests and dunder methods.
+    #[clap(long)]
+    pub allow: Vec<String>,
+
+    /// Also scan this directory for mentions, without reporting what it declares, such as a virtualenv's
+    /// site-packages or plugins checked out elsewhere. Its mentions are third-party. Give more than once for
+    /// several.
+    #[clap(long, value_name = "DIR", validator = path_exists)]
+    pub search_root: Vec<PathBuf>,
+
+    /// Reuse the scans of unchanged files from this directory, and store new ones there. Entries are keyed by
+    /// content, so a directory restored from a CI cache is safe to share between branches.
+    #[clap(long, value_name = "DIR")]
+    pub cache_dir: Option<PathBuf>,
+
+    /// Read configuration from this file instead of looking for snapbug.toml in the scanned root.
+    /// An http or https URL is fetched, so that many repositories can share one configuration.
+    #[clap(long, validator = config_exists)]
+    pub config: Option<PathBuf>,
+
+    /// Ignore mentions inside comments and string literals when counting references.
+    #[clap(long)]
+    pub strip_comments: bool,
+
+    /// Also count references in shell scripts, makefiles, dockerfiles and CI configuration.
+    #[clap(long)]
+    pub scan_scripts: bool,
+
+    /// Estimate the cyclomatic complexity of each flagged function.
+    #[clap(long)]
+    pub complexity: bool,
+
+    /// The sorts of declaration to check, such as functions,classes.
+    #[clap(
+        long,
+        arg_enum,
+        use_value_delimiter = true,
+        default_value = "functions,methods"
+    )]
+    pub kinds: Vec<DeclarationKind>,
+
+    /// Also flag module-level constants that are never referenced, such as orphaned feature flags.
+    /// The same as adding constants to --kinds.
+    #[clap(long)]
+    pub constants: bool,
+
+    /// Also flag functions whose bodies are copied in other files, which often explains why the original looks unused.
+    #[clap(long)]
+    pub duplicates: bool,
+
+    /// Don't count mentions from modules that are never imported, or only by other such modules.
+    /// Suits applications better than libraries, whose modules are imported by their users.
+    #[clap(long)]
+    pub dead_modules: bool,
+
+    /// Don't count mentions from test files for declarations outside them, flagging what only tests use.
+    /// Test files are those under `tests` or `test` directories, named like tests, or importing pytest or
+    /// unittest.
+    #[clap(long)]
+    pub test_only: bool,
+
+    /// Don't count mentions under a file's `if __name__ == "__main__":` guard for its own declarations,
+    /// flagging those only its script uses as script-only. Suits libraries, whose scripts aren't real users.
+    #[clap(long)]
+    pub script_only: bool,
+
+    /// Only count mentions in files that could see a python declaration: its importers, the modules in its
+    /// directory, files in other languages and the configured extra roots.
+    #[clap(long)]
+    pub scoped_counting: bool,
+
+    /// Only count mentions in files whose build target is, or depends on, a declaration's target, as told
+    /// by the build system. Files outside python targets count everywhere.
+    #[clap(
+        long,
+        arg_enum,
+        value_name = "SYSTEM",
+        conflicts_with = "scoped-counting"
+    )]
+    pub build_graph: Option<BuildSystem>,
+
+    /// Only scan a deterministic sample of the files, such as 10%, for quick estimates on huge trees.
+    /// Functions used only by files outside the sample are flagged too.
+    #[clap(long, value_name = "PERCENT", parse(try_from_str = sample::parse_percent))]
+    pub sample: Option<f64>,
+
+    /// Walk the tree and print how many files and declarations each directory would contribute, without
+    /// scanning them, to check exclusions quickly on huge trees.
+    #[clap(long)]
+    pub dry_run: bool,
+
+    /// Also flag names in `__all__` that their module never defines, and public functions and classes
+    /// missing from it.
+    #[clap(long)]
+    pub exports: bool,
+
+    /// List every entry left out of the scan and why, such as hidden, excluded, binary or too large files.
+    #[clap(short, long)]
+    pub verbose: bool,
+
+    /// Read code owners from this file instead of looking for a CODEOWNERS file.
+    #[clap(long, validator = path_exists)]
+    pub owners_file: Option<PathBuf>,
+
+    /// Add who last changed the line declaring each finding, and when, to machine-readable reports.
+    #[clap(long)]
+    pub blame: bool,
+
+    /// Languages to scan, each reporting findings under its own rules.
+    #[clap(
+        long = "lang",
+        arg_enum,
+        use_value_delimiter = true,
+        default_value = "python"
+    )]
+    pub languages: Vec<Language>,
+
+    /// Treat functions that the given frameworks invoke themselves as used.
+    #[clap(long = "framework", arg_enum)]
+    pub frameworks: Vec<Framework>,
+
+    /// Apply a bundle of exemptions suited to a kind of project, on top of the configuration.
+    #[clap(long = "preset", arg_enum)]
+    pub presets: Vec<Preset>,
+
+    /// Suppress findings listed in this baseline file until their entries expire.
+    #[clap(long, validator = path_exists)]
+    pub baseline: Option<PathBuf>,
+
+    /// Write every current finding to this baseline file, replacing its entries, so that only new findings fail
+    /// later runs given it with --baseline. Reasons and expiry dates of entries that are kept are preserved.
+    #[clap(long)]
+    pub write_baseline: Option<PathBuf>,
+
+    /// Skip findings listed in this file as `path:line:name`, one per line, as they appear in the text report.
+    #[clap(long, validator = path_exists)]
+    pub ignore_file: Option<PathBuf>,
+
+    /// Only fail if there are more findings than recorded in this state file, which is updated as the count drops.
+    #[clap(long)]
+    pub ratchet: Option<PathBuf>,
+
+    /// Write Prometheus gauges describing the scan to this file.
+    #[clap(long)]
+    pub metrics_textfile: Option<PathBuf>,
+
+    /// Send spans for each phase of the scan to this OTLP/HTTP collector, e.g. http://localhost:4318.
+    #[clap(long)]
+    pub otlp_endpoint: Option<String>,
+
+    /// Suggest an order for deleting flagged functions, including those only they reference.
+    #[clap(long)]
+    pub deletion_plan: bool,
+
+    /// How to decide which functions are dead.
+    #[clap(long, arg_enum, default_value = "mentions")]
+    pub mode: Mode,
+
+    /// Group findings in the report.
+    #[clap(long, arg_enum)]
+    pub group_by: Option<GroupBy>,
+
+    /// Rewrite the start of reported paths, such as `/app=/home/dev/project` for reports made in a container.
+    /// Give more than once to map several prefixes; the first that matches is used.
+    #[clap(long, value_name = "FROM=TO", parse(try_from_str = path_map::parse_mapping))]
+    pub path_map: Vec<(String, String)>,
+
+    /// How to print the report. Give more than once to produce several reports from one scan: text always goes to
+    /// the terminal, while machine-readable formats go to the `--output` files in the same order, or to stdout.
+    #[clap(
+        long = "output-format",
+        alias = "format",
+        arg_enum,
+        default_value = "text"
+    )]
+    pub output_formats: Vec<OutputFormat>,
+
+    /// Write a machine-readable report to this file instead of stdout. Give once per machine-readable format.
+    #[clap(long)]
+    pub output: Vec<PathBuf>,
+
+    #[clap(subcommand)]
+    pub command: Option<Command>,
+}
+
+impl Args {
+    /// The flags that affect what is found, for sharing with the library API.
+    pub fn options(&self) -> Options {
+        Options {
+            config: self.config.clone(),
+            strip_comments: self.strip_comments,
+            scan_scripts: self.scan_scripts,
+            complexity: self.complexity,
+            kinds: self.kinds.clone(),
+            constants: self.constants,
+            duplicates: self.duplicates,
+            dead_modules: self.dead_modules,
+            test_only: self.test_only,
+            script_only: self.script_only,
+            scoped_counting: self.scoped_counting,
+            build_graph: self.build_graph,
+            exports: self.exports,
+            languages: self.languages.clone(),
+            frameworks: self.frameworks.clone(),
+            presets: self.presets.clone(),
+            exclude: self.exclude.clone(),
+            respect_gitignore: self.respect_gitignore,
+            allow: self.allow.clone(),
+            search_roots: self.search_root.clone(),
+            cache_dir: self.cache_dir.clone(),
+            mode: self.mode,
+        }
+    }
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Search git or Mercurial history for the last commit in which a function was referenced.
+    Archaeology {
+        /// The name of the function to look for.
+        function: String,
+
+        /// The repository to search.
+        #[clap(default_value = ".", validator = path_exists)]
+        path: PathBuf,
+    },
+    /// Add findings to a baseline file so that they no longer fail the scan.
+    Suppress {
+        /// Fingerprints of the findings, as found in the JSON report, e.g. `pkg/mod.py::helper`.
+        #[clap(required = true)]
+        fingerprints: Vec<String>,
+
+        /// The baseline file to append to, which is created if it doesn't exist.
+        #[clap(long)]
+        baseline: PathBuf,
+
+        /// Why the findings are being accepted.
+        #[clap(long)]
+        reason: Option<String>,
+
+        /// When the suppressions stop applying, as a date such as 2025-06-30 or a duration such as 90d.
+        #[clap(long)]
+        expires: Option<String>,
+    },
+    /// Check the environment and the tree for problems before running a full scan.
+    Doctor {
+        /// The source tree that would be scanned.
+        #[clap(default_value = ".", validator = path_exists)]
+        path: PathBuf,
+    },
+    /// Dump how often each definition is mentioned by each file, for analysis elsewhere.
+    Export {
+        /// The format to write.
+        #[clap(long, arg_enum, default_value = "csv")]
+        format: ExportFormat,
+
+        /// Write to this file instead of stdout. Required for parquet.
+        #[clap(long, short)]
+        output: Option<PathBuf>,
+
+        /// The source tree to scan.
+        #[clap(default_value = ".", validator = path_exists)]
+        path: PathBuf,
+    },
+    /// List every occurrence of a name, grouped by how it is used, before renaming or removing it.
+    RenameCheck {
+        /// The name to look for.
+        name: String,
+
+        /// The source tree to search.
+        #[clap(default_value = ".", validator = path_exists)]
+        path: PathBuf,
+    },
+    /// List the entries of a baseline and ignore file, failing if any no longer match a definition.
+    Suppressions {
+        /// Only list the stale entries, which can be deleted.
+        #[clap(long)]
+        unused: bool,
+
+        /// The baseline file to audit, instead of the one given before the subcommand.
+        #[clap(long, validator = path_exists)]
+        baseline: Option<PathBuf>,
+
+        /// The ignore file to audit, instead of the one given before the subcommand.
+        #[clap(long, validator = path_exists)]
+        ignore_file: Option<PathBuf>,
+
+        /// The source tree the suppressions apply to.
+        #[clap(default_value = ".", validator = path_exists)]
+        path: PathBuf,
+    },
+    /// Scan a synthetic tree to check the install, and compare the throughput against a baseline.
+    Selfcheck {
+        /// A performance baseline from an earlier run, which fails the check if throughput has halved.
+        #[clap(long)]
+        baseline: Option<PathBuf>,
+
+        /// Write this run's measurement to the baseline instead of comparing against it.
+        #[clap(long, requires = "baseline")]
+        save: bool,
+    },
+}
+
+/// How the report is printed.
+#[derive(ArgEnum, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum OutputFormat {
+    /// Human-readable lines on stderr.
+    #[clap(alias = "human")]
+    Text,
+    /// A JSON document on stdout.
+    Json,
+    /// A SARIF 2.1.0 log on stdout, for code scanning services.
+    Sarif,
+    /// One JSON finding per line on stdout, flushed as it goes, so that an interrupted run still leaves
+    /// whole lines.
+    Jsonl,
+}
+
+/// How findings are grouped in the report.
+#[derive(ArgEnum, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum GroupBy {
+    /// Group findings by their code owners.
+    Owner,
+    /// Group findings by the top-level package or service they are in.
+    Package,
+}
+
+/// The JSON report, printed to stdout.
+#[derive(Serialize)]
+struct JsonReport<'a> {
+    metadata: &'a Metadata,
+    stats: &'a Stats,
+    /// Lines of potentially dead code, not counting nested functions twice.
+    dead_lines: usize,
+    findings: &'a [JsonFinding<'a>],
+    /// Findings in modules that look up functions dynamically, which never affect the exit code.
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    quarantined: &'a [JsonFinding<'a>],
+    deletion_plan: Vec<JsonLocation<'a>>,
+    /// Every entry left out of the scan, with why.
+    skipped: &'a [JsonSkipped],
+}
+
+/// A line of the JSONL report.
+#[derive(Serialize)]
+struct JsonLine<'a> {
+    #[serde(flatten)]
+    finding: &'a JsonFinding<'a>,
+    /// Whether the finding is in a module that looks up functions dynamically.
+    quarantined: bool,
+}
+
+#[derive(Serialize)]
+struct JsonSkipped {
+    /// The path relative to the scanned root, with forward slashes.
+    path: String,
+    reason: &'static str,
+}
+
+#[derive(Serialize)]
+pub struct JsonFinding<'a> {
+    /// Identifies the finding for `snapbug suppress`.
+    pub fingerprint: String,
+    pub name: &'a str,
+    pub kind: &'static str,
+    pub path: String,
+    /// The path relative to the scanned root, with forward slashes, unless the path map rewrites it.
+    #[serde(skip)]
+    pub relative: String,
+    pub line: usize,
+    pub rule: String,
+    pub severity: &'static str,
+    /// What to do about the finding, such as `delete` or `add-suppression`, for bots that open fixes.
+    pub suggestion: &'static str,
+    /// A sentence describing the finding, as in the text report.
+    #[serde(skip)]
+    pub message: String,
+    pub lines: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mentions: Option<Mentions>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub complexity: Option<usize>,
+    pub owners: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub suppression_expired: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub redefinitions: Vec<JsonLocation<'a>>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub copies: Vec<JsonLocation<'a>>,
+    /// The first line of the defining module's docstring.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub module_summary: Option<String>,
+    /// Who last changed the declaring line, with `--blame`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub blame: Option<JsonBlame>,
+}
+
+#[derive(Serialize)]
+pub struct JsonBlame {
+    author: String,
+    /// The day of the change, such as 2024-01-31.
+    date: String,
+}
+
+#[derive(Serialize)]
+pub struct JsonLocation<'a> {
+    name: &'a str,
+    path: String,
+    line: usize,
+}
+
+impl<'a> JsonLocation<'a> {
+    /// Locates a function relative to the path given on the command line.
+    fn new(function: &'a Function, root: &Path, path: &Path, path_map: &PathMap) -> Result<Self> {
+        Ok(Self {
+            name: &function.name,
+            path: reported_path(path, function.location.0.strip_prefix(root)?, path_map),
+            line: function.location.1,
+        })
+    }
+}
+
+/// Where a file is reported: the path given on the command line joined with the file's path relative to
+/// the root, rewritten by the path map.
+fn reported_path(path: &Path, relative: &Path, path_map: &PathMap) -> String {
+    path_map.apply(&path.join(relative).display().to_string())
+}
+
+/// Validates that a path exists.
+fn path_exists(s: &str) -> Result<()> {
+    let path = PathBuf::from(s);
+
+    if !path.exists() {
+        return Err(anyhow!("path does not exist"));
+    }
+
+    Ok(())
+}
+
+fn config_exists(s: &str) -> Result<()> {
+    if config::is_url(s) {
+        Ok(())
+    } else {
+        path_exists(s)
+    }
+}
+
+/// Sums the lines of the given functions, skipping any nested inside another.
+/// Expects the functions to be sorted by location.
+fn dead_weight<'a>(functions: impl Iterator<Item = &'a Function>) -> usize {
+    let mut weight = 0;
+    let mut covered: Option<(&Path, usize)> = None;
+
+    for function in functions {
+        let (path, line) = (function.location.0.as_path(), function.location.1);
+        if let Some((covered_path, covered_until)) = covered {
+            if covered_path == path && line <= covered_until {
+                continue;
+            }
+        }
+        weight += function.lines;
+        covered = Some((path, line + function.lines - 1));
+    }
+
+    weight
+}
+
+/// Drops the `\\?\` prefix that `canonicalize` adds on windows, which confuses people and tools such as git,
+/// wherever the path means the same without it. A UNC path such as `\\?\UNC\server\share` becomes `\\server\share`.
+fn simplify(path: &Path) -> PathBuf {
+    let mut components = path.components();
+    let prefix = match components.next() {
+        Some(Component::Prefix(prefix)) => match prefix.kind() {
+            Prefix::VerbatimDisk(disk) => format!("{}:", disk as char),
+            Prefix::VerbatimUNC(server, share) => format!(
+                r"\\{}\{}",
+                server.to_string_lossy(),
+                share.to_string_lossy()
+            ),
+            _ => return path.to_owned(),
+        },
+        _ => return path.to_owned(),
+    };
+    PathBuf::from(format!("{}{}", prefix, components.as_path().display()))
+}
+
+/// The first path component under the root, or "." for files directly inside it. A `src` directory is
+/// skipped when it holds packages, as in the src layout.
+fn package_of(relative: &Path) -> String {
+    let mut components = relative
+        .parent()
+        .into_iter()
+        .flat_map(Path::components)
+        .map(|component| component.as_os_str().to_string_lossy());
+    match (components.next(), components.next()) {
+        (Some(first), Some(second)) if first == "src" => second.into_owned(),
+        (Some(first), _) => first.into_owned(),
+        (None, _) => ".".to_owned(),
+    }
+}
+
+/// Writes a machine-readable report to the given file, or to stdout if there are fewer files than formats.
+/// The file is replaced atomically, so that it is never left truncated.
+fn write_report(output: Option<&PathBuf>, report: String) -> Result<()> {
+    match output {
+        Some(output) => atomic::write(output, report + "\n")
+            .with_context(|| format!("failed to write report {}", output.display())),
+        None => {
+            println!("{}", report);
+            Ok(())
+        }
+    }
+}
+
+/// How many lines of a JSONL report are buffered before they are flushed.
+const JSONL_FLUSH_INTERVAL: usize = 64;
+
+/// Writes one finding per line to the given file, or to stdout, flushing every few lines. Unlike the other
+/// reports this is written in place, so that a crash leaves every line written before it.
+fn write_lines(
+    output: Option<&PathBuf>,
+    findings: &[JsonFinding],
+    quarantined: &[JsonFinding],
+) -> Result<()> {
+    let writer: Box<dyn Write> = match output {
+        Some(output) => Box::new(
+            File::create(output)
+                .with_context(|| format!("failed to write report {}", output.display()))?,
+        ),
+        None => Box::new(io::stdout().lock()),
+    };
+    let mut writer = BufWriter::new(writer);
+    let lines = findings
+        .iter()
+        .map(|finding| (finding, false))
+        .chain(quarantined.iter().map(|finding| (finding, true)));
+    for (index, (finding, quarantined)) in lines.enumerate() {
+        serde_json::to_writer(
+            &mut writer,
+            &JsonLine {
+                finding,
+                quarantined,
+            },
+        )?;
+        writeln!(writer)?;
+        if (index + 1) % JSONL_FLUSH_INTERVAL == 0 {
+            writer.flush()?;
+        }
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Find potentially unused functions in the given python source tree.
+fn scan(args: &Args, path: &Path) -> Result<()> {
+    let root = path.canonicalize()?;
+
+    let machine_formats = args
+        .output_formats
+        .iter()
+        .filter(|&&format| format != OutputFormat::Text)
+        .count();
+    if args.output.len() > machine_formats {
+        return Err(anyhow!(
+            "--output was given more often than machine-readable formats"
+        ));
+    }
+
+    let owners = match args.owners_file.clone().or_else(|| Owners::find(&root)) {
+        Some(path) => Some(Owners::load(&path)?),
+        None => None,
+    };
+    if args.group_by == Some(GroupBy::Owner) && owners.is_none() {
+        return Err(anyhow!("no CODEOWNERS file was found to group by"));
+    }
+
+    let baseline = match &args.baseline {
+        Some(path) => Baseline::load(path)?,
+        None => Baseline::default(),
+    };
+    let ignored = match &args.ignore_file {
+        Some(ignore_file) => IgnoreList::load(ignore_file, path)?,
+        None => IgnoreList::default(),
+    };
+
+    let config = load_config(args.config.as_deref(), &root)?;
+    let escalate_after = config.escalate_after()?;
+    let options = scan_options(&args.options(), &config)?;
+
+    let started = Instant::now();
+    let mut tracer = Tracer::new();
+
+    let span = Tracer::start("walk");
+    let (mut files, mut skipped) = walk::files(&root, &options);
+    if let Some(percent) = args.sample {
+        let (sampled, unsampled) = sample::select(files, &root, percent);
+        files = sampled;
+        skipped.extend(
+            unsampled
+                .iter()
+                .map(|entry| Skipped::new(entry.path(), "unsampled")),
+        );
+    }
+    tracer.end(span, &[("files", files.len())]);
+    if args.dry_run {
+        return dry_run::report(&files, &root, &options, &count_skipped(&skipped));
+    }
+
+    let span = Tracer::start("scan");
+    let mut tree = Tree::scan(&root, files, &options)?;
+    skipped.append(&mut tree.declared.skipped);
+    let files = &tree.files;
+    let declared = &tree.declared;
+    let functions = &declared.functions;
+    tracer.end(
+        span,
+        &[("files", files.len()), ("functions", functions.len())],
+    );
+    let path_map = PathMap::new(args.path_map.clone());
+    let metadata = Metadata::new(
+        path_map.apply(&simplify(&root).display().to_string()),
+        config.hash.clone(),
+        files.len(),
+        args.sample,
+    )?;
+
+    let span = Tracer::start("count");
+    let (mut unused_functions, graph) = tree.findings(&options)?;
+    tracer.end(
+        span,
+        &[
+            ("functions", functions.len()),
+            ("findings", unused_functions.len()),
+        ],
+    );
+
+    if let Some(scope) = &args.report_scope {
+        let scope = scope.canonicalize()?;
+        unused_functions.retain(|finding| finding.function.location.0.starts_with(&scope));
+    }
+    if let Some(since) = &args.since {
+        let vcs = vcs::detect(&simplify(&root));
+        let toplevel = PathBuf::from(vcs.root()?);
+        let changed: HashSet<PathBuf> = vcs
+            .changed_files(since)?
+            .into_iter()
+            .map(|path| toplevel.join(path))
+            .collect();
+        unused_functions
+            .retain(|finding| changed.contains(&simplify(&finding.function.location.0)));
+    }
+    if !args.quiet_paths.is_empty() {
+        let patterns = args
+            .quiet_paths
+            .iter()
+            .map(|pattern| owners::pattern_to_regex(pattern))
+            .collect::<Result<Vec<_>>>()?;
+        unused_functions.retain(|finding| {
+            let relative = finding
+                .function
+                .location
+                .0
+                .strip_prefix(&root)
+                .unwrap_or(&root);
+            let relative = baseline::normalize(relative);
+            !patterns.iter().any(|pattern| pattern.is_match(&relative))
+        });
+    }
+
+    let skipped_during_scan = skipped
+        .iter()
+        .filter(|entry| ["binary", "undecodable"].contains(&entry.reason))
+        .count();
+    let mut stats = Stats {
+        files: files.len() - skipped_during_scan,
+        skipped: count_skipped(&skipped),
+        cache: declared.cache.as_ref().map(Cache::stats),
+        ..Default::default()
+    };
+    let skipped: Vec<JsonSkipped> = skipped
+        .iter()
+        .map(|entry| JsonSkipped {
+            path: baseline::normalize(entry.path.strip_prefix(&root).unwrap_or(&entry.path)),
+            reason: entry.reason,
+        })
+        .collect();
+    if args.verbose {
+        for entry in &skipped {
+            eprintln!("skipped {} ({})", entry.path, entry.reason);
+        }
+    }
+    if let Some(cache) = stats.cache {
+        eprintln!(
+            "cache: reused {} of {} scanned files",
+            cache.hits,
+            cache.hits + cache.misses
+        );
+    }
+    for function in functions.iter().filter(|function| !function.root) {
+        *stats.definitions.entry(function.kind.name()).or_default() += 1;
+    }
+
+    // the new baseline covers everything found, so that this run passes and only later findings fail.
+    let baseline = match &args.write_baseline {
+        Some(path) => {
+            let fingerprints = unused_functions
+                .iter()
+                .map(|finding| {
+                    let function = finding.function;
+                    let relative = function.location.0.strip_prefix(&root)?;
+                    Ok(baseline::fingerprint(relative, &function.name))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            baseline::write(path, &fingerprints)?;
+            Baseline::load(path)?
+        }
+        None => baseline,
+    };
+
+    let mut expired = HashMap::new();
+    unused_functions.retain(|finding| {
+        let function = finding.function;
+        let relative = function.location.0.strip_prefix(&root).unwrap_or(&root);
+        if ignored.contains(relative, function.location.1, &function.name) {
+            stats.suppressed += 1;
+            return false;
+        }
+        match baseline.lookup(relative, &function.name) {
+            Suppression::None => true,
+            Suppression::Active => {
+                stats.suppressed += 1;
+                false
+            }
+            Suppression::Expired(entry) => {
+                expired.insert(function, entry);
+                true
+            }
+        }
+    });
+
+    // findings in dynamic modules are reported separately, and never fail the build.
+    let (quarantined, unused_functions): (Vec<Finding>, Vec<Finding>) = unused_functions
+        .into_iter()
+        .partition(|finding| finding.function.dynamic);
+    stats.quarantined = quarantined.len();
+
+    // with an escalation window, findings only fail the build once unreferenced for longer than it.
+    let mut escalated = HashMap::new();
+    if let Some(window) = escalate_after {
+        let vcs = vcs::detect(&simplify(&root));
+        let names: Vec<&str> = unused_functions
+            .iter()
+            .map(|finding| finding.function.name.as_str())
+            .collect();
+        // for a flagged function, this is when its last reference was removed, or when it was added.
+        let changed = vcs
+            .last_changed(&names)
+            .context("failed to read history to escalate findings")?;
+        for finding in &unused_functions {
+            if let Some(&since) = changed.get(&finding.function.name) {
+                let age = metadata.unix_timestamp.saturating_sub(since).max(0) as u64;
+                if age > window.as_secs() {
+                    escalated.insert(finding.function, age / (24 * 60 * 60));
+                }
+            }
+        }
+    }
+    let severity = |function: &Function| {
+        if escalate_after.is_none() || escalated.contains_key(function) {
+            "error"
+        } else {
+            "warning"
+        }
+    };
+    let errors = unused_functions
+        .iter()
+        .filter(|finding| severity(finding.function) == "error")
+        .map(|finding| {
+            let relative = finding.function.location.0.strip_prefix(&root)?;
+            Ok((finding.rule(), relative))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let finding_count = unused_functions.len();
+    let span = Tracer::start("report");
+
+    for category in Category::ALL {
+        for language in &args.languages {
+            let rule = format!("{}{}", language.rule_prefix(), category.rule());
+            stats.findings_by_rule.insert(rule, 0);
+        }
+    }
+    for finding in &unused_functions {
+        *stats.findings_by_rule.entry(finding.rule()).or_default() += 1;
+    }
+
+    if let Some(path) = &args.metrics_textfile {
+        let mut metrics = Metrics {
+            findings: finding_count,
+            by_rule: stats.findings_by_rule.clone(),
+            duration: started.elapsed(),
+            ..Default::default()
+        };
+        for finding in &unused_functions {
+            let relative = finding.function.location.0.strip_prefix(&root)?;
+            *metrics.by_package.entry(package_of(relative)).or_default() += 1;
+        }
+        metrics.write_textfile(path, &metadata)?;
+    }
+    let mut dead: Vec<&Function> = unused_functions
+        .iter()
+        .filter(|finding| finding.category.is_dead_code())
+        .flat_map(Finding::definitions)
+        .collect();
+    dead.sort_by_key(|function| function.sort_key());
+    let weight = dead_weight(dead.iter().copied());
+
+    let deletion_plan = if args.deletion_plan && finding_count > 0 {
+        let graph = match graph {
+            Some(graph) => graph,
+            None => ReferenceGraph::build(&declared.haystack, functions)?,
+        };
+        graph.deletion_plan(&dead)
+    } else {
+        Vec::new()
+    };
+
+    // the structured findings shared by the machine-readable formats.
+    let (records, quarantined_records) = if machine_formats > 0 {
+        let mut summaries = HashMap::new();
+        for finding in unused_functions.iter().chain(&quarantined) {
+            let path = finding.function.location.0.as_path();
+            if !summaries.contains_key(path) {
+                let summary = match path.extension().and_then(OsStr::to_str) {
+                    Some(extension) if PYTHON_EXTENSIONS.contains(&extension) => {
+                        docstrings::summary(path)?
+                    }
+                    _ => None,
+                };
+                summaries.insert(path, summary);
+            }
+        }
+
+        let mut blames = HashMap::new();
+        if args.blame {
+            let vcs = vcs::detect(&simplify(&root));
+            for finding in unused_functions.iter().chain(&quarantined) {
+                let (path, line) = &finding.function.location;
+                match vcs.blame(&simplify(path), *line) {
+                    Ok(Some(blame)) => {
+                        blames.insert(finding.function, blame);
+                    }
+                    Ok(None) => {}
+                    Err(err) => {
+                        eprintln!("warning: findings were not blamed: {:#}", err);
+                        break;
+                    }
+                }
+            }
+        }
+
+        let mut records = unused_functions
+            .iter()
+            .chain(&quarantined)
+            .map(
+                |finding @ Finding {
+                     function,
+                     redefinitions,
+                     copies,
+                     ..
+                 }| {
+                    let relative = function.location.0.strip_prefix(&root)?;
+                    Ok(JsonFinding {
+                        fingerprint: baseline::fingerprint(relative, &function.name),
+                        name: &function.name,
+                        kind: function.kind.name(),
+                        path: reported_path(path, relative, &path_map),
+                        relative: path_map
+                            .rewrite(&path.join(relative).display().to_string())
+                            .map(|mapped| mapped.replace('\\', "/"))
+                            .unwrap_or_else(|| baseline::normalize(relative)),
+                        line: function.location.1,
+                        rule: finding.rule(),
+                        severity: severity(function),
+                        suggestion: if function.dynamic {
+                            "add-suppression"
+                        } else {
+                            finding.category.suggestion()
+                        },
+                        message: finding.message(),
+                        lines: function.lines,
+                        mentions: finding.mentions,
+                        complexity: function.complexity,
+                        owners: owners
+                            .as_ref()
+                            .map(|owners| owners.owners_of(relative).to_vec())
+                            .unwrap_or_default(),
+                        suppression_expired: expired
+                            .get(function)
+                            .and_then(|entry| entry.expires)
+                            .map(|expires| expires.to_string()),
+                        redefinitions: redefinitions
+                            .iter()
+                            .map(|function| JsonLocation::new(function, &root, path, &path_map))
+                            .collect::<Result<Vec<_>>>()?,
+                        copies: copies
+                            .iter()
+                            .map(|function| JsonLocation::new(function, &root, path, &path_map))
+                            .collect::<Result<Vec<_>>>()?,
+                        module_summary: summaries[function.location.0.as_path()].clone(),
+                        blame: blames.get(function).map(|blame| JsonBlame {
+                            author: blame.author.clone(),
+                            date: time::OffsetDateTime::from_unix_timestamp(blame.timestamp)
+                                .map(|date| date.date().to_string())
+                                .unwrap_or_default(),
+                        }),
+                    })
+                },
+            )
+            .collect::<Result<Vec<_>>>()?;
+        let quarantined_records = records.split_off(unused_functions.len());
+        (records, quarantined_records)
+    } else {
+        Default::default()
+    };
+
+    let mut outputs = args.output.iter();
+    for format in &args.output_formats {
+        match format {
+            OutputFormat::Json => {
+                let plan = deletion_plan
+                    .iter()
+                    .map(|function| JsonLocation::new(function, &root, path, &path_map))
+                    .collect::<Result<Vec<_>>>()?;
+
+                let report = JsonReport {
+                    metadata: &metadata,
+                    stats: &stats,
+                    dead_lines: weight,
+                    findings: &records,
+                    quarantined: &quarantined_records,
+                    deletion_plan: plan,
+                    skipped: &skipped,
+                };
+                write_report(outputs.next(), serde_json::to_string_pretty(&report)?)?;
+            }
+            OutputFormat::Sarif => {
+                let rules = args
+                    .languages
+                    .iter()
+                    .flat_map(|language| {
+                        Category::ALL.iter().map(move |category| {
+                            (
+                                format!("{}{}", language.rule_prefix(), category.rule()),
+                                format!("A declaration {}", category.verdict()),
+                            )
+                        })
+                    })
+                    .collect();
+                let log = sarif::Log::new(rules, &records, &quarantined_records);
+                write_report(outputs.next(), serde_json::to_string_pretty(&log)?)?;
+            }
+            OutputFormat::Jsonl => write_lines(outputs.next(), &records, &quarantined_records)?,
+            OutputFormat::Text => {
+                let mut groups: BTreeMap<String, Vec<String>> = BTreeMap::new();
+                let mut quarantined_descriptions = Vec::new();
+                for (
+                    index,
+                    Finding {
+                        function,
+                        category,
+                        redefinitions,
+                        copies,
+                        ..
+                    },
+                ) in unused_functions.iter().chain(&quarantined).enumerate()
+                {
+                    let relative = function.location.0.strip_prefix(&root)?;
+                    let function_owners = owners
+                        .as_ref()
+                        .map(|owners| owners.owners_of(relative).join(" "))
+                        .unwrap_or_default();
+
+                    let complexity = function
+                        .complexity
+                        .map(|complexity| format!(" (complexity {})", complexity))
+                        .unwrap_or_default();
+                    let expiry = expired
+                        .get(function)
+                        .and_then(|entry| entry.expires)
+                        .map(|expires| format!(" (suppression expired on {})", expires))
+                        .unwrap_or_default();
+                    let locations = |functions: &[&Function]| {
+                        functions
+                            .iter()
+                            .map(|function| {
+                                Ok(format!(
+                                    "{}:{}",
+                                    reported_path(
+                                        path,
+                                        function.location.0.strip_prefix(&root)?,
+                                        &path_map
+                                    ),
+                                    function.location.1
+                                ))
+                            })
+                            .collect::<Result<Vec<_>>>()
+                            .map(|locations| locations.join(", "))
+                    };
+                    let redefined = if redefinitions.is_empty() {
+                        String::new()
+                    } else {
+                        format!(" (also defined at {})", locations(redefinitions)?)
+                    };
+                    let copied = if copies.is_empty() {
+                        String::new()
+                    } else {
+                        format!(" (copied at {})", locations(copies)?)
+                    };
+                    let escalation = escalated
+                        .get(function)
+                        .map(|days| format!(" (unreferenced for {} days)", days))
+                        .unwrap_or_default();
+                    let ownership =
+                        if function_owners.is_empty() || args.group_by == Some(GroupBy::Owner) {
+                            String::new()
+                        } else {
+                            format!(" (owned by {})", function_owners)
+                        };
+                    let description = format!(
+                        "{}:{} - {} \"{}\" {}{}{}{}{}{}{}",
+                        reported_path(path, relative, &path_map),
+                        function.location.1,
+                        function.kind.noun(),
+                        function.name,
+                        category.verdict(),
+                        redefined,
+                        copied,
+                        complexity,
+                        expiry,
+                        escalation,
+                        ownership
+                    );
+
+                    if index >= unused_functions.len() {
+                        quarantined_descriptions.push(description);
+                        continue;
+                    }
+                    match args.group_by {
+                        Some(GroupBy::Owner) => {
+                            let group = if function_owners.is_empty() {
+                                "unowned".to_owned()
+                            } else {
+                                function_owners
+                            };
+                            groups.entry(group).or_default().push(description);
+                        }
+                        Some(GroupBy::Package) => {
+                            groups
+                                .entry(package_of(relative))
+                                .or_default()
+                                .push(description);
+                        }
+                        None => eprintln!("{}", description),
+                    }
+                }
+
+                for (group, descriptions) in groups {
+                    eprintln!("{} ({} findings)", group, descriptions.len());
+                    for description in descriptions {
+                        eprintln!("  {}", description);
+                    }
+                }
+
+                if !quarantined_descriptions.is_empty() {
+                    eprintln!(
+                    "quarantined, as these modules look up functions dynamically ({} findings):",
+                    quarantined_descriptions.len()
+                );
+                    for description in quarantined_descriptions {
+                        eprintln!("  {}", description);
+                    }
+                }
+
+                if !deletion_plan.is_empty() {
+                    eprintln!("deletion plan:");
+                    for (step, function) in deletion_plan.iter().enumerate() {
+                        eprintln!(
+                            "  {}. {}:{} - {}",
+                            step + 1,
+                            reported_path(
+                                path,
+                                function.location.0.strip_prefix(&root)?,
+                                &path_map
+                            ),
+                            function.location.1,
+                            function.name
+                        );
+                    }
+                }
+
+                if finding_count > 0 {
+                    eprintln!("{} lines of potentially dead code", weight);
+                }
+                if let Some(percent) = args.sample {
+                    eprintln!(
+                        "these are estimates from a {}% sample of the files, some of which may be used outside it",
+                        percent
+                    );
+                }
+            }
+        }
+    }
+    tracer.end(span, &[("findings", finding_count)]);
+
+    if let Some(endpoint) = &args.otlp_endpoint {
+        if let Err(err) = tracer.export(endpoint, &metadata) {
+            eprintln!("warning: {:#}", err);
+        }
+    }
+
+    // a headline for scripts, unless stdout already holds a machine-readable report.
+    if machine_formats <= args.output.len() {
+        println!(
+            "snapbug: {} findings in {} files",
+            finding_count, stats.files
+        );
+    }
+
+    if let Some(path) = &args.ratchet {
+        ratchet::check(path, finding_count)
+    } else {
+        caps::check(&config.max_findings, &config.max_findings_by_path, &errors)
+    }
+}
+
+/// Runs the command line interface, parsing the process arguments.
+pub fn run() -> Result<()> {
+    let args = Args::parse();
+
+    match (&args.command, &args.path) {
+        (Some(Command::Archaeology { function, path }), _) => {
+            archaeology::run(vcs::detect(path).as_ref(), function)
+        }
+        (Some(Command::RenameCheck { name, path }), _) => rename::run(path, name),
+        (Some(Command::Doctor { path }), _) => doctor::run(&args, path),
+        (
+            Some(Command::Suppressions {
+                unused,
+                baseline,
+                ignore_file,
+                path,
+            }),
+            _,
+        ) => suppressions::run(
+            &args,
+            path,
+            baseline.as_ref().or(args.baseline.as_ref()),
+            ignore_file.as_ref().or(args.ignore_file.as_ref()),
+            *unused,
+        ),
+        (Some(Command::Selfcheck { baseline, save }), _) => {
+            selfcheck::run(baseline.as_deref(), *save)
+        }
+        (
+            Some(Command::Export {
+                format,
+                output,
+                path,
+            }),
+            _,
+        ) => export::run(&args, path, *format, output.as_deref()),
+        (
+            Some(Command::Suppress {
+                fingerprints,
+                baseline,
+                reason,
+                expires,
+            }),
+            _,
+        ) => {
+            let expires = expires.as_deref().map(baseline::parse_expiry).transpose()?;
+            baseline::suppress(baseline, fingerprints, reason.as_deref(), expires)
+        }
+        (None, Some(path)) => scan(&args, path),
+        (None, None) => unreachable!("clap requires a path without a subcommand"),
+    }
+}
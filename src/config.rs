@@ -0,0 +1,241 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::{anyhow, Context, Result};
+use regex::Regex;
+use serde::Deserialize;
+
+use crate::metadata;
+
+/// The name of the configuration file looked for in the scanned root.
+const FILE_NAME: &str = "snapbug.toml";
+
+/// Holds the configuration in a `[tool.snapbug]` table when there is no snapbug.toml.
+const PYPROJECT: &str = "pyproject.toml";
+
+/// Patterns of names that are allowed to be unused, such as tests and dunder methods.
+const ALLOWED_NAMES: [&str; 2] = ["test_", "__"];
+
+/// Methods that unittest and pytest call by convention, never by name.
+const FRAMEWORK_METHODS: [&str; 24] = [
+    "setUp",
+    "tearDown",
+    "setUpClass",
+    "tearDownClass",
+    "setUpModule",
+    "tearDownModule",
+    "asyncSetUp",
+    "asyncTearDown",
+    "setup_method",
+    "teardown_method",
+    "setup_class",
+    "teardown_class",
+    "setup_module",
+    "teardown_module",
+    "setup_function",
+    "teardown_function",
+    "pytest_addoption",
+    "pytest_configure",
+    "pytest_unconfigure",
+    "pytest_sessionstart",
+    "pytest_sessionfinish",
+    "pytest_collection_modifyitems",
+    "pytest_generate_tests",
+    "pytest_runtest_setup",
+];
+
+/// Modules run directly by tools, whose top-level functions are entry points.
+const ENTRY_MODULES: [&str; 8] = [
+    "manage.py",
+    "wsgi.py",
+    "asgi.py",
+    "conftest.py",
+    "noxfile.py",
+    "fabfile.py",
+    "setup.py",
+    "__main__.py",
+];
+
+/// Settings read from a `snapbug.toml` file, or the `[tool.snapbug]` table of a `pyproject.toml`.
+#[derive(Deserialize, Default, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Extra ways of declaring functions, on top of python's `def`.
+    pub definitions: Vec<Definition>,
+    /// Methods invoked by test frameworks, replacing the built-in list if given.
+    pub framework_methods: Option<Vec<String>>,
+    /// Modules whose top-level functions are entry points, replacing the built-in list if given.
+    pub entry_modules: Option<Vec<String>>,
+    /// Names of further functions that reachability analysis starts from, such as a CLI's `main`.
+    pub entry_points: Vec<String>,
+    /// Gitignore-style patterns of paths to leave out of the scan, such as `migrations/`.
+    pub exclude: Vec<String>,
+    /// Gitignore-style patterns of paths whose mentions count for every declaration under
+    /// `--scoped-counting`, such as `scripts/`.
+    pub extra_roots: Vec<String>,
+    /// How much mentions from each origin count, such as `{ tests = 0 }` to ignore tests' mentions or
+    /// `{ third-party = 1 }` to treat plugins under the search roots as users. Origins default to 1.
+    pub origin_weights: BTreeMap<String, usize>,
+    /// Whether to also leave out whatever `.gitignore` files ignore.
+    pub respect_gitignore: bool,
+    /// Whether to flag declarations only their own file's `__main__` guard uses, as `--script-only` does.
+    pub script_only: bool,
+    /// Regexes found anywhere in the names of functions that are never flagged, replacing the built-in
+    /// `test_` and `__` if given.
+    pub allow: Option<Vec<String>>,
+    /// Decorators marking functions that are called for us, such as `app.route` or `pytest.fixture`.
+    pub decorators: Vec<String>,
+    /// Calls whose string arguments name functions, such as `register_handler` or `signal.connect`.
+    pub registration_calls: Vec<String>,
+    /// How long a finding may go unreferenced before it fails the build, such as "180d".
+    /// Until then findings are only advisory.
+    pub escalate_after: Option<String>,
+    /// The most findings each rule may report before failing the build, such as `{ "unused-function" = 50 }`.
+    pub max_findings: BTreeMap<String, usize>,
+    /// The most findings allowed under each directory relative to the root, such as `{ "core" = 0 }`.
+    pub max_findings_by_path: BTreeMap<String, usize>,
+    /// SHA-256 of the file this configuration was read from.
+    #[serde(skip)]
+    pub hash: Option<String>,
+}
+
+/// A regex that declares a function in files with the given extension.
+/// The function name is taken from the `name` capture group, or the first group if there is none.
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Definition {
+    pub extension: String,
+    pub pattern: String,
+}
+
+impl Config {
+    /// Looks for a configuration file in the given root, falling back to a `pyproject.toml` with a
+    /// `[tool.snapbug]` table.
+    pub fn find(root: &Path) -> Option<PathBuf> {
+        let directory = if root.is_dir() { root } else { root.parent()? };
+        let file = directory.join(FILE_NAME);
+        if file.is_file() {
+            return Some(file);
+        }
+        let pyproject = directory.join(PYPROJECT);
+        let contents = fs::read_to_string(&pyproject).ok()?;
+        tool_table(&contents.parse().ok()?).map(|_| pyproject)
+    }
+
+    /// Parses the given configuration file, which may be a URL to fetch it from.
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = match path.to_str().filter(|path| is_url(path)) {
+            Some(url) => ureq::get(url)
+                .call()
+                .and_then(|mut response| response.body_mut().read_to_string())
+                .with_context(|| format!("failed to fetch config {}", url))?,
+            None => fs::read_to_string(path)
+                .with_context(|| format!("failed to read config {}", path.display()))?,
+        };
+        let mut config: Self = if path.file_name() == Some(PYPROJECT.as_ref()) {
+            let table: toml::Table = contents
+                .parse()
+                .with_context(|| format!("invalid config {}", path.display()))?;
+            tool_table(&table)
+                .ok_or_else(|| anyhow!("{} has no [tool.snapbug] table", path.display()))?
+                .clone()
+                .try_into()
+        } else {
+            toml::from_str(&contents)
+        }
+        .with_context(|| format!("invalid config {}", path.display()))?;
+        config.hash = Some(metadata::sha256(contents.as_bytes()));
+        Ok(config)
+    }
+
+    /// The configured framework methods, or the built-in list.
+    pub fn framework_methods(&self) -> Vec<String> {
+        match &self.framework_methods {
+            Some(methods) => methods.clone(),
+            None => FRAMEWORK_METHODS
+                .iter()
+                .map(|&method| method.to_owned())
+                .collect(),
+        }
+    }
+
+    /// The configured entry modules, or the built-in list.
+    pub fn entry_modules(&self) -> Vec<String> {
+        match &self.entry_modules {
+            Some(modules) => modules.clone(),
+            None => ENTRY_MODULES
+                .iter()
+                .map(|&module| module.to_owned())
+                .collect(),
+        }
+    }
+
+    /// The configured names allowed to be unused, or the built-in list.
+    pub fn allow(&self) -> Vec<String> {
+        match &self.allow {
+            Some(names) => names.clone(),
+            None => ALLOWED_NAMES.iter().map(|&name| name.to_owned()).collect(),
+        }
+    }
+
+    /// The parsed escalation window, if one is configured.
+    pub fn escalate_after(&self) -> Result<Option<Duration>> {
+        self.escalate_after
+            .as_deref()
+            .map(|window| {
+                parse_duration(window)
+                    .with_context(|| format!("invalid escalate_after {:?}", window))
+            })
+            .transpose()
+    }
+
+    /// Compiles the extra definition patterns, paired with the extension they apply to.
+    pub fn definition_patterns(&self) -> Result<Vec<(String, Regex)>> {
+        self.definitions
+            .iter()
+            .map(|definition| {
+                let pattern = Regex::new(&definition.pattern).with_context(|| {
+                    format!("invalid definition pattern {}", definition.pattern)
+                })?;
+                if pattern.captures_len() < 2 {
+                    return Err(anyhow!(
+                        "definition pattern {} has no group capturing the name",
+                        definition.pattern
+                    ));
+                }
+                let extension = definition.extension.trim_start_matches('.').to_owned();
+                Ok((extension, pattern))
+            })
+            .collect()
+    }
+}
+
+/// The `[tool.snapbug]` table of a parsed `pyproject.toml`.
+fn tool_table(pyproject: &toml::Table) -> Option<&toml::Value> {
+    pyproject.get("tool")?.get("snapbug")
+}
+
+/// Whether a configuration path is an http or https URL.
+pub fn is_url(path: &str) -> bool {
+    path.starts_with("https://") || path.starts_with("http://")
+}
+
+/// Parses a whole number of hours, days or weeks, such as "180d".
+pub fn parse_duration(text: &str) -> Result<Duration> {
+    let split = text
+        .find(|c: char| !c.is_ascii_digit())
+        .ok_or_else(|| anyhow!("missing a unit of h, d or w"))?;
+    let (count, unit) = text.split_at(split);
+    let count: u64 = count.parse().context("missing a number")?;
+    let hours = match unit {
+        "h" => 1,
+        "d" => 24,
+        "w" => 24 * 7,
+        _ => return Err(anyhow!("unknown unit {:?}, expected h, d or w", unit)),
+    };
+    Ok(Duration::from_secs(count * hours * 60 * 60))
+}
@@ -0,0 +1,217 @@
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+};
+
+use anyhow::Result;
+use rayon::prelude::*;
+use serde::Serialize;
+
+use crate::{
+    declarations::Declared,
+    find_dead_decorators, find_unused_functions,
+    haystack::{Haystack, Needles, Segment},
+    modules::{ImportGraph, MentionScope, Visibility},
+    origins::Origin,
+    targets::Targets,
+    walk::SourceFile,
+    Category, Finding, Function, ScanOptions,
+};
+
+/// How many times a function is mentioned in code and in prose (comments and strings).
+#[derive(Clone, Copy, Default, Serialize, Debug)]
+pub struct Mentions {
+    pub code: usize,
+    pub prose: usize,
+}
+
+/// Counts the mentions of each function in one source file's segment of the haystack,
+/// leaving out the functions that it never mentions.
+pub(crate) fn count_segment<'a>(
+    haystack: &Haystack,
+    segment: &Segment,
+    functions: &[&'a Function],
+    needles: &Needles,
+) -> Result<HashMap<&'a Function, Mentions>> {
+    let mut counts = HashMap::new();
+    for ((code, prose), function) in haystack.count(segment, needles)?.into_iter().zip(functions) {
+        if code > 0 || prose > 0 {
+            counts.insert(*function, Mentions { code, prose });
+        }
+    }
+    Ok(counts)
+}
+
+/// Adds the mentions counted in one segment to the totals.
+fn add_mentions<'a>(
+    totals: &mut HashMap<&'a Function, Mentions>,
+    other: HashMap<&'a Function, Mentions>,
+) {
+    for (function, mentions) in other {
+        let total = totals.entry(function).or_default();
+        total.code += mentions.code;
+        total.prose += mentions.prose;
+    }
+}
+
+/// Finds the declarations that are only mentioned once in the files they were collected from, as walked
+/// from the root.
+pub fn count<'a>(
+    declared: &'a Declared,
+    files: &[SourceFile],
+    root: &Path,
+    options: &ScanOptions,
+) -> Result<Vec<Finding<'a>>> {
+    count_mentions(
+        &declared.haystack,
+        &declared.functions,
+        files,
+        root,
+        options,
+    )
+}
+
+/// Finds the functions that are only mentioned once, building the import graph first if dead modules or
+/// scoped counting need it.
+pub(crate) fn count_mentions<'a>(
+    haystack: &Haystack,
+    functions: &'a HashSet<Function>,
+    files: &[SourceFile],
+    root: &Path,
+    options: &ScanOptions,
+) -> Result<Vec<Finding<'a>>> {
+    if let Some(system) = options.build_graph {
+        let targets = Targets::query(system, files, root, &options.extra_roots)?;
+        let dead_modules = if options.dead_modules {
+            ImportGraph::build(files, root, options)?.dead_modules()
+        } else {
+            HashSet::new()
+        };
+        return scan_for_unused_functions(
+            haystack,
+            functions,
+            &dead_modules,
+            Some(&targets),
+            options,
+        );
+    }
+    if !options.dead_modules && !options.scoped_counting {
+        return scan_for_unused_functions(haystack, functions, &HashSet::new(), None, options);
+    }
+    let graph = ImportGraph::build(files, root, options)?;
+    let dead_modules = if options.dead_modules {
+        graph.dead_modules()
+    } else {
+        HashSet::new()
+    };
+    let visibility = options
+        .scoped_counting
+        .then(|| Visibility::new(&graph, files, root, &options.extra_roots));
+    scan_for_unused_functions(
+        haystack,
+        functions,
+        &dead_modules,
+        visibility
+            .as_ref()
+            .map(|visibility| visibility as &dyn MentionScope),
+        options,
+    )
+}
+
+/// Scan the haystack files to find functions that are only mentioned once.
+/// Each source file's segment of the haystack is counted in parallel. Mentions in the given dead modules,
+/// by index into the segments, don't count, and neither do mentions that the visibility rules out. Mentions
+/// are weighed by their origin, except in the declaring file, and under `--test-only`, those in tests only count for declarations in tests.
+fn scan_for_unused_functions<'a>(
+    haystack: &Haystack,
+    functions: &'a HashSet<Function>,
+    dead_modules: &HashSet<usize>,
+    visibility: Option<&dyn MentionScope>,
+    options: &ScanOptions,
+) -> Result<Vec<Finding<'a>>> {
+    let test_only = options.test_only;
+    let ordered: Vec<&Function> = functions.iter().collect();
+    let needles = Needles::new(ordered.iter().map(|function| function.name.as_str()));
+
+    // the mentions everywhere, those outside dead modules and tests, and those outside tests.
+    let (mut counts, mut live, mut untested) = haystack
+        .segments()
+        .par_iter()
+        .enumerate()
+        .map(|(index, segment)| -> Result<_> {
+            let mut counts = count_segment(haystack, segment, &ordered, &needles)?;
+            if let Some(visibility) = visibility {
+                counts.retain(|function, _| visibility.sees(index, &function.location.0));
+            }
+            // a declaration's own file isn't weighed, as it holds the declaration's own mention.
+            let weight = options.origin_weights.of(segment.origin());
+            if weight != 1 {
+                counts.retain(|function, mentions| {
+                    if function.location.0 == segment.path() {
+                        return true;
+                    }
+                    mentions.code *= weight;
+                    mentions.prose *= weight;
+                    weight > 0
+                });
+            }
+            let untested = if !test_only {
+                HashMap::new()
+            } else if segment.origin() == Origin::Tests {
+                counts
+                    .iter()
+                    .filter(|(function, _)| function.test)
+                    .map(|(&function, &mentions)| (function, mentions))
+                    .collect()
+            } else {
+                counts.clone()
+            };
+            let live = if dead_modules.contains(&index) {
+                HashMap::new()
+            } else if test_only {
+                untested.clone()
+            } else {
+                counts.clone()
+            };
+            Ok((counts, live, untested))
+        })
+        .try_reduce(
+            || (HashMap::new(), HashMap::new(), HashMap::new()),
+            |(mut counts, mut live, mut untested), (other, other_live, other_untested)| {
+                add_mentions(&mut counts, other);
+                add_mentions(&mut live, other_live);
+                add_mentions(&mut untested, other_untested);
+                Ok((counts, live, untested))
+            },
+        )?;
+
+    for function in functions {
+        counts.entry(function).or_default();
+        live.entry(function).or_default();
+        untested.entry(function).or_default();
+    }
+
+    let mut findings = find_unused_functions(&live, options.script_only);
+    if !dead_modules.is_empty() || test_only {
+        let unused_in = |counts| -> HashSet<&Function> {
+            find_unused_functions(counts, options.script_only)
+                .iter()
+                .map(|finding| finding.function)
+                .collect()
+        };
+        let unused = unused_in(&counts);
+        let unused_outside_tests = unused_in(&untested);
+        for finding in &mut findings {
+            if !unused.contains(finding.function) {
+                // whichever mentions were left out make the difference.
+                finding.category = if test_only && unused_outside_tests.contains(finding.function) {
+                    Category::TestOnly
+                } else {
+                    Category::DeadModuleOnly
+                };
+            }
+        }
+    }
+    find_dead_decorators(&live, &mut findings);
+    Ok(findings)
+}
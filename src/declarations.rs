@@ -0,0 +1,777 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs::File,
+    io::Read,
+};
+
+use anyhow::Result;
+use rayon::prelude::*;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    baseline,
+    cache::Cache,
+    calls::NamingCalls,
+    classes::Classes,
+    decorators::{self, Decorators},
+    extension_of,
+    haystack::{identifiers, Haystack, Text, MAX_HELD},
+    is_entry_module,
+    lines::ChunkedLines,
+    metadata, open_cache,
+    origins::Origin,
+    should_consider_function, source,
+    strip::Stripper,
+    syntax::Declarations,
+    testing,
+    walk::{Skipped, SourceFile},
+    Function, Kind, Language, ScanOptions, PYTHON_EXTENSIONS,
+};
+
+/// Methods that make a class usable in a `with` statement.
+const CONTEXT_MANAGER_METHODS: [&str; 2] = ["__enter__", "__aenter__"];
+
+/// How many `getattr` calls make a module dynamic enough to quarantine its findings.
+const HEAVY_GETATTR: usize = 3;
+
+/// The fewest lines of code a body needs for its copies to be flagged, as shorter ones repeat by coincidence.
+const MIN_DUPLICATE_LINES: usize = 3;
+
+/// How many files are scanned in parallel before their text is written to the haystack.
+const SCAN_BATCH: usize = 256;
+
+/// How many bytes of files are scanned in parallel before their text is written to the haystack, as the
+/// text of each is held in memory until then, up to `MAX_HELD` bytes of each.
+const SCAN_BATCH_BYTES: u64 = 64 * MAX_HELD as u64;
+
+/// Python files larger than this many bytes aren't parsed, as their syntax tree would be many times their
+/// size. Their declarations are found by the patterns instead, as for files that don't parse.
+const MAX_PARSED: u64 = 1024 * 1024;
+
+/// Where a declaration is made: the line of the enclosing class, if any, and the indentation within it.
+type Scope = (Option<usize>, usize);
+
+/// The rough shape of a source line, used to find where blocks end.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum LineShape {
+    /// Empty or comment-only lines.
+    Blank,
+    /// Lines that belong to whatever came before, such as the inside of a string or a closing bracket.
+    Continuation,
+    /// Lines starting a new statement at the given indentation.
+    Indented(usize),
+}
+
+/// Classifies a line by how it affects block structure.
+fn line_shape(line: &str, in_string: bool) -> LineShape {
+    let trimmed = line.trim_start();
+    if in_string || trimmed.starts_with([')', ']', '}']) {
+        LineShape::Continuation
+    } else if trimmed.is_empty() || trimmed.starts_with('#') {
+        LineShape::Blank
+    } else {
+        LineShape::Indented(line.len() - trimmed.len())
+    }
+}
+
+/// What is gathered of a block's code: the branching keywords, for estimating its complexity, and its body
+/// with whitespace removed, for finding copies of it.
+#[derive(Clone, Default)]
+struct BlockCode {
+    branches: usize,
+    body: Sha256,
+    /// How many lines of the body weren't empty once whitespace was removed.
+    body_lines: usize,
+}
+
+impl BlockCode {
+    fn add(&mut self, branch_pattern: &Regex, code: &str, in_body: bool) {
+        self.branches += branch_pattern.find_iter(code).count();
+        let normalized: String = code.split_whitespace().collect();
+        if in_body && !normalized.is_empty() {
+            if self.body_lines > 0 {
+                self.body.update(b"\n");
+            }
+            self.body.update(normalized.as_bytes());
+            self.body_lines += 1;
+        }
+    }
+
+    /// Estimates cyclomatic complexity as one plus the number of branching keywords.
+    fn complexity(&self) -> usize {
+        1 + self.branches
+    }
+
+    /// Hashes the body, so that copies differing only in formatting, comments or the contents of strings hash
+    /// the same. Bodies too short to compare have no digest.
+    fn digest(self) -> Option<String> {
+        (self.body_lines >= MIN_DUPLICATE_LINES).then(|| metadata::hex(&self.body.finalize()))
+    }
+}
+
+/// A block whose end hasn't been found yet.
+struct OpenBlock {
+    start: usize,
+    indent: usize,
+    /// The last line of the block so far that isn't blank.
+    end: usize,
+    /// The code up to the last line that isn't blank, and with the blank lines since, which only belong to the
+    /// block if a later line does.
+    code: Option<(BlockCode, Option<BlockCode>)>,
+}
+
+/// Finds where blocks end, and gathers their code, as the lines of a file go by, so that the lines needn't
+/// be kept. A block runs up to its last non-blank line before one that is indented no further than it.
+#[derive(Default)]
+struct Blocks {
+    open: Vec<OpenBlock>,
+    /// The number of lines in each block, by its first line.
+    lengths: HashMap<usize, usize>,
+    code: HashMap<usize, BlockCode>,
+}
+
+impl Blocks {
+    /// Passes a line, ending the blocks it isn't part of and adding it to the others.
+    fn line(&mut self, lineno: usize, shape: LineShape, code: &str, branch_pattern: &Regex) {
+        if let LineShape::Indented(indent) = shape {
+            while self.open.last().is_some_and(|block| block.indent >= indent) {
+                let block = self.open.pop().expect("a block is open");
+                self.close(block);
+            }
+        }
+        for block in &mut self.open {
+            if shape != LineShape::Blank {
+                block.end = lineno;
+            }
+            if let Some((kept, since)) = &mut block.code {
+                let blank = shape == LineShape::Blank;
+                match since {
+                    None if !blank => kept.add(branch_pattern, code, true),
+                    _ => {
+                        let mut added = since.take().unwrap_or_else(|| kept.clone());
+                        added.add(branch_pattern, code, true);
+                        if blank {
+                            *since = Some(added);
+                        } else {
+                            *kept = added;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Starts a block on the given line, whose code is gathered if asked to. A line that doesn't start a
+    /// statement is a block of its own.
+    fn open(&mut self, lineno: usize, shape: LineShape, code: Option<(&str, &Regex)>) {
+        if self.lengths.contains_key(&lineno) || self.open.iter().any(|block| block.start == lineno)
+        {
+            return;
+        }
+        let gathered = code.map(|(code, branch_pattern)| {
+            let mut gathered = BlockCode::default();
+            gathered.add(branch_pattern, code, false);
+            gathered
+        });
+        match shape {
+            LineShape::Indented(indent) => self.open.push(OpenBlock {
+                start: lineno,
+                indent,
+                end: lineno,
+                code: gathered.map(|gathered| (gathered, None)),
+            }),
+            _ => {
+                self.lengths.insert(lineno, 1);
+                if let Some(gathered) = gathered {
+                    self.code.insert(lineno, gathered);
+                }
+            }
+        }
+    }
+
+    fn close(&mut self, block: OpenBlock) {
+        self.lengths
+            .insert(block.start, block.end - block.start + 1);
+        if let Some((kept, _)) = block.code {
+            self.code.insert(block.start, kept);
+        }
+    }
+
+    /// Ends the blocks still open at the end of the file.
+    fn finish(&mut self) {
+        while let Some(block) = self.open.pop() {
+            self.close(block);
+        }
+    }
+
+    /// The number of lines in the block starting on the given line.
+    fn length(&self, lineno: usize) -> usize {
+        self.lengths.get(&lineno).copied().unwrap_or(1)
+    }
+}
+
+/// Patterns matched against every line, compiled once for all files.
+struct LinePatterns {
+    branch_pattern: Regex,
+    constant_pattern: Regex,
+    dynamic_pattern: Regex,
+    getattr_pattern: Regex,
+    module_getattr_pattern: Regex,
+    guard_pattern: Regex,
+    suppression_pattern: Regex,
+    parser_pattern: Regex,
+    test_import_pattern: Regex,
+}
+
+impl LinePatterns {
+    fn new() -> Result<Self> {
+        Ok(Self {
+            branch_pattern: Regex::new(r"\b(if|elif|for|while|except|and|or|case)\b")?,
+            constant_pattern: Regex::new(r"^([A-Z][A-Z0-9_]*)\s*(?::[^=]*)?=[^=]")?,
+            dynamic_pattern: Regex::new(r"\b(?:eval|exec|__import__)\s*\(|\bimportlib\b")?,
+            getattr_pattern: Regex::new(r"\bgetattr\s*\(")?,
+            module_getattr_pattern: Regex::new(r"^def\s+__getattr__\s*\(")?,
+            guard_pattern: Regex::new(
+                r#"^if\s+(?:__name__\s*==\s*['"]__main__['"]|['"]__main__['"]\s*==\s*__name__)\s*:"#,
+            )?,
+            suppression_pattern: Regex::new(r"(?:#|//)\s*snapbug:\s*ignore\b")?,
+            parser_pattern: Regex::new(r"\bArgumentParser\s*\(")?,
+            test_import_pattern: Regex::new(testing::IMPORT_PATTERN)?,
+        })
+    }
+}
+
+/// The declarations found in a single file, and the text to add to the haystack for it.
+#[derive(Serialize, Deserialize, Default)]
+pub(crate) struct ScannedFile {
+    pub(crate) functions: Vec<Function>,
+    code: Text,
+    prose: Text,
+    /// Whether mentions in the prose are counted as code.
+    prose_is_code: bool,
+    /// Whether the file is a test.
+    is_test: bool,
+    /// Whether the file imports each preset's framework, whose methods are then allowed to be unused.
+    imports_preset: Vec<bool>,
+    /// Why the file couldn't be scanned, if it couldn't. Its segment of the haystack is left empty.
+    #[serde(skip)]
+    skipped: Option<&'static str>,
+}
+
+/// Appends text and an optional line break.
+fn push_text(buffer: &mut Text, text: &str, ends_line: bool) -> Result<()> {
+    buffer.push_str(text)?;
+    if ends_line {
+        buffer.push_str("\n")?;
+    }
+    Ok(())
+}
+
+/// Counts the identifiers in the code.
+fn count_identifiers(counts: &mut HashMap<String, usize>, code: &str) {
+    for identifier in identifiers(code) {
+        match counts.get_mut(identifier) {
+            Some(count) => *count += 1,
+            None => {
+                counts.insert(identifier.to_owned(), 1);
+            }
+        }
+    }
+}
+
+/// Adds the other counts of identifiers to the first.
+fn merge_counts(counts: &mut HashMap<String, usize>, other: HashMap<String, usize>) {
+    for (identifier, count) in other {
+        *counts.entry(identifier).or_default() += count;
+    }
+}
+
+/// The declarations found in the scanned files, and the haystack of their text that mentions are counted in.
+pub struct Declared {
+    /// Everything declared outside of search roots.
+    pub functions: HashSet<Function>,
+    /// The files that turned out to be binary or undecodable.
+    pub skipped: Vec<Skipped>,
+    pub(crate) haystack: Haystack,
+    pub(crate) cache: Option<Cache>,
+}
+
+/// Scans the given files for declarations, as walked from a root, reusing the cached scans if the options
+/// give a cache directory.
+pub fn collect(files: &[SourceFile], options: &ScanOptions) -> Result<Declared> {
+    let mut haystack = Haystack::new()?;
+    let cache = open_cache(options)?;
+    let mut skipped = Vec::new();
+    let functions = scan_files(files, options, &mut haystack, &mut skipped, cache.as_ref())?;
+    Ok(Declared {
+        functions,
+        skipped,
+        haystack,
+        cache,
+    })
+}
+
+/// Scan the given files, finding all declared functions.
+/// Also populates the haystack files used later for counting references.
+/// Files that turn out to be binary or undecodable are added to the skipped entries.
+fn scan_files(
+    files: &[SourceFile],
+    options: &ScanOptions,
+    haystack: &mut Haystack,
+    skipped: &mut Vec<Skipped>,
+    cache: Option<&Cache>,
+) -> Result<HashSet<Function>> {
+    let patterns = LinePatterns::new()?;
+    let mut functions = HashSet::new();
+    let mut writer = haystack.writer();
+
+    // files are scanned in parallel a batch at a time, which bounds how much text is held in memory,
+    // and written to the haystack in order.
+    for batch in scan_batches(files) {
+        let scanned = batch
+            .par_iter()
+            .map(
+                |entry| match scan_cached(entry, options, &patterns, cache) {
+                    Err(err) if is_undecodable(&err) => Ok(ScannedFile {
+                        skipped: Some("undecodable"),
+                        ..Default::default()
+                    }),
+                    scanned => scanned,
+                },
+            )
+            .collect::<Result<Vec<_>>>()?;
+        for (entry, mut file) in batch.iter().zip(scanned) {
+            if let Some(reason) = file.skipped {
+                skipped.push(Skipped::new(entry.path(), reason));
+            }
+            let origin = origin_of(entry, file.is_test, options);
+            if origin != Origin::ThirdParty {
+                // whether the framework covers the file depends on others nearby, so it isn't cached.
+                for function in &mut file.functions {
+                    function.root |= options.preset_methods.iter().zip(&file.imports_preset).any(
+                        |(preset, &imported)| {
+                            preset.contains(&function.name) && (imported || preset.is_marked(entry))
+                        },
+                    );
+                }
+                functions.extend(file.functions);
+            }
+            writer.file(
+                entry.path(),
+                file.code,
+                file.prose,
+                file.prose_is_code,
+                origin,
+            )?;
+        }
+    }
+    writer.finish()?;
+
+    Ok(functions)
+}
+
+/// Splits the files into batches of at most `SCAN_BATCH` files and `SCAN_BATCH_BYTES` bytes.
+fn scan_batches(files: &[SourceFile]) -> Vec<&[SourceFile]> {
+    let mut batches = Vec::new();
+    let mut start = 0;
+    let mut bytes = 0;
+    for (index, entry) in files.iter().enumerate() {
+        let size = entry.size().min(MAX_HELD as u64);
+        if index > start && (index - start == SCAN_BATCH || bytes + size > SCAN_BATCH_BYTES) {
+            batches.push(&files[start..index]);
+            start = index;
+            bytes = 0;
+        }
+        bytes += size;
+    }
+    if start < files.len() {
+        batches.push(&files[start..]);
+    }
+    batches
+}
+
+/// Scans a file, unless the cache holds its scan.
+fn scan_cached(
+    entry: &SourceFile,
+    options: &ScanOptions,
+    patterns: &LinePatterns,
+    cache: Option<&Cache>,
+) -> Result<ScannedFile> {
+    let cache = match cache {
+        Some(cache) => cache,
+        None => return scan_file(entry, options, patterns),
+    };
+    let key = cache.key(
+        &baseline::normalize(&entry.relative_path()),
+        File::open(entry.path())?,
+    )?;
+    if let Some(mut scanned) = cache.load(&key) {
+        // the same contents may be checked out anywhere.
+        for function in &mut scanned.functions {
+            function.location.0 = entry.path().to_owned();
+        }
+        return Ok(scanned);
+    }
+
+    let scanned = scan_file(entry, options, patterns)?;
+    if scanned.skipped.is_none() && !scanned.code.is_spilled() && !scanned.prose.is_spilled() {
+        // a cache that can't be written to only makes the next run slower.
+        let _ = cache.store(&key, &scanned);
+    }
+    Ok(scanned)
+}
+
+/// Where the mentions in a scanned file come from.
+fn origin_of(entry: &SourceFile, is_test: bool, options: &ScanOptions) -> Origin {
+    if options
+        .search_roots
+        .iter()
+        .any(|root| entry.path().starts_with(root))
+    {
+        Origin::ThirdParty
+    } else if is_test {
+        Origin::Tests
+    } else if entry.is_script() {
+        Origin::Config
+    } else {
+        Origin::FirstParty
+    }
+}
+
+/// Whether scanning failed because a file isn't valid in its encoding.
+fn is_undecodable(err: &anyhow::Error) -> bool {
+    err.downcast_ref::<std::io::Error>()
+        .is_some_and(|err| err.kind() == std::io::ErrorKind::InvalidData)
+}
+
+/// Parses a python file for its declarations, unless it is too large, can't be read or isn't valid python,
+/// which the line scan then reports or copes with.
+fn parse_declarations(entry: &SourceFile) -> Option<Declarations> {
+    if entry.size() > MAX_PARSED {
+        return None;
+    }
+    let mut source = String::new();
+    source::open(entry.path())
+        .ok()?
+        .read_to_string(&mut source)
+        .ok()?;
+    Declarations::parse(&source, &entry.path().to_string_lossy())
+}
+
+/// Scans a single file for declarations, and separates its code from its prose.
+fn scan_file(
+    entry: &SourceFile,
+    options: &ScanOptions,
+    patterns: &LinePatterns,
+) -> Result<ScannedFile> {
+    let LinePatterns {
+        branch_pattern,
+        constant_pattern,
+        dynamic_pattern,
+        getattr_pattern,
+        module_getattr_pattern,
+        guard_pattern,
+        suppression_pattern,
+        parser_pattern,
+        test_import_pattern,
+    } = patterns;
+    let extension = extension_of(entry.path());
+    let is_python = PYTHON_EXTENSIONS.contains(&extension);
+    let mut patterns: Vec<&Regex> = options
+        .definitions
+        .iter()
+        .filter(|(pattern_extension, _)| pattern_extension == extension)
+        .map(|(_, pattern)| pattern)
+        .collect();
+
+    // python's own declarations are read from its syntax tree, falling back to the patterns for files that
+    // don't parse. Configured patterns always apply.
+    let parsed = is_python.then(|| parse_declarations(entry)).flatten();
+    if parsed.is_some() {
+        let builtin = Language::Python.definition_patterns();
+        patterns.retain(|pattern| !builtin.contains(&pattern.as_str()));
+    }
+
+    let is_entry_module = is_entry_module(entry.path(), &options.entry_modules);
+    let mut stripper = Stripper::new();
+    let mut naming_calls = NamingCalls::new(&options.naming_calls);
+    let mut decorators = Decorators::new();
+    let mut classes = Classes::new()?;
+    let mut blocks = Blocks::default();
+    let mut declarations = Vec::new();
+    // the first overload of each name in each scope, and whether it is implemented there.
+    let mut overloads: HashMap<(String, Scope), (Option<usize>, bool)> = HashMap::new();
+    let mut context_managers = Vec::new();
+    let mut constants = Vec::new();
+    let mut suppressions = HashSet::new();
+    let mut dynamic_lookups = false;
+    let mut getattr_calls = 0;
+    let mut in_guard = false;
+    // the identifiers in the body of the current top-level function, which is script code too if it builds an
+    // argument parser.
+    let mut setup: Option<(HashMap<String, usize>, bool)> = None;
+    let mut lazy_exports = false;
+    let mut imports_test_framework = false;
+    let mut imports_preset = vec![false; options.preset_methods.len()];
+    // the identifiers in script code, which is only kept as counts, for bounded memory.
+    let mut guarded = HashMap::new();
+    // whether the code of each block is gathered, for its complexity or to find copies of it.
+    let gathers = options.complexity || options.duplicates;
+    // whether each declaration is at the top level of the module.
+    let mut top_level = HashSet::new();
+    let mut scanned = ScannedFile::default();
+
+    let mut lineno = 0;
+    for chunk in ChunkedLines::new(source::open(entry.path())?) {
+        let chunk = chunk?;
+        let line = chunk.text;
+        // text never holds NUL, while compiled and packed files nearly always do early on.
+        if lineno == 0 && line.contains('\0') {
+            return Ok(ScannedFile {
+                skipped: Some("binary"),
+                ..Default::default()
+            });
+        }
+        let shape = line_shape(&line, stripper.in_string());
+
+        let (code, prose) = if is_python {
+            stripper.split(&line, chunk.ends_line)
+        } else {
+            (line.clone(), String::new())
+        };
+
+        // only the first chunk of a long line is examined for definitions and structure.
+        if chunk.starts_line {
+            blocks.line(lineno, shape, &code, branch_pattern);
+            if is_python {
+                classes.observe(&code, shape, lineno);
+            }
+
+            let names: Vec<String> = parsed
+                .iter()
+                .flat_map(|parsed| parsed.functions_at(lineno).iter().cloned())
+                .chain(patterns.iter().filter_map(|pattern| {
+                    pattern
+                        .captures(&line)
+                        .and_then(|c| c.name("name").or_else(|| c.get(1)))
+                        .map(|name| name.as_str().to_owned())
+                }))
+                .collect();
+            let class = is_python
+                .then(|| classes.declared(lineno))
+                .flatten()
+                .filter(|_| options.kinds.contains(&Kind::Class))
+                .map(str::to_owned);
+            // a `# snapbug: ignore` comment on a declaration's line suppresses its findings.
+            let suppressed = suppression_pattern.is_match(&line);
+            if suppressed {
+                suppressions.insert(lineno);
+            }
+            if options.kinds.contains(&Kind::Constant)
+                && is_python
+                && shape == LineShape::Indented(0)
+            {
+                if let Some(captures) = constant_pattern.captures(&code) {
+                    constants.push((captures[1].to_owned(), lineno));
+                }
+            }
+            // classes may turn out to be context managers, whose length is needed too.
+            let declares = !names.is_empty()
+                || class.is_some()
+                || classes.declared(lineno).is_some()
+                || constants.last().is_some_and(|&(_, line)| line == lineno);
+            if declares {
+                blocks.open(lineno, shape, gathers.then_some((&code, branch_pattern)));
+                if shape == LineShape::Indented(0) {
+                    top_level.insert(lineno);
+                }
+            }
+
+            if names.is_empty() && class.is_none() {
+                decorators.observe(&code);
+            } else {
+                let applied = decorators.take();
+                let (is_method, is_servicer_method) = match shape {
+                    LineShape::Indented(indent) => (
+                        classes.is_method(indent),
+                        classes.is_servicer_method(indent),
+                    ),
+                    _ => (false, false),
+                };
+                let kind = if is_method {
+                    Kind::Method
+                } else {
+                    Kind::Function
+                };
+                for name in names {
+                    if CONTEXT_MANAGER_METHODS.contains(&name.as_str()) {
+                        if let LineShape::Indented(indent) = shape {
+                            if let Some((class, line)) = classes.enclosing(indent) {
+                                context_managers.push((class.to_owned(), line));
+                            }
+                        }
+                    }
+                    // RPC methods are called by gRPC, and named in UpperCamelCase by convention.
+                    let exempt = (is_servicer_method && name.starts_with(char::is_uppercase))
+                        || suppressed
+                        || !should_consider_function(&name, &applied, options);
+                    let scope: Scope = match shape {
+                        LineShape::Indented(indent) => {
+                            (classes.enclosing(indent).map(|(_, line)| line), indent)
+                        }
+                        _ => (None, 0),
+                    };
+                    // stubs declare overloads without ever implementing them.
+                    if is_python && extension != "pyi" {
+                        let group = overloads.entry((name.clone(), scope)).or_default();
+                        if applied
+                            .iter()
+                            .any(|decorator| decorators::matches(decorator, "overload"))
+                        {
+                            group.0.get_or_insert(lineno);
+                        } else {
+                            group.1 = true;
+                        }
+                    }
+                    if options.kinds.contains(&kind) {
+                        declarations.push((name, kind, lineno, applied.clone(), exempt, scope));
+                    }
+                }
+                if let Some(name) = class {
+                    let exempt = suppressed || !should_consider_function(&name, &applied, options);
+                    let scope: Scope = match shape {
+                        LineShape::Indented(indent) => (None, indent),
+                        _ => (None, 0),
+                    };
+                    declarations.push((name, Kind::Class, lineno, applied, exempt, scope));
+                }
+            }
+        }
+
+        if is_python {
+            if chunk.starts_line && shape == LineShape::Indented(0) {
+                in_guard = guard_pattern.is_match(&line);
+                lazy_exports |= module_getattr_pattern.is_match(&code);
+                if let Some((body, true)) = setup.take() {
+                    merge_counts(&mut guarded, body);
+                }
+                let trimmed = code.trim_start();
+                if trimmed.starts_with("def ") || trimmed.starts_with("async def ") {
+                    setup = Some((HashMap::new(), false));
+                }
+            } else if let Some((body, builds_parser)) = &mut setup {
+                *builds_parser |= parser_pattern.is_match(&code);
+                count_identifiers(body, &code);
+            }
+            if in_guard {
+                count_identifiers(&mut guarded, &code);
+            }
+            dynamic_lookups |= dynamic_pattern.is_match(&code);
+            imports_test_framework |= is_python && test_import_pattern.is_match(&code);
+            for (imported, preset) in imports_preset.iter_mut().zip(&options.preset_methods) {
+                *imported |= preset.is_import(&code);
+            }
+            getattr_calls += getattr_pattern.find_iter(&code).count();
+        }
+
+        if options.strip_comments {
+            if naming_calls.observe(&code) {
+                // functions named in these strings are real references, so they are kept on the code's line.
+                push_text(&mut scanned.code, &code, false)?;
+                scanned.code.push_str(" ")?;
+                push_text(&mut scanned.code, &prose, chunk.ends_line)?;
+            } else {
+                push_text(&mut scanned.code, &code, chunk.ends_line)?;
+                push_text(&mut scanned.prose, &prose, chunk.ends_line)?;
+            }
+        } else {
+            push_text(&mut scanned.code, &line, chunk.ends_line)?;
+        }
+        if chunk.ends_line {
+            lineno += 1;
+        }
+    }
+
+    if let Some((body, true)) = setup {
+        merge_counts(&mut guarded, body);
+    }
+    blocks.finish();
+    let dynamic = dynamic_lookups || getattr_calls >= HEAVY_GETATTR;
+    let is_test = imports_test_framework || testing::is_test_path(&entry.relative_path());
+    let guarded_mentions = |name: &str| -> usize { guarded.get(name).copied().unwrap_or_default() };
+    for (name, kind, lineno, decorators, exempt, scope) in declarations {
+        let lines = blocks.length(lineno);
+        let code = blocks.code.get(&lineno);
+        let complexity = (options.complexity && matches!(kind, Kind::Function | Kind::Method))
+            .then(|| code.map(BlockCode::complexity))
+            .flatten();
+        // the declaration is left out, so that copies under another name are matched too.
+        let body = options
+            .duplicates
+            .then(|| code.cloned().and_then(BlockCode::digest))
+            .flatten();
+        let guarded_mentions = guarded_mentions(&name);
+        let unimplemented_overload = overloads
+            .get(&(name.clone(), scope))
+            .filter(|(_, implemented)| !implemented)
+            .and_then(|(first, _)| *first);
+        let enters = kind == Kind::Class && context_managers.contains(&(name.clone(), lineno));
+        let collected = is_test && testing::is_collected(&name, kind == Kind::Class);
+        scanned.functions.push(Function {
+            name,
+            kind,
+            location: (entry.path().to_owned(), lineno + 1),
+            lines,
+            complexity,
+            decorators,
+            enters,
+            root: exempt || (is_entry_module && top_level.contains(&lineno)) || collected,
+            dynamic,
+            test: is_test,
+            body,
+            guarded_mentions,
+            unimplemented_overload,
+        });
+    }
+
+    // context manager classes are checked even when other classes aren't.
+    if options.kinds.contains(&Kind::Class) {
+        context_managers.clear();
+    }
+    let other_declarations = context_managers
+        .into_iter()
+        .map(|(name, lineno)| (name, Kind::Class, lineno))
+        .chain(
+            constants
+                .into_iter()
+                .map(|(name, lineno)| (name, Kind::Constant, lineno)),
+        );
+    for (name, kind, lineno) in other_declarations {
+        if suppressions.contains(&lineno) || !should_consider_function(&name, &[], options) {
+            continue;
+        }
+        scanned.functions.push(Function {
+            guarded_mentions: guarded_mentions(&name),
+            name,
+            kind,
+            location: (entry.path().to_owned(), lineno + 1),
+            lines: blocks.length(lineno),
+            complexity: None,
+            decorators: Vec::new(),
+            enters: kind == Kind::Class,
+            root: false,
+            dynamic,
+            test: is_test,
+            body: None,
+            unimplemented_overload: None,
+        });
+    }
+
+    // a module `__getattr__` (PEP 562) usually serves names listed in strings.
+    scanned.prose_is_code = lazy_exports;
+    scanned.is_test = is_test;
+    scanned.imports_preset = imports_preset;
+    Ok(scanned)
+}
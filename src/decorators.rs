@@ -0,0 +1,56 @@
+/// Collects the decorators applied to the next definition, including those whose arguments span lines.
+pub struct Decorators {
+    pending: Vec<String>,
+    depth: usize,
+}
+
+impl Decorators {
+    pub fn new() -> Self {
+        Self {
+            pending: Vec::new(),
+            depth: 0,
+        }
+    }
+
+    /// Feeds a line of code that is not itself a definition.
+    pub fn observe(&mut self, code: &str) {
+        let trimmed = code.trim();
+        if self.depth == 0 {
+            if let Some(decorator) = trimmed.strip_prefix('@') {
+                // `@ name` is as valid as `@name`, if unusual.
+                let name = decorator
+                    .trim_start()
+                    .split(|c: char| c == '(' || c.is_whitespace())
+                    .next()
+                    .unwrap_or_default();
+                self.pending.push(name.to_owned());
+            } else if !trimmed.is_empty() {
+                self.pending.clear();
+                return;
+            }
+        }
+
+        for c in trimmed.chars() {
+            match c {
+                '(' | '[' | '{' => self.depth += 1,
+                ')' | ']' | '}' => self.depth = self.depth.saturating_sub(1),
+                _ => {}
+            }
+        }
+    }
+
+    /// Returns the decorators seen since the last definition.
+    pub fn take(&mut self) -> Vec<String> {
+        self.depth = 0;
+        std::mem::take(&mut self.pending)
+    }
+}
+
+/// Whether the decorator is the given name, possibly qualified by a module, e.g. `sa.event.listens_for`.
+pub fn matches(decorator: &str, name: &str) -> bool {
+    decorator == name
+        || decorator
+            .strip_suffix(name)
+            .map(|prefix| prefix.ends_with('.'))
+            .unwrap_or(false)
+}
@@ -0,0 +1,47 @@
+use std::{io::BufRead, path::Path};
+
+use anyhow::Result;
+
+use crate::source;
+
+/// Returns the first line of a python module's docstring, if it starts with one.
+pub fn summary(path: &Path) -> Result<Option<String>> {
+    let mut lines = source::open(path)?.lines();
+
+    // the docstring must be the first statement.
+    let first = loop {
+        match lines.next().transpose()? {
+            Some(line) if line.trim().is_empty() || line.trim_start().starts_with('#') => {}
+            Some(line) => break line,
+            None => return Ok(None),
+        }
+    };
+
+    let statement = first.trim().trim_start_matches(['r', 'R', 'u', 'U']);
+    let quote = match ["\"\"\"", "'''", "\"", "'"]
+        .into_iter()
+        .find(|quote| statement.starts_with(quote))
+    {
+        Some(quote) => quote,
+        None => return Ok(None),
+    };
+
+    let rest = &statement[quote.len()..];
+    let text = match rest.split(quote).next().unwrap_or_default().trim() {
+        // the summary follows the opening quotes on the next line.
+        "" if quote.len() == 3 && !rest.contains(quote) => lines
+            .next()
+            .transpose()?
+            .map(|line| {
+                line.split(quote)
+                    .next()
+                    .unwrap_or_default()
+                    .trim()
+                    .to_owned()
+            })
+            .unwrap_or_default(),
+        text => text.to_owned(),
+    };
+
+    Ok(Some(text).filter(|text| !text.is_empty()))
+}
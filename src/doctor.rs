@@ -0,0 +1,105 @@
+use std::{fs, path::Path};
+
+use anyhow::Result;
+use tempfile::{tempfile, tempfile_in};
+use walkdir::WalkDir;
+
+use crate::{cli::Args, config::Config, owners::Owners, vcs, walk::is_useful};
+
+/// Directories that hold installed third-party code, whose references hide dead code in the project.
+const VENDORED_DIRECTORIES: [&str; 2] = ["site-packages", "node_modules"];
+
+/// Checks the environment and the tree for problems that would make a scan fail or mislead, printing a
+/// line for each check.
+pub fn run(args: &Args, path: &Path) -> Result<()> {
+    let root = path.canonicalize()?;
+    let mut warnings = 0;
+    let mut warn = |message: String| {
+        println!("warning: {}", message);
+        warnings += 1;
+    };
+
+    match args.config.clone().or_else(|| Config::find(&root)) {
+        Some(config) => match Config::load(&config) {
+            Ok(_) => println!("ok: using config {}", config.display()),
+            Err(err) => warn(format!("{:#}", err)),
+        },
+        None => println!(
+            "ok: no snapbug.toml in {}, so the defaults apply",
+            root.display()
+        ),
+    }
+
+    if let Some(owners) = args.owners_file.clone().or_else(|| Owners::find(&root)) {
+        if let Err(err) = Owners::load(&owners) {
+            warn(format!("{:#}", err));
+        }
+    }
+
+    // every scanned line is spilled to temporary files while counting.
+    match tempfile() {
+        Ok(_) => println!("ok: the temporary directory is writable"),
+        Err(err) => warn(format!(
+            "cannot create temporary files ({}), set TMPDIR to a writable directory",
+            err
+        )),
+    }
+
+    // scanned files are stored in the cache as they are read.
+    if let Some(cache_dir) = &args.cache_dir {
+        match fs::create_dir_all(cache_dir).and_then(|_| tempfile_in(cache_dir)) {
+            Ok(_) => println!(
+                "ok: the cache directory {} is writable",
+                cache_dir.display()
+            ),
+            Err(err) => warn(format!(
+                "cannot write to the cache directory {} ({}), which the scan would fail on",
+                cache_dir.display(),
+                err
+            )),
+        }
+    }
+
+    let vcs = vcs::detect(&root);
+    match vcs.root() {
+        Ok(toplevel) => println!("ok: inside the {} repository at {}", vcs.name(), toplevel),
+        Err(err) => warn(format!(
+            "{} is unavailable or this is not a repository, so archaeology and escalate_after won't work: {:#}",
+            vcs.name(),
+            err
+        )),
+    }
+
+    let mut walker = WalkDir::new(&root).sort_by_file_name().into_iter();
+    while let Some(entry) = walker.next() {
+        let entry = match entry {
+            Ok(entry) if entry.file_type().is_dir() => entry,
+            _ => continue,
+        };
+        if !is_useful(&entry, args.scan_scripts) {
+            walker.skip_current_dir();
+            continue;
+        }
+
+        let name = entry.file_name().to_string_lossy();
+        let relative = entry.path().strip_prefix(&root).unwrap_or(entry.path());
+        if entry.path().join("pyvenv.cfg").is_file() {
+            warn(format!(
+                "{} is a virtual environment, whose packages would be scanned too; move it out of the tree or rename it to start with a dot",
+                relative.display()
+            ));
+            walker.skip_current_dir();
+        } else if VENDORED_DIRECTORIES.contains(&name.as_ref()) {
+            warn(format!(
+                "{} holds third-party code, which would be scanned too; move it out of the tree",
+                relative.display()
+            ));
+            walker.skip_current_dir();
+        }
+    }
+
+    if warnings == 0 {
+        println!("no problems found");
+    }
+    Ok(())
+}
@@ -0,0 +1,77 @@
+use std::{collections::BTreeMap, io::BufRead, path::Path};
+
+use anyhow::Result;
+use rayon::prelude::*;
+
+use crate::{extension_of, source, walk::SourceFile, ScanOptions};
+
+/// Prints how many files would be scanned in each top-level directory, and roughly how many declarations
+/// they hold, along with the entries that were skipped. Declarations are estimated by matching the
+/// definition patterns against each line, which is far cheaper than a scan.
+pub fn report(
+    files: &[SourceFile],
+    root: &Path,
+    options: &ScanOptions,
+    skipped: &BTreeMap<&'static str, usize>,
+) -> Result<()> {
+    let estimates = files
+        .par_iter()
+        .map(|entry| estimate_definitions(entry, options))
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut directories: BTreeMap<String, (usize, usize)> = BTreeMap::new();
+    for (entry, definitions) in files.iter().zip(estimates) {
+        let relative = entry.path().strip_prefix(root).unwrap_or(entry.path());
+        let totals = directories.entry(directory_of(relative)).or_default();
+        totals.0 += 1;
+        totals.1 += definitions;
+    }
+
+    println!("{:>8} {:>12}  directory", "files", "definitions");
+    for (directory, (files, definitions)) in &directories {
+        println!("{:>8} {:>12}  {}", files, definitions, directory);
+    }
+    let definitions: usize = directories
+        .values()
+        .map(|(_, definitions)| definitions)
+        .sum();
+    println!("{:>8} {:>12}  total", files.len(), definitions);
+    for (reason, count) in skipped {
+        println!("skipped {} {} entries", count, reason);
+    }
+    Ok(())
+}
+
+/// Counts the lines of a file that look like declarations.
+fn estimate_definitions(entry: &SourceFile, options: &ScanOptions) -> Result<usize> {
+    let extension = extension_of(entry.path());
+    let patterns: Vec<_> = options
+        .definitions
+        .iter()
+        .filter(|(pattern_extension, _)| pattern_extension == extension)
+        .map(|(_, pattern)| pattern)
+        .collect();
+    if patterns.is_empty() {
+        return Ok(0);
+    }
+
+    let mut count = 0;
+    for line in source::open(entry.path())?.lines() {
+        let line = line?;
+        if patterns.iter().any(|pattern| pattern.is_match(&line)) {
+            count += 1;
+        }
+    }
+    Ok(count)
+}
+
+/// The first path component under the root, or "." for files directly inside it.
+fn directory_of(relative: &Path) -> String {
+    match relative
+        .parent()
+        .and_then(|parent| parent.components().next())
+    {
+        Some(component) => component.as_os_str().to_string_lossy().into_owned(),
+        None => ".".to_owned(),
+    }
+}
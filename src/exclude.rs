@@ -0,0 +1,135 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use ignore::{gitignore::Gitignore, Match};
+use regex::Regex;
+use walkdir::DirEntry;
+
+use crate::{baseline::normalize, owners::pattern_to_regex};
+
+/// The ignore files looked for in each directory, with the reason given for the entries they ignore.
+/// `.snapbugignore` files always apply, while `.gitignore` files only apply if requested.
+const IGNORE_FILES: [(&str, &str); 2] = [
+    (".snapbugignore", "snapbugignored"),
+    (".gitignore", "gitignored"),
+];
+
+/// Paths left out of the scan, by gitignore-style pattern, by `.snapbugignore` files, and optionally by
+/// `.gitignore` files.
+#[derive(Default, Debug)]
+pub struct Exclusions {
+    patterns: Vec<Regex>,
+    gitignore: bool,
+}
+
+/// Checks the entries of a walk, in the depth-first order they are visited, keeping the ignore files
+/// of the directories above the current entry.
+pub struct Checker<'a> {
+    exclusions: &'a Exclusions,
+    root: PathBuf,
+    /// The ignore files that apply, each of which decides independently.
+    ignores: Vec<IgnoreStack>,
+}
+
+/// The ignore files of one name in the directories above the current entry, innermost last.
+struct IgnoreStack {
+    name: &'static str,
+    reason: &'static str,
+    files: Vec<(PathBuf, Gitignore)>,
+}
+
+impl IgnoreStack {
+    /// Whether the entry at the given path is ignored, dropping the files of directories that the walk has
+    /// left. The innermost file with an opinion decides, as in git.
+    fn check(&mut self, path: &Path, is_dir: bool) -> bool {
+        while let Some((directory, _)) = self.files.last() {
+            if path.starts_with(directory) {
+                break;
+            }
+            self.files.pop();
+        }
+        for (_, gitignore) in self.files.iter().rev() {
+            match gitignore.matched(path, is_dir) {
+                Match::Ignore(_) => return true,
+                Match::Whitelist(_) => return false,
+                Match::None => {}
+            }
+        }
+        false
+    }
+
+    /// Loads the ignore file in a directory being descended into, if there is one.
+    fn enter(&mut self, directory: &Path) {
+        let file = directory.join(self.name);
+        if file.is_file() {
+            // like git, lines that fail to parse are skipped rather than failing the scan.
+            let (gitignore, _) = Gitignore::new(file);
+            self.files.push((directory.to_owned(), gitignore));
+        }
+    }
+}
+
+impl Exclusions {
+    pub fn new(patterns: &[String], gitignore: bool) -> Result<Self> {
+        Ok(Self {
+            patterns: patterns
+                .iter()
+                .map(|pattern| pattern_to_regex(pattern))
+                .collect::<Result<_>>()?,
+            gitignore,
+        })
+    }
+
+    /// Starts checking the entries of a walk from the given root.
+    pub fn checker(&self, root: &Path) -> Checker<'_> {
+        Checker {
+            exclusions: self,
+            root: root.to_owned(),
+            ignores: IGNORE_FILES
+                .iter()
+                .take(if self.gitignore { 2 } else { 1 })
+                .map(|&(name, reason)| IgnoreStack {
+                    name,
+                    reason,
+                    files: Vec::new(),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl Checker<'_> {
+    /// Why the entry is excluded, if it is. Must be given every entry that is descended into.
+    pub fn check(&mut self, entry: &DirEntry) -> Option<&'static str> {
+        let path = entry.path();
+        let is_dir = entry.file_type().is_dir();
+        let relative = path.strip_prefix(&self.root).unwrap_or(path);
+
+        // directories match patterns ending with a slash, such as `migrations/`. The root never matches.
+        let mut normalized = normalize(relative);
+        if is_dir {
+            normalized.push('/');
+        }
+        if !relative.as_os_str().is_empty()
+            && self
+                .exclusions
+                .patterns
+                .iter()
+                .any(|pattern| pattern.is_match(&normalized))
+        {
+            return Some("excluded");
+        }
+
+        for ignores in &mut self.ignores {
+            if ignores.check(path, is_dir) {
+                return Some(ignores.reason);
+            }
+        }
+        if is_dir {
+            for ignores in &mut self.ignores {
+                ignores.enter(path);
+            }
+        }
+        None
+    }
+}
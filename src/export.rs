@@ -0,0 +1,207 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+    sync::Arc,
+};
+
+use anyhow::{anyhow, Result};
+use clap::ArgEnum;
+use parquet::{
+    data_type::{ByteArray, ByteArrayType, DataType, Int64Type},
+    file::{
+        properties::WriterProperties,
+        writer::{SerializedFileWriter, SerializedRowGroupWriter},
+    },
+    schema::parser::parse_message_type,
+};
+use rayon::prelude::*;
+
+use crate::{
+    atomic,
+    cli::Args,
+    count::count_segment,
+    declarations,
+    haystack::Needles,
+    load_config, scan_options, scanned_extensions,
+    walk::{self, search_root_files},
+    Function,
+};
+
+/// The parquet schema of the reference matrix, with one column per field of a `Reference`.
+const SCHEMA: &str = "
+message reference {
+    REQUIRED BYTE_ARRAY definition_path (UTF8);
+    REQUIRED INT64 definition_line;
+    REQUIRED BYTE_ARRAY name (UTF8);
+    REQUIRED BYTE_ARRAY kind (UTF8);
+    REQUIRED BYTE_ARRAY referencing_path (UTF8);
+    REQUIRED BYTE_ARRAY referencing_origin (UTF8);
+    REQUIRED INT64 code_mentions;
+    REQUIRED INT64 prose_mentions;
+}
+";
+
+/// How the reference matrix is written.
+#[derive(ArgEnum, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ExportFormat {
+    Csv,
+    Parquet,
+}
+
+/// One cell of the reference matrix: how often a file mentions a definition.
+struct Reference {
+    definition_path: String,
+    definition_line: i64,
+    name: String,
+    kind: &'static str,
+    referencing_path: String,
+    /// Where the mentions come from, such as `first-party` or `third-party`.
+    referencing_origin: &'static str,
+    code_mentions: i64,
+    prose_mentions: i64,
+}
+
+/// Scans the tree and writes a row for every file that mentions each definition, including the file
+/// declaring it. Files that never mention a definition have no row for it.
+pub fn run(args: &Args, path: &Path, format: ExportFormat, output: Option<&Path>) -> Result<()> {
+    if format == ExportFormat::Parquet && output.is_none() {
+        return Err(anyhow!(
+            "parquet can only be written to a file, given with --output"
+        ));
+    }
+
+    let root = path.canonicalize()?;
+    let config = load_config(args.config.as_deref(), &root)?;
+    let options = scan_options(&args.options(), &config)?;
+    let (mut files, _) = walk::files(&root, &options);
+    files.extend(search_root_files(
+        &root,
+        &scanned_extensions(&options),
+        &options,
+    ));
+
+    let declared = declarations::collect(&files, &options)?;
+    let haystack = &declared.haystack;
+
+    let mut ordered: Vec<&Function> = declared.functions.iter().collect();
+    ordered.sort_by_key(|function| function.sort_key());
+    let needles = Needles::new(ordered.iter().map(|function| function.name.as_str()));
+
+    let relative = |path: &Path| {
+        path.strip_prefix(&root)
+            .unwrap_or(path)
+            .display()
+            .to_string()
+    };
+    let counts = haystack
+        .segments()
+        .par_iter()
+        .map(|segment| count_segment(haystack, segment, &ordered, &needles))
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut references = Vec::new();
+    for ((entry, segment), counts) in files.iter().zip(haystack.segments()).zip(counts) {
+        let referencing_path = relative(entry.path());
+        for function in &ordered {
+            if let Some(mentions) = counts.get(function) {
+                references.push(Reference {
+                    definition_path: relative(&function.location.0),
+                    definition_line: function.location.1 as i64,
+                    name: function.name.clone(),
+                    kind: function.kind.name(),
+                    referencing_path: referencing_path.clone(),
+                    referencing_origin: segment.origin().name(),
+                    code_mentions: mentions.code as i64,
+                    prose_mentions: mentions.prose as i64,
+                });
+            }
+        }
+    }
+
+    match (format, output) {
+        (ExportFormat::Csv, Some(output)) => atomic::write_with(output, |file| {
+            write_csv(&mut BufWriter::new(file), &references)
+        }),
+        (ExportFormat::Csv, None) => write_csv(&mut io::stdout().lock(), &references),
+        (ExportFormat::Parquet, Some(output)) => {
+            atomic::write_with(output, |file| write_parquet(file.try_clone()?, &references))
+        }
+        (ExportFormat::Parquet, None) => unreachable!("parquet requires an output file"),
+    }
+}
+
+/// Quotes a CSV field if it contains a separator, quote or line break.
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_owned()
+    }
+}
+
+fn write_csv(writer: &mut impl Write, references: &[Reference]) -> Result<()> {
+    writeln!(
+        writer,
+        "definition_path,definition_line,name,kind,referencing_path,referencing_origin,code_mentions,\
+         prose_mentions"
+    )?;
+    for reference in references {
+        writeln!(
+            writer,
+            "{},{},{},{},{},{},{},{}",
+            csv_field(&reference.definition_path),
+            reference.definition_line,
+            csv_field(&reference.name),
+            reference.kind,
+            csv_field(&reference.referencing_path),
+            reference.referencing_origin,
+            reference.code_mentions,
+            reference.prose_mentions,
+        )?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Writes every reference into a single row group.
+fn write_parquet(file: File, references: &[Reference]) -> Result<()> {
+    let schema = Arc::new(parse_message_type(SCHEMA)?);
+    let properties = Arc::new(WriterProperties::builder().build());
+    let mut writer = SerializedFileWriter::new(file, schema, properties)?;
+    let mut group = writer.next_row_group()?;
+
+    let text = |field: fn(&Reference) -> &str| {
+        references
+            .iter()
+            .map(|reference| ByteArray::from(field(reference)))
+            .collect::<Vec<_>>()
+    };
+    let number = |field: fn(&Reference) -> i64| references.iter().map(field).collect::<Vec<_>>();
+
+    write_column::<ByteArrayType>(&mut group, &text(|r| &r.definition_path))?;
+    write_column::<Int64Type>(&mut group, &number(|r| r.definition_line))?;
+    write_column::<ByteArrayType>(&mut group, &text(|r| &r.name))?;
+    write_column::<ByteArrayType>(&mut group, &text(|r| r.kind))?;
+    write_column::<ByteArrayType>(&mut group, &text(|r| &r.referencing_path))?;
+    write_column::<ByteArrayType>(&mut group, &text(|r| r.referencing_origin))?;
+    write_column::<Int64Type>(&mut group, &number(|r| r.code_mentions))?;
+    write_column::<Int64Type>(&mut group, &number(|r| r.prose_mentions))?;
+
+    group.close()?;
+    writer.close()?;
+    Ok(())
+}
+
+/// Writes the values of the next column in the schema.
+fn write_column<T: DataType>(
+    group: &mut SerializedRowGroupWriter<File>,
+    values: &[T::T],
+) -> Result<()> {
+    let mut column = group
+        .next_column()?
+        .ok_or_else(|| anyhow!("the parquet schema has too few columns"))?;
+    column.typed::<T>().write_batch(values, None, None)?;
+    column.close()?;
+    Ok(())
+}
@@ -0,0 +1,210 @@
+use std::{collections::HashSet, path::Path};
+
+use anyhow::Result;
+use regex::Regex;
+
+use crate::{
+    extension_of, lines::ChunkedLines, source, walk::SourceFile, Function, Kind, PYTHON_EXTENSIONS,
+};
+
+/// Patterns for reading a module's exports and the names it binds, compiled once for all files.
+pub struct Patterns {
+    all_pattern: Regex,
+    def_pattern: Regex,
+    import_pattern: Regex,
+    from_pattern: Regex,
+    assignment_pattern: Regex,
+    /// Modules that bind names we can't see.
+    dynamic_pattern: Regex,
+}
+
+impl Patterns {
+    pub fn new() -> Result<Self> {
+        Ok(Self {
+            all_pattern: Regex::new(r"^__all__\s*(?:\+?=|\.(?:append|extend)\s*\()")?,
+            def_pattern: Regex::new(r"^(\s*)(?:async\s+def|def|class)\s+([^\W\d]\w*)")?,
+            import_pattern: Regex::new(r"^\s*import\s+(.+)$")?,
+            from_pattern: Regex::new(r"^\s*from\s+\S+\s+import\s+(.+)$")?,
+            assignment_pattern: Regex::new(r"^\s*([^\W\d]\w*)\s*(?::[^=]*)?=[^=]")?,
+            dynamic_pattern: Regex::new(r"^def\s+__getattr__\s*\(|\bglobals\s*\(\s*\)")?,
+        })
+    }
+}
+
+/// Reads the string literals of an `__all__` statement, which may span lines.
+#[derive(Default)]
+struct Exports {
+    /// Each name listed, with the zero-based line it is on.
+    names: Vec<(String, usize)>,
+    depth: usize,
+    quote: Option<char>,
+    current: String,
+    /// Whether the statement continues on the next line.
+    open: bool,
+    /// Whether anything besides string literals was exported, such as another module's `__all__`.
+    dynamic: bool,
+}
+
+impl Exports {
+    /// Feeds the rest of a line of the statement.
+    fn observe(&mut self, text: &str, line: usize) {
+        for c in text.chars() {
+            if let Some(quote) = self.quote {
+                if c == quote {
+                    self.names.push((std::mem::take(&mut self.current), line));
+                    self.quote = None;
+                } else {
+                    self.current.push(c);
+                }
+                continue;
+            }
+            match c {
+                '\'' | '"' => self.quote = Some(c),
+                '[' | '(' => self.depth += 1,
+                ']' | ')' => self.depth = self.depth.saturating_sub(1),
+                '#' => break,
+                ',' | '+' | '\\' => {}
+                c if c.is_whitespace() => {}
+                _ => self.dynamic = true,
+            }
+        }
+        self.open = self.depth > 0 || text.trim_end().ends_with('\\');
+    }
+}
+
+/// Finds the mismatches between a python module's `__all__` and its definitions: names listed that the
+/// module never binds, and public top-level functions and classes that are missing from the list.
+/// Modules that bind names dynamically or star-import aren't checked for the former, and modules that
+/// build `__all__` from other lists aren't checked for the latter.
+pub fn mismatches(entry: &SourceFile, patterns: &Patterns) -> Result<Vec<Function>> {
+    if !PYTHON_EXTENSIONS.contains(&extension_of(entry.path())) {
+        return Ok(Vec::new());
+    }
+
+    let mut exports = Exports::default();
+    let mut found = false;
+    let mut bound = HashSet::new();
+    let mut public = Vec::new();
+    let mut pending_import = false;
+    let mut star_import = false;
+    let mut dynamic = false;
+
+    let mut lineno = 0;
+    for chunk in ChunkedLines::new(source::open(entry.path())?) {
+        let chunk = chunk?;
+        let line = &chunk.text;
+        if exports.open {
+            exports.observe(line, lineno);
+        } else if chunk.starts_line {
+            if let Some(statement) = patterns.all_pattern.find(line) {
+                found = true;
+                if statement.as_str().ends_with('(') {
+                    exports.depth += 1;
+                }
+                exports.observe(&line[statement.end()..], lineno);
+            } else if let Some(captures) = patterns.def_pattern.captures(line) {
+                let name = captures[2].to_owned();
+                if captures[1].is_empty() && !name.starts_with('_') {
+                    let kind = if line.trim_start().starts_with("class") {
+                        Kind::Class
+                    } else {
+                        Kind::Function
+                    };
+                    public.push((name.clone(), kind, lineno));
+                }
+                bound.insert(name);
+            } else if pending_import {
+                bind_imported(line, &mut bound);
+                pending_import = !line.contains(')');
+            } else if let Some(captures) = patterns.from_pattern.captures(line) {
+                star_import |= captures[1].trim() == "*";
+                bind_imported(&captures[1], &mut bound);
+                pending_import = captures[1].trim_start().starts_with('(') && !line.contains(')');
+            } else if let Some(captures) = patterns.import_pattern.captures(line) {
+                for module in captures[1].split(',') {
+                    let mut parts = module.split(" as ");
+                    let module = parts.next().unwrap_or_default().trim();
+                    let name = match parts.next() {
+                        Some(alias) => alias.trim(),
+                        None => module.split('.').next().unwrap_or_default(),
+                    };
+                    bound.insert(name.to_owned());
+                }
+            } else if let Some(captures) = patterns.assignment_pattern.captures(line) {
+                bound.insert(captures[1].to_owned());
+            }
+            dynamic |= patterns.dynamic_pattern.is_match(line);
+        }
+        if chunk.ends_line {
+            lineno += 1;
+        }
+    }
+    if !found {
+        return Ok(Vec::new());
+    }
+
+    let path = entry.path();
+    let is_package = path.file_stem() == Some("__init__".as_ref());
+    let listed: HashSet<&str> = exports
+        .names
+        .iter()
+        .map(|(name, _)| name.as_str())
+        .collect();
+    let mut mismatches = Vec::new();
+    if !dynamic && !star_import {
+        let mut reported = HashSet::new();
+        for (name, line) in &exports.names {
+            // a package may list its submodules, which `from package import *` imports.
+            let is_submodule = is_package && {
+                let directory = path.parent().unwrap_or(path);
+                directory.join(name).is_dir() || directory.join(format!("{}.py", name)).is_file()
+            };
+            if !bound.contains(name) && !is_submodule && reported.insert(name) {
+                mismatches.push(mismatch(name, Kind::Export, path, *line));
+            }
+        }
+    }
+    if !exports.dynamic {
+        // overloads declare a name several times, but it is missing once.
+        let mut reported = HashSet::new();
+        for (name, kind, line) in public {
+            if !listed.contains(name.as_str()) && reported.insert(name.clone()) {
+                mismatches.push(mismatch(&name, kind, path, line));
+            }
+        }
+    }
+    Ok(mismatches)
+}
+
+/// Adds the names bound by the imported part of a `from` import, such as `a, b as c`.
+fn bind_imported(names: &str, bound: &mut HashSet<String>) {
+    for name in names.split(',') {
+        let name = name
+            .split(" as ")
+            .last()
+            .unwrap_or_default()
+            .trim_matches(|c: char| c.is_whitespace() || c == '(' || c == ')' || c == '\\');
+        if !name.is_empty() && name != "*" {
+            bound.insert(name.to_owned());
+        }
+    }
+}
+
+/// A declaration standing in for a mismatch, given its zero-based line.
+fn mismatch(name: &str, kind: Kind, path: &Path, line: usize) -> Function {
+    Function {
+        name: name.to_owned(),
+        kind,
+        location: (path.to_owned(), line + 1),
+        lines: 1,
+        complexity: None,
+        decorators: Vec::new(),
+        enters: false,
+        root: false,
+        dynamic: false,
+        test: false,
+        body: None,
+        guarded_mentions: 0,
+        unimplemented_overload: None,
+    }
+}
@@ -0,0 +1,49 @@
+use clap::ArgEnum;
+
+/// Frameworks that invoke decorated functions themselves.
+#[derive(ArgEnum, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Framework {
+    /// Event listeners, validators, and hybrid attributes.
+    Sqlalchemy,
+    /// Signal receivers, admin registrations and template tags.
+    Django,
+    /// Tasks run by workers.
+    Celery,
+    /// Tasks and DAGs run by the scheduler.
+    Airflow,
+    /// Flows and tasks.
+    Prefect,
+    /// Assets, ops and jobs.
+    Dagster,
+}
+
+impl Framework {
+    /// Decorators marking functions that the framework calls on our behalf.
+    pub fn decorators(&self) -> &'static [&'static str] {
+        match self {
+            Framework::Sqlalchemy => &[
+                "event.listens_for",
+                "listens_for",
+                "validates",
+                "hybrid_property",
+                "hybrid_method",
+                "declared_attr",
+                "reconstructor",
+            ],
+            Framework::Django => &[
+                "receiver",
+                "admin.register",
+                "admin.action",
+                "admin.display",
+                "register.filter",
+                "register.simple_tag",
+                "register.inclusion_tag",
+                "register.tag",
+            ],
+            Framework::Celery => &["shared_task", "app.task"],
+            Framework::Airflow => &["task", "dag"],
+            Framework::Prefect => &["flow", "task"],
+            Framework::Dagster => &["asset", "op", "job"],
+        }
+    }
+}
@@ -0,0 +1,217 @@
+use std::{
+    collections::{HashMap, HashSet},
+    io::{BufReader, Read},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+use anyhow::{anyhow, Context, Result};
+
+use crate::vcs::{self, Blame, Vcs, LOG_MARKER};
+
+/// Runs git in the given directory, returning its standard output.
+fn git(directory: &Path, args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(directory)
+        .args(args)
+        .output()
+        .context("failed to run git")?;
+
+    if !output.status.success() {
+        return Err(anyhow!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Selects the commits that add or remove a line mentioning the name as a whole identifier, which `-S`
+/// would also select for names that merely contain it.
+fn pickaxe(name: &str) -> String {
+    format!(r"-G\b{}\b", regex::escape(name))
+}
+
+/// A git repository, run from the given directory.
+pub struct Git {
+    directory: PathBuf,
+}
+
+impl Git {
+    pub fn new(directory: PathBuf) -> Self {
+        Self { directory }
+    }
+}
+
+impl Vcs for Git {
+    fn name(&self) -> &'static str {
+        "git"
+    }
+
+    fn root(&self) -> Result<String> {
+        Ok(git(&self.directory, &["rev-parse", "--show-toplevel"])?
+            .trim()
+            .to_owned())
+    }
+
+    fn head(&self) -> &'static str {
+        "HEAD"
+    }
+
+    fn mentions_at(&self, revision: &str, name: &str) -> usize {
+        // git grep exits unsuccessfully when nothing matches. Like the scan, only whole identifiers count.
+        git(
+            &self.directory,
+            &["grep", "-o", "-w", "-F", name, revision, "--", "*.py"],
+        )
+        .map(|matches| matches.lines().count())
+        .unwrap_or_default()
+    }
+
+    fn describe(&self, revision: &str) -> Result<String> {
+        Ok(git(
+            &self.directory,
+            &["log", "-1", "--format=%h %as %an: %s", revision],
+        )?
+        .trim()
+        .to_owned())
+    }
+
+    fn changes(&self, name: &str) -> Result<Vec<String>> {
+        let pickaxe = pickaxe(name);
+        let commits = git(
+            &self.directory,
+            &["log", "--format=%H", &pickaxe, "--", "*.py"],
+        )?;
+        Ok(commits.lines().map(str::to_owned).collect())
+    }
+
+    fn parent(&self, revision: &str) -> Option<String> {
+        let parent = format!("{}^", revision);
+        git(
+            &self.directory,
+            &["rev-parse", "--verify", "--quiet", &parent],
+        )
+        .ok()
+        .map(|_| parent)
+    }
+
+    fn last_changed(&self, names: &[&str]) -> Result<HashMap<String, i64>> {
+        // the log is read as it is written, as the whole history of a large repository is too big to hold.
+        let format = format!("--format={}%ct", LOG_MARKER);
+        let mut child = Command::new("git")
+            .arg("-C")
+            .arg(&self.directory)
+            .args(["log", "-p", "-U0", "--no-color", &format, "--", "*.py"])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .context("failed to run git")?;
+        let stdout = child.stdout.take().expect("stdout is piped");
+        let changed = vcs::last_changed(BufReader::new(stdout), names);
+        let wanted: HashSet<&str> = names.iter().copied().collect();
+        if changed
+            .as_ref()
+            .is_ok_and(|changed| changed.len() == wanted.len())
+        {
+            // everything was found, so the rest of history isn't needed.
+            let _ = child.kill();
+            let _ = child.wait();
+            return changed;
+        }
+
+        let status = child.wait()?;
+        if !status.success() {
+            let mut stderr = String::new();
+            if let Some(mut pipe) = child.stderr.take() {
+                pipe.read_to_string(&mut stderr)?;
+            }
+            return Err(anyhow!("git log failed: {}", stderr.trim()));
+        }
+        changed
+    }
+
+    fn changed_files(&self, since: &str) -> Result<Vec<PathBuf>> {
+        let changed = git(
+            &self.directory,
+            &["diff", "--name-only", "--diff-filter=AMR", since, "--"],
+        )?;
+        let untracked = git(
+            &self.directory,
+            &["ls-files", "--others", "--exclude-standard", "--full-name"],
+        )?;
+        Ok(changed
+            .lines()
+            .chain(untracked.lines())
+            .map(PathBuf::from)
+            .collect())
+    }
+
+    fn blame(&self, path: &Path, line: usize) -> Result<Option<Blame>> {
+        let range = format!("{},{}", line, line);
+        let path = path.to_string_lossy();
+        let porcelain = git(
+            &self.directory,
+            &["blame", "--porcelain", "-L", &range, "--", &path],
+        )?;
+
+        let mut lines = porcelain.lines();
+        // uncommitted lines are blamed on a commit of zeros.
+        let commit = lines.next().unwrap_or_default();
+        if commit.starts_with("0000000000") {
+            return Ok(None);
+        }
+        let (mut author, mut timestamp) = (None, None);
+        for line in lines {
+            if let Some(name) = line.strip_prefix("author ") {
+                author = Some(name.to_owned());
+            } else if let Some(time) = line.strip_prefix("author-time ") {
+                timestamp = Some(time.parse()?);
+            }
+        }
+        match (author, timestamp) {
+            (Some(author), Some(timestamp)) => Ok(Some(Blame { author, timestamp })),
+            _ => Err(anyhow!("git blame printed no author for {}:{}", path, line)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    #[test]
+    fn changes_ignore_names_inside_longer_identifiers() {
+        let root = tempfile::tempdir().unwrap();
+        let commit = |source: &str| {
+            fs::write(root.path().join("lib.py"), source).unwrap();
+            git(root.path(), &["add", "lib.py"]).unwrap();
+            git(
+                root.path(),
+                &[
+                    "-c",
+                    "user.name=snapbug",
+                    "-c",
+                    "user.email=snapbug@localhost",
+                    "commit",
+                    "-q",
+                    "-m",
+                    "change",
+                ],
+            )
+            .unwrap();
+        };
+        git(root.path(), &["init", "-q"]).unwrap();
+        commit("def get():\n    pass\n");
+        commit("def get():\n    pass\n\n\ndef get_user():\n    pass\n");
+
+        let git = Git::new(root.path().to_owned());
+        assert_eq!(git.changes("get").unwrap().len(), 1);
+        assert_eq!(git.changes("get_user").unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,160 @@
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+};
+
+use anyhow::Result;
+use rayon::prelude::*;
+
+use crate::{
+    haystack::{Haystack, Needles},
+    Function,
+};
+
+/// Where a mention was found.
+#[derive(PartialEq, Eq, Hash, Debug)]
+enum Referrer<'a> {
+    /// Inside the body of the given outermost function.
+    Function(&'a Function),
+    /// Outside of any function in the given file.
+    Module(PathBuf),
+}
+
+/// Mentions of each function, by where they were found.
+type Referrers<'a> = HashMap<&'a Function, HashMap<Referrer<'a>, usize>>;
+
+/// Records where each function is mentioned.
+pub struct ReferenceGraph<'a> {
+    referrers: Referrers<'a>,
+}
+
+impl<'a> ReferenceGraph<'a> {
+    /// Attributes every mention of the given functions in the haystack, looking up each file's segment in
+    /// parallel.
+    pub fn build(haystack: &Haystack, functions: &'a HashSet<Function>) -> Result<Self> {
+        let mut by_file: HashMap<&Path, Vec<&Function>> = HashMap::new();
+        for function in functions {
+            by_file
+                .entry(function.location.0.as_path())
+                .or_default()
+                .push(function);
+        }
+        for file_functions in by_file.values_mut() {
+            file_functions.sort_by_key(|function| function.location.1);
+        }
+        let ordered: Vec<&Function> = functions.iter().collect();
+        let needles = Needles::new(ordered.iter().map(|function| function.name.as_str()));
+
+        let referrers = haystack
+            .segments()
+            .par_iter()
+            .map(|segment| -> Result<Referrers> {
+                let path = segment.path();
+                let file_functions = by_file.get(path).map(Vec::as_slice).unwrap_or_default();
+                let mut referrers: Referrers = HashMap::new();
+                for (index, line) in haystack.find(segment, &needles)? {
+                    let function = ordered[index];
+                    let enclosing = file_functions
+                        .iter()
+                        .find(|function| function.contains(path, line + 1))
+                        .copied();
+                    if enclosing == Some(function) {
+                        continue;
+                    }
+                    let referrer = match enclosing {
+                        Some(enclosing) => Referrer::Function(enclosing),
+                        None => Referrer::Module(path.to_owned()),
+                    };
+                    *referrers
+                        .entry(function)
+                        .or_default()
+                        .entry(referrer)
+                        .or_default() += 1;
+                }
+                Ok(referrers)
+            })
+            .try_reduce(HashMap::new, |mut referrers, other| {
+                for (function, other) in other {
+                    let found = referrers.entry(function).or_default();
+                    for (referrer, count) in other {
+                        *found.entry(referrer).or_default() += count;
+                    }
+                }
+                Ok(referrers)
+            })?;
+
+        Ok(Self { referrers })
+    }
+
+    /// Finds every function reachable from the roots, as well as from module-level code in the entry
+    /// modules, by following mentions from the bodies of reachable functions.
+    pub fn reachable(
+        &self,
+        roots: impl Iterator<Item = &'a Function>,
+        is_entry_module: impl Fn(&Path) -> bool,
+    ) -> HashSet<&'a Function> {
+        let mut reachable: HashSet<&Function> = roots.collect();
+
+        loop {
+            let found: Vec<&Function> = self
+                .referrers
+                .iter()
+                .filter(|(function, _)| !reachable.contains(*function))
+                .filter(|(_, referrers)| {
+                    referrers.keys().any(|referrer| match referrer {
+                        Referrer::Function(referrer) => reachable.contains(referrer),
+                        Referrer::Module(path) => is_entry_module(path),
+                    })
+                })
+                .map(|(function, _)| *function)
+                .collect();
+
+            if found.is_empty() {
+                break;
+            }
+            reachable.extend(found);
+        }
+
+        reachable
+    }
+
+    /// Orders the given dead functions, followed by everything that only they reference, so that each
+    /// deletion leaves no dangling references behind. Functions nested inside another are left out since
+    /// they are removed along with it. Expects `dead` to be sorted.
+    pub fn deletion_plan(&self, dead: &[&'a Function]) -> Vec<&'a Function> {
+        let mut removed: HashSet<&Function> = dead.iter().copied().collect();
+        let mut plan = dead.to_vec();
+
+        loop {
+            let mut round: Vec<&Function> = self
+                .referrers
+                .iter()
+                .filter(|(function, _)| !removed.contains(*function))
+                .filter(|(_, referrers)| {
+                    referrers.keys().all(|referrer| match referrer {
+                        Referrer::Function(referrer) => removed.contains(referrer),
+                        Referrer::Module(_) => false,
+                    })
+                })
+                .map(|(function, _)| *function)
+                .collect();
+
+            if round.is_empty() {
+                break;
+            }
+
+            round.sort_by_key(|function| function.sort_key());
+            removed.extend(round.iter().copied());
+            plan.extend(round);
+        }
+
+        plan.iter()
+            .copied()
+            .filter(|function| {
+                !plan.iter().any(|other| {
+                    other != function && other.contains(&function.location.0, function.location.1)
+                })
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,537 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{self, BufWriter, Seek, Write},
+    ops::Range,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Result;
+use memchr::memmem::Finder;
+use serde::{ser, Deserialize, Deserializer, Serialize, Serializer};
+use tempfile::tempfile;
+
+use crate::origins::Origin;
+
+/// How much of a segment is read at once when counting.
+const WINDOW: usize = 1024 * 1024;
+
+/// How many bytes of a file's text are held in memory before the rest is moved to a temporary file.
+pub const MAX_HELD: usize = 256 * 1024;
+
+/// The byte ranges in each haystack file holding a single source file.
+#[derive(Clone, Debug)]
+pub struct Segment {
+    code: Range<u64>,
+    prose: Range<u64>,
+    /// Whether mentions in the prose are counted as code, such as in a module exporting names lazily.
+    prose_is_code: bool,
+    /// Where the file's mentions come from.
+    origin: Origin,
+    /// The file the segment holds.
+    path: PathBuf,
+}
+
+impl Segment {
+    pub fn origin(&self) -> Origin {
+        self.origin
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+/// Names to count, most of which are identifiers and counted together by looking up every identifier in
+/// the text, so that a single pass finds them all. Any others are searched for one at a time.
+pub struct Needles {
+    /// The indices of the needles spelling each identifier, as several functions can share a name.
+    identifiers: HashMap<Vec<u8>, Vec<usize>>,
+    others: Vec<(usize, Finder<'static>)>,
+    len: usize,
+}
+
+/// Temporary files holding every scanned line, used later for counting references.
+/// Prose is only separated from code when comment stripping is enabled.
+pub struct Haystack {
+    code: File,
+    prose: File,
+    segments: Vec<Segment>,
+}
+
+/// One source file's code or prose on its way to the haystack. It is held in memory until it grows past
+/// `MAX_HELD` bytes, when it moves to a temporary file, so that a large file is never held whole.
+#[derive(Default)]
+pub struct Text {
+    held: String,
+    spilled: Option<BufWriter<File>>,
+}
+
+/// Appends lines to a haystack, one source file at a time.
+pub struct Writer<'a> {
+    code: BufWriter<&'a File>,
+    prose: BufWriter<&'a File>,
+    segments: &'a mut Vec<Segment>,
+    offsets: (u64, u64),
+}
+
+impl Needles {
+    pub fn new<'a>(names: impl IntoIterator<Item = &'a str>) -> Self {
+        let mut needles = Self {
+            identifiers: HashMap::new(),
+            others: Vec::new(),
+            len: 0,
+        };
+        for (index, name) in names.into_iter().enumerate() {
+            let name = name.as_bytes();
+            if !name.is_empty() && name.iter().copied().all(is_identifier_byte) {
+                needles
+                    .identifiers
+                    .entry(name.to_vec())
+                    .or_default()
+                    .push(index);
+            } else {
+                needles.others.push((index, Finder::new(name).into_owned()));
+            }
+            needles.len = index + 1;
+        }
+        needles
+    }
+}
+
+impl Text {
+    pub fn push_str(&mut self, text: &str) -> Result<()> {
+        if self.spilled.is_none() && self.held.len() + text.len() > MAX_HELD {
+            let mut spilled = BufWriter::new(tempfile()?);
+            spilled.write_all(std::mem::take(&mut self.held).as_bytes())?;
+            self.spilled = Some(spilled);
+        }
+        match &mut self.spilled {
+            Some(spilled) => spilled.write_all(text.as_bytes())?,
+            None => self.held.push_str(text),
+        }
+        Ok(())
+    }
+
+    /// Whether the text outgrew memory, and so can't be cached.
+    pub fn is_spilled(&self) -> bool {
+        self.spilled.is_some()
+    }
+
+    /// Copies the text to the given writer, returning how many bytes it held.
+    fn write_to(self, writer: &mut impl Write) -> Result<u64> {
+        match self.spilled {
+            Some(spilled) => {
+                let mut file = spilled.into_inner().map_err(|err| err.into_error())?;
+                file.rewind()?;
+                Ok(io::copy(&mut file, writer)?)
+            }
+            None => {
+                writer.write_all(self.held.as_bytes())?;
+                Ok(self.held.len() as u64)
+            }
+        }
+    }
+}
+
+impl From<&str> for Text {
+    fn from(text: &str) -> Self {
+        Self {
+            held: text.to_owned(),
+            spilled: None,
+        }
+    }
+}
+
+/// Text is cached as a string, which only text held in memory can be.
+impl Serialize for Text {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if self.is_spilled() {
+            return Err(ser::Error::custom(
+                "text moved to a temporary file can't be cached",
+            ));
+        }
+        self.held.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Text {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(Self {
+            held: String::deserialize(deserializer)?,
+            spilled: None,
+        })
+    }
+}
+
+impl Haystack {
+    pub fn new() -> Result<Self> {
+        Ok(Self {
+            code: tempfile()?,
+            prose: tempfile()?,
+            segments: Vec::new(),
+        })
+    }
+
+    pub fn writer(&mut self) -> Writer<'_> {
+        let offsets = self
+            .segments
+            .last()
+            .map(|segment| (segment.code.end, segment.prose.end))
+            .unwrap_or_default();
+
+        Writer {
+            code: BufWriter::new(&self.code),
+            prose: BufWriter::new(&self.prose),
+            segments: &mut self.segments,
+            offsets,
+        }
+    }
+
+    /// One segment per scanned file, in the order they were written.
+    pub fn segments(&self) -> &[Segment] {
+        &self.segments
+    }
+
+    /// Counts occurrences of each needle in the code and prose of a single segment,
+    /// reading it in bounded windows.
+    pub fn count(&self, segment: &Segment, needles: &Needles) -> Result<Vec<(usize, usize)>> {
+        let code = count_range(&self.code, &segment.code, needles)?;
+        let prose = count_range(&self.prose, &segment.prose, needles)?;
+        Ok(code
+            .into_iter()
+            .zip(prose)
+            .map(|(code, prose)| {
+                if segment.prose_is_code {
+                    (code + prose, 0)
+                } else {
+                    (code, prose)
+                }
+            })
+            .collect())
+    }
+
+    /// Finds every needle in the code of a single segment, as the needle's index and the zero-based line it
+    /// is on, reading it in bounded windows.
+    pub fn find(&self, segment: &Segment, needles: &Needles) -> Result<Vec<(usize, usize)>> {
+        let mut found = Vec::new();
+        find_range(&self.code, &segment.code, needles, &mut |index, line| {
+            found.push((index, line))
+        })?;
+        Ok(found)
+    }
+}
+
+impl Writer<'_> {
+    /// Appends the code and prose of one source file from the given origin, whose prose may be counted as
+    /// code.
+    pub fn file(
+        &mut self,
+        path: &Path,
+        code: Text,
+        prose: Text,
+        prose_is_code: bool,
+        origin: Origin,
+    ) -> Result<()> {
+        let starts = self.offsets;
+        self.offsets.0 += code.write_to(&mut self.code)?;
+        self.offsets.1 += prose.write_to(&mut self.prose)?;
+        self.segments.push(Segment {
+            code: starts.0..self.offsets.0,
+            prose: starts.1..self.offsets.1,
+            prose_is_code,
+            origin,
+            path: path.to_owned(),
+        });
+        Ok(())
+    }
+
+    /// Flushes everything written so far.
+    pub fn finish(mut self) -> Result<()> {
+        self.code.flush()?;
+        self.prose.flush()?;
+        Ok(())
+    }
+}
+
+/// Whether the needle found at the given index is a whole identifier, rather than part of a longer one,
+/// so that `get` is not mentioned by `get_user`.
+pub fn is_whole_word(text: &[u8], index: usize, needle: &[u8]) -> bool {
+    let joined = |edge: Option<&u8>, neighbour: Option<u8>| {
+        edge.copied().is_some_and(is_identifier_byte) && neighbour.is_some_and(is_identifier_byte)
+    };
+    let before = index.checked_sub(1).map(|before| text[before]);
+    let after = text.get(index + needle.len()).copied();
+    !joined(needle.first(), before) && !joined(needle.last(), after)
+}
+
+/// The identifiers in the text, along with anything else made of identifier bytes, such as numbers.
+pub fn identifiers(text: &str) -> impl Iterator<Item = &str> {
+    // bytes that split identifiers are all ascii, so every piece is still valid text.
+    text.split(|c: char| c.is_ascii() && !is_identifier_byte(c as u8))
+        .filter(|word| !word.is_empty())
+}
+
+/// Whether the byte can be part of a python or javascript identifier. Any non-ascii byte might be.
+fn is_identifier_byte(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || byte == b'_' || byte == b'$' || !byte.is_ascii()
+}
+
+/// Counts each needle within a byte range.
+fn count_range(file: &File, range: &Range<u64>, needles: &Needles) -> Result<Vec<usize>> {
+    let mut counts = vec![0; needles.len];
+    find_range(file, range, needles, &mut |index, _| counts[index] += 1)?;
+    Ok(counts)
+}
+
+/// Finds each needle within a byte range, calling back with the needle's index and its zero-based line.
+fn find_range(
+    file: &File,
+    range: &Range<u64>,
+    needles: &Needles,
+    found: &mut impl FnMut(usize, usize),
+) -> Result<()> {
+    find_identifiers(file, range, &needles.identifiers, found)?;
+    if !needles.others.is_empty() {
+        find_others(file, range, &needles.others, found)?;
+    }
+    Ok(())
+}
+
+/// Finds the identifiers in a byte range that are needles, one window at a time. An identifier running
+/// to the end of a window is carried into the next, which can see where it ends, unless it is already
+/// longer than every needle, such as in minified code, when the rest of it is skipped.
+fn find_identifiers(
+    file: &File,
+    range: &Range<u64>,
+    identifiers: &HashMap<Vec<u8>, Vec<usize>>,
+    found: &mut impl FnMut(usize, usize),
+) -> Result<()> {
+    let longest = identifiers.keys().map(Vec::len).max().unwrap_or_default();
+    let mut buffer = Vec::with_capacity(WINDOW + longest);
+    // whether the window starts in the middle of an identifier that was too long to carry.
+    let mut skipping = false;
+    let mut offset = range.start;
+    let mut line = 0;
+    let mut lookup = |word: &[u8], line: usize| {
+        for &index in identifiers.get(word).into_iter().flatten() {
+            found(index, line);
+        }
+    };
+
+    while offset < range.end {
+        let length = (range.end - offset).min(WINDOW as u64) as usize;
+        let start = buffer.len();
+        buffer.resize(start + length, 0);
+        read_at(file, &mut buffer[start..], offset)?;
+        offset += length as u64;
+
+        if skipping {
+            let end = buffer
+                .iter()
+                .position(|&byte| !is_identifier_byte(byte))
+                .unwrap_or(buffer.len());
+            buffer.drain(..end);
+            skipping = buffer.is_empty();
+        }
+
+        let complete = if offset >= range.end {
+            buffer.len()
+        } else {
+            buffer
+                .iter()
+                .rposition(|&byte| !is_identifier_byte(byte))
+                .map_or(0, |index| index + 1)
+        };
+        let mut start = 0;
+        for (end, &byte) in buffer[..complete].iter().enumerate() {
+            if !is_identifier_byte(byte) {
+                lookup(&buffer[start..end], line);
+                if byte == b'\n' {
+                    line += 1;
+                }
+                start = end + 1;
+            }
+        }
+        lookup(&buffer[start..complete], line);
+        buffer.drain(..complete);
+        if buffer.len() > longest {
+            buffer.clear();
+            skipping = true;
+        }
+    }
+
+    Ok(())
+}
+
+/// Finds each of the other needles within a byte range, one window at a time. The tail of each window is
+/// carried into the next so that matches straddling a boundary are found, and only found once.
+fn find_others(
+    file: &File,
+    range: &Range<u64>,
+    needles: &[(usize, Finder)],
+    found: &mut impl FnMut(usize, usize),
+) -> Result<()> {
+    // one byte more than the longest needle, so that the bytes around every match can be seen.
+    let overlap = needles
+        .iter()
+        .map(|(_, needle)| needle.needle().len() + 1)
+        .max()
+        .unwrap_or_default();
+
+    let mut buffer = Vec::with_capacity(WINDOW + overlap);
+    let mut carried = 0;
+    let mut offset = range.start;
+    // the line that the window starts on.
+    let mut line = 0;
+
+    while offset < range.end {
+        let length = (range.end - offset).min(WINDOW as u64) as usize;
+        let start = buffer.len();
+        buffer.resize(start + length, 0);
+        read_at(file, &mut buffer[start..], offset)?;
+        offset += length as u64;
+        let last = offset >= range.end;
+        let newlines: Vec<usize> = memchr::memchr_iter(b'\n', &buffer).collect();
+
+        for (index, needle) in needles {
+            let length = needle.needle().len();
+            // matches ending in the carried bytes were found with the previous window, while those
+            // ending with this one are left for the next, which can see the byte after them.
+            for start in needle
+                .find_iter(&buffer)
+                .filter(|start| {
+                    start + length >= carried && (last || start + length < buffer.len())
+                })
+                .filter(|&start| is_whole_word(&buffer, start, needle.needle()))
+            {
+                found(
+                    *index,
+                    line + newlines.partition_point(|&newline| newline < start),
+                );
+            }
+        }
+
+        let keep = overlap.min(buffer.len());
+        let drained = buffer.len() - keep;
+        line += newlines.partition_point(|&newline| newline < drained);
+        buffer.drain(..drained);
+        carried = keep;
+    }
+
+    Ok(())
+}
+
+/// Reads exactly enough bytes to fill the buffer without moving the file's cursor, so that
+/// segments can be read concurrently.
+fn read_at(file: &File, buffer: &mut [u8], offset: u64) -> Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::FileExt;
+        file.read_exact_at(buffer, offset)?;
+    }
+
+    #[cfg(windows)]
+    {
+        use std::os::windows::fs::FileExt;
+        let mut read = 0;
+        while read < buffer.len() {
+            let count = file.seek_read(&mut buffer[read..], offset + read as u64)?;
+            if count == 0 {
+                return Err(anyhow::anyhow!("haystack ended unexpectedly"));
+            }
+            read += count;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Counts the needles in code written to a fresh haystack.
+    fn count(code: &str, names: &[&str]) -> Vec<usize> {
+        let mut haystack = Haystack::new().unwrap();
+        let mut writer = haystack.writer();
+        writer
+            .file(
+                Path::new("a.py"),
+                code.into(),
+                "".into(),
+                false,
+                Origin::FirstParty,
+            )
+            .unwrap();
+        writer.finish().unwrap();
+        let counts = haystack
+            .count(
+                &haystack.segments()[0],
+                &Needles::new(names.iter().copied()),
+            )
+            .unwrap();
+        counts.into_iter().map(|(code, _)| code).collect()
+    }
+
+    #[test]
+    fn identifiers_split_between_windows_are_counted_once() {
+        let code = format!("{}get get_user", " ".repeat(WINDOW - 1));
+        assert_eq!(count(&code, &["get", "get_user"]), [1, 1]);
+    }
+
+    #[test]
+    fn needles_are_found_on_their_lines() {
+        let mut haystack = Haystack::new().unwrap();
+        let mut writer = haystack.writer();
+        let code = format!("get()\n{}\nx = get_user\nget-user get", " ".repeat(WINDOW));
+        writer
+            .file(
+                Path::new("a.py"),
+                code.as_str().into(),
+                "".into(),
+                false,
+                Origin::FirstParty,
+            )
+            .unwrap();
+        writer.finish().unwrap();
+        let needles = Needles::new(["get", "get_user", "get-user"]);
+        let mut found = haystack.find(&haystack.segments()[0], &needles).unwrap();
+        found.sort_unstable();
+        assert_eq!(found, [(0, 0), (0, 3), (0, 3), (1, 2), (2, 3)]);
+    }
+
+    #[test]
+    fn text_moved_to_a_temporary_file_is_counted_whole() {
+        let mut text = Text::default();
+        let line = "get_user(get)\n";
+        for _ in 0..MAX_HELD / line.len() + 1 {
+            text.push_str(line).unwrap();
+        }
+        assert!(text.is_spilled());
+        assert!(serde_json::to_string(&text).is_err());
+
+        let mut haystack = Haystack::new().unwrap();
+        let mut writer = haystack.writer();
+        writer
+            .file(
+                Path::new("a.py"),
+                text,
+                "get".into(),
+                false,
+                Origin::FirstParty,
+            )
+            .unwrap();
+        writer.finish().unwrap();
+        let counts = haystack
+            .count(&haystack.segments()[0], &Needles::new(["get"]))
+            .unwrap();
+        assert_eq!(counts, [(MAX_HELD / line.len() + 1, 1)]);
+    }
+
+    #[test]
+    fn identifiers_longer_than_a_window_are_skipped() {
+        let code = format!("{}get get", "x".repeat(3 * WINDOW));
+        assert_eq!(count(&code, &["get"]), [1]);
+    }
+}
@@ -0,0 +1,204 @@
+use std::{
+    collections::{HashMap, HashSet},
+    io::{BufReader, Read},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+use anyhow::{anyhow, Context, Result};
+
+use crate::vcs::{self, Blame, Vcs, LOG_MARKER};
+
+/// Limits commands to python files.
+const PYTHON_FILES: &str = "glob:**.py";
+
+/// Runs Mercurial in the given directory, returning its standard output.
+fn hg(directory: &Path, args: &[&str]) -> Result<String> {
+    let output = Command::new("hg")
+        .arg("--cwd")
+        .arg(directory)
+        .args(args)
+        .env("HGPLAIN", "1")
+        .output()
+        .context("failed to run hg")?;
+
+    if !output.status.success() {
+        return Err(anyhow!(
+            "hg {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// A Mercurial repository, run from the given directory.
+pub struct Mercurial {
+    directory: PathBuf,
+}
+
+impl Mercurial {
+    pub fn new(directory: PathBuf) -> Self {
+        Self { directory }
+    }
+}
+
+impl Vcs for Mercurial {
+    fn name(&self) -> &'static str {
+        "hg"
+    }
+
+    fn root(&self) -> Result<String> {
+        Ok(hg(&self.directory, &["root"])?.trim().to_owned())
+    }
+
+    fn head(&self) -> &'static str {
+        "."
+    }
+
+    fn mentions_at(&self, revision: &str, name: &str) -> usize {
+        // hg grep exits unsuccessfully when nothing matches, and takes a regex. Like the scan, only whole
+        // identifiers count.
+        hg(
+            &self.directory,
+            &[
+                "grep",
+                "-r",
+                revision,
+                &format!(r"\b{}\b", regex::escape(name)),
+                "-I",
+                PYTHON_FILES,
+            ],
+        )
+        .map(|matches| matches.lines().count())
+        .unwrap_or_default()
+    }
+
+    fn describe(&self, revision: &str) -> Result<String> {
+        Ok(hg(
+            &self.directory,
+            &[
+                "log",
+                "-r",
+                revision,
+                "--template",
+                "{node|short} {date|shortdate} {author|person}: {desc|firstline}",
+            ],
+        )?
+        .trim()
+        .to_owned())
+    }
+
+    fn changes(&self, name: &str) -> Result<Vec<String>> {
+        // nothing matching is not a failure here.
+        let matches = hg(
+            &self.directory,
+            &[
+                "grep",
+                "--diff",
+                "--template",
+                "{node}\n",
+                &format!(r"\b{}\b", regex::escape(name)),
+                "-I",
+                PYTHON_FILES,
+            ],
+        )
+        .unwrap_or_default();
+        let mut revisions: Vec<String> = Vec::new();
+        for revision in matches.lines() {
+            if !revisions.iter().any(|seen| seen == revision) {
+                revisions.push(revision.to_owned());
+            }
+        }
+        Ok(revisions)
+    }
+
+    fn parent(&self, revision: &str) -> Option<String> {
+        let revset = format!("p1({})", revision);
+        hg(
+            &self.directory,
+            &["log", "-r", &revset, "--template", "{node}"],
+        )
+        .ok()
+        .map(|parent| parent.trim().to_owned())
+        .filter(|parent| !parent.is_empty())
+    }
+
+    fn last_changed(&self, names: &[&str]) -> Result<HashMap<String, i64>> {
+        // the log is read as it is written, as the whole history of a large repository is too big to hold.
+        // The date is the seconds since the epoch, then the timezone's offset.
+        let template = format!("{}{{date|hgdate}}\n", LOG_MARKER);
+        let mut child = Command::new("hg")
+            .arg("--cwd")
+            .arg(&self.directory)
+            .args(["log", "-p", "--template", &template, "-I", PYTHON_FILES])
+            .env("HGPLAIN", "1")
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .context("failed to run hg")?;
+        let stdout = child.stdout.take().expect("stdout is piped");
+        let changed = vcs::last_changed(BufReader::new(stdout), names);
+        let wanted: HashSet<&str> = names.iter().copied().collect();
+        if changed
+            .as_ref()
+            .is_ok_and(|changed| changed.len() == wanted.len())
+        {
+            // everything was found, so the rest of history isn't needed.
+            let _ = child.kill();
+            let _ = child.wait();
+            return changed;
+        }
+
+        let status = child.wait()?;
+        if !status.success() {
+            let mut stderr = String::new();
+            if let Some(mut pipe) = child.stderr.take() {
+                pipe.read_to_string(&mut stderr)?;
+            }
+            return Err(anyhow!("hg log failed: {}", stderr.trim()));
+        }
+        changed
+    }
+
+    fn changed_files(&self, since: &str) -> Result<Vec<PathBuf>> {
+        // added, modified and unknown files, by their paths from the root.
+        let changed = hg(
+            &self.directory,
+            &["status", "--rev", since, "-amu", "--template", "{path}\n"],
+        )?;
+        Ok(changed.lines().map(PathBuf::from).collect())
+    }
+
+    fn blame(&self, path: &Path, line: usize) -> Result<Option<Blame>> {
+        // the working copy, so that line numbers match what was scanned.
+        let annotated = hg(
+            &self.directory,
+            &[
+                "annotate",
+                "-r",
+                "wdir()",
+                "--template",
+                "{lines % '{node}\t{date|hgdate}\t{user|person}\n'}",
+                &path.to_string_lossy(),
+            ],
+        )?;
+        let annotation = annotated
+            .lines()
+            .nth(line.saturating_sub(1))
+            .ok_or_else(|| anyhow!("hg annotate has no line {} in {}", line, path.display()))?;
+
+        let mut fields = annotation.splitn(3, '\t');
+        let node = fields.next().unwrap_or_default();
+        // uncommitted lines belong to the working copy's pseudo-revision of all fs.
+        if node.is_empty() || node.bytes().all(|byte| byte == b'f') {
+            return Ok(None);
+        }
+        let date = fields.next().unwrap_or_default();
+        Ok(Some(Blame {
+            timestamp: date.split_whitespace().next().unwrap_or_default().parse()?,
+            author: fields.next().unwrap_or_default().to_owned(),
+        }))
+    }
+}
@@ -0,0 +1,125 @@
+use std::{collections::HashSet, fs, path::Path};
+
+use anyhow::{anyhow, Context, Result};
+
+use crate::baseline::normalize;
+
+/// Findings to skip, listed one per line as `path:line:name`. A lighter-weight alternative to a
+/// baseline, for projects that don't need reasons or expiry dates.
+#[derive(Default, Debug)]
+pub struct IgnoreList {
+    /// Paths relative to the scanned root, with forward slashes.
+    entries: HashSet<(String, usize, String)>,
+}
+
+impl IgnoreList {
+    /// Parses the given ignore file, skipping blank lines and `#` comments. Paths may be relative to the
+    /// scanned root or, as printed in the text report, start with the path that was scanned.
+    pub fn load(path: &Path, scanned: &Path) -> Result<Self> {
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("failed to read ignore file {}", path.display()))?;
+
+        let mut entries = HashSet::new();
+        for (index, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            // split from the right, so that windows drive letters stay part of the path.
+            let mut parts = line.rsplitn(3, ':');
+            let (name, lineno, file) = match (parts.next(), parts.next(), parts.next()) {
+                (Some(name), Some(lineno), Some(file)) => (name, lineno, file),
+                _ => {
+                    return Err(anyhow!(
+                        "{}:{}: expected path:line:name",
+                        path.display(),
+                        index + 1
+                    ))
+                }
+            };
+            let lineno = lineno.parse().with_context(|| {
+                format!(
+                    "{}:{}: invalid line {:?}",
+                    path.display(),
+                    index + 1,
+                    lineno
+                )
+            })?;
+
+            let file = Path::new(file);
+            let relative = normalize(file.strip_prefix(scanned).unwrap_or(file));
+            entries.insert((
+                relative.trim_start_matches("./").to_owned(),
+                lineno,
+                name.to_owned(),
+            ));
+        }
+
+        Ok(Self { entries })
+    }
+
+    /// The path, line and name of each entry, in order.
+    pub fn entries(&self) -> Vec<(&str, usize, &str)> {
+        let mut entries: Vec<_> = self
+            .entries
+            .iter()
+            .map(|(path, line, name)| (path.as_str(), *line, name.as_str()))
+            .collect();
+        entries.sort_unstable();
+        entries
+    }
+
+    /// Whether the named function declared on the given line of a file, relative to the root, is ignored.
+    pub fn contains(&self, relative: &Path, line: usize, name: &str) -> bool {
+        self.entries
+            .contains(&(normalize(relative), line, name.to_owned()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn load(contents: &str, scanned: &Path) -> Result<IgnoreList> {
+        let root = tempfile::Builder::new()
+            .prefix("snapbug")
+            .tempdir()
+            .unwrap();
+        let path = root.path().join("snapbug.ignore");
+        fs::write(&path, contents).unwrap();
+        IgnoreList::load(&path, scanned)
+    }
+
+    #[test]
+    fn entries_may_start_with_the_scanned_path() {
+        let ignored = load(
+            "# reviewed\nsrc/project/pkg/a.py:3:helper\n\n./b.py:10:Job\n",
+            Path::new("src/project"),
+        )
+        .unwrap();
+        assert_eq!(
+            ignored.entries(),
+            [("b.py", 10, "Job"), ("pkg/a.py", 3, "helper")]
+        );
+        assert!(ignored.contains(Path::new("pkg/a.py"), 3, "helper"));
+        assert!(!ignored.contains(Path::new("pkg/a.py"), 4, "helper"));
+        assert!(!ignored.contains(Path::new("pkg/a.py"), 3, "other"));
+    }
+
+    #[test]
+    fn malformed_entries_name_their_line() {
+        let err = load("a.py:1:helper\na.py:helper\n", Path::new(".")).unwrap_err();
+        assert!(
+            err.to_string().ends_with(":2: expected path:line:name"),
+            "{}",
+            err
+        );
+        let err = load("a.py:one:helper\n", Path::new(".")).unwrap_err();
+        assert!(
+            err.to_string().ends_with(":1: invalid line \"one\""),
+            "{}",
+            err
+        );
+    }
+}
@@ -0,0 +1,57 @@
+use clap::ArgEnum;
+
+use crate::PYTHON_EXTENSIONS;
+
+/// Extensions of javascript and typescript sources.
+const JAVASCRIPT_EXTENSIONS: [&str; 6] = ["js", "jsx", "mjs", "cjs", "ts", "tsx"];
+
+/// Languages whose declarations can be scanned for.
+#[derive(ArgEnum, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Language {
+    Python,
+    /// Javascript and typescript, whose comments and strings are never stripped.
+    Javascript,
+}
+
+impl Language {
+    pub const ALL: [Language; 2] = [Language::Python, Language::Javascript];
+
+    /// Extensions of the files written in this language.
+    pub fn extensions(&self) -> &'static [&'static str] {
+        match self {
+            Language::Python => &PYTHON_EXTENSIONS,
+            Language::Javascript => &JAVASCRIPT_EXTENSIONS,
+        }
+    }
+
+    /// Patterns declaring functions, each capturing the function name in its first group. Python's only
+    /// apply to modules that don't parse, such as python 2 code.
+    pub fn definition_patterns(&self) -> &'static [&'static str] {
+        match self {
+            Language::Python => &[
+                r"^\s*(?:async\s+)?def\s+([^\W\d]\w*)\s*[(\[]",
+                r"^\s*([^\W\d]\w*)\s*(?::[^=]*)?=\s*lambda\b",
+            ],
+            Language::Javascript => &[
+                r"^\s*(?:export\s+)?(?:default\s+)?(?:async\s+)?function\s*\*?\s*([A-Za-z_$][\w$]*)\s*\(",
+                r"^\s*(?:export\s+)?(?:const|let|var)\s+([A-Za-z_$][\w$]*)\s*=\s*(?:async\s+)?(?:function\b|\([^)]*\)\s*=>|[A-Za-z_$][\w$]*\s*=>)",
+            ],
+        }
+    }
+
+    /// Prepended to the rules reporting findings in this language. Python rules keep their original,
+    /// unprefixed names.
+    pub fn rule_prefix(&self) -> &'static str {
+        match self {
+            Language::Python => "",
+            Language::Javascript => "javascript/",
+        }
+    }
+
+    /// The language of files with the given extension, if it is one we know.
+    pub fn of(extension: &str) -> Option<Language> {
+        Language::ALL
+            .into_iter()
+            .find(|language| language.extensions().contains(&extension))
+    }
+}
@@ -0,0 +1,1100 @@
+use std::{
+    collections::{HashMap, HashSet},
+    ffi::OsStr,
+    fmt,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use clap::ArgEnum;
+use rayon::prelude::*;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    cache::Cache,
+    config::Config,
+    count::Mentions,
+    declarations::Declared,
+    exclude::Exclusions,
+    graph::ReferenceGraph,
+    origins::Weights,
+    presets::PresetMethods,
+    walk::{search_root_files, SourceFile},
+};
+pub use crate::{
+    cli::run, frameworks::Framework, languages::Language, presets::Preset, targets::BuildSystem,
+};
+
+mod archaeology;
+mod atomic;
+mod baseline;
+mod cache;
+mod calls;
+mod caps;
+mod classes;
+mod cli;
+mod config;
+pub mod count;
+pub mod declarations;
+mod decorators;
+mod docstrings;
+mod doctor;
+mod dry_run;
+mod exclude;
+mod export;
+mod exports;
+mod frameworks;
+mod git;
+mod graph;
+mod haystack;
+mod hg;
+mod ignore;
+mod languages;
+mod lines;
+mod metadata;
+mod metrics;
+mod modules;
+mod origins;
+mod owners;
+mod path_map;
+mod presets;
+mod ratchet;
+mod rename;
+mod sample;
+mod sarif;
+mod selfcheck;
+mod source;
+mod stats;
+mod strip;
+mod suppressions;
+mod syntax;
+mod targets;
+mod telemetry;
+mod testing;
+mod vcs;
+pub mod walk;
+
+/// Settings for scanning from code, each behaving like the command line flag of the same name.
+#[derive(Clone, Debug)]
+pub struct Options {
+    pub config: Option<PathBuf>,
+    pub strip_comments: bool,
+    pub scan_scripts: bool,
+    pub complexity: bool,
+    pub kinds: Vec<DeclarationKind>,
+    pub constants: bool,
+    pub duplicates: bool,
+    pub dead_modules: bool,
+    pub test_only: bool,
+    pub script_only: bool,
+    pub scoped_counting: bool,
+    pub build_graph: Option<BuildSystem>,
+    pub exports: bool,
+    pub languages: Vec<Language>,
+    pub frameworks: Vec<Framework>,
+    pub presets: Vec<Preset>,
+    pub exclude: Vec<String>,
+    pub respect_gitignore: bool,
+    pub allow: Vec<String>,
+    pub search_roots: Vec<PathBuf>,
+    pub cache_dir: Option<PathBuf>,
+    pub mode: Mode,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            config: None,
+            strip_comments: false,
+            scan_scripts: false,
+            complexity: false,
+            kinds: vec![DeclarationKind::Functions, DeclarationKind::Methods],
+            constants: false,
+            duplicates: false,
+            dead_modules: false,
+            test_only: false,
+            script_only: false,
+            scoped_counting: false,
+            build_graph: None,
+            exports: false,
+            languages: vec![Language::Python],
+            frameworks: Vec::new(),
+            presets: Vec::new(),
+            exclude: Vec::new(),
+            respect_gitignore: false,
+            allow: Vec::new(),
+            search_roots: Vec::new(),
+            cache_dir: None,
+            mode: Mode::Mentions,
+        }
+    }
+}
+
+/// A finding reported by `Scanner::scan` or `assert_no_unused`.
+#[derive(Clone, Debug)]
+pub struct Unused {
+    pub name: String,
+    pub kind: &'static str,
+    /// The scanned path joined with the file's path relative to it.
+    pub path: PathBuf,
+    pub line: usize,
+    pub rule: String,
+    /// The description given in text reports, such as `function "helper" may be unused`.
+    pub message: String,
+}
+
+/// The error returned by `assert_no_unused` when anything is flagged, listing every finding.
+#[derive(Clone, Debug)]
+pub struct UnusedFunctions {
+    pub findings: Vec<Unused>,
+}
+
+impl fmt::Display for UnusedFunctions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} possible unused functions were found",
+            self.findings.len()
+        )?;
+        for finding in &self.findings {
+            write!(
+                f,
+                "\n  {}:{} - {} \"{}\" ({})",
+                finding.path.display(),
+                finding.line,
+                finding.kind,
+                finding.name,
+                finding.rule
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for UnusedFunctions {}
+
+/// Finds unused code from other tools, such as editor plugins and lint aggregators, returning the findings
+/// instead of printing them.
+#[derive(Clone, Debug, Default)]
+pub struct Scanner {
+    options: Options,
+}
+
+impl Scanner {
+    pub fn new(options: Options) -> Self {
+        Self { options }
+    }
+
+    /// Scans each tree as the command line would, one at a time, so mentions in one tree don't keep code
+    /// in another alive. Baselines and reporting options don't apply.
+    pub fn scan(&self, paths: &[impl AsRef<Path>]) -> Result<Vec<Unused>> {
+        let mut findings = Vec::new();
+        for path in paths {
+            findings.extend(scan_tree(path.as_ref(), &self.options)?);
+        }
+        Ok(findings)
+    }
+}
+
+/// Scans the tree as the command line would, failing with `UnusedFunctions` if anything is flagged.
+/// Meant for test harnesses and build scripts checking python code embedded in a rust project.
+/// Baselines and reporting options don't apply; anything flagged is an error.
+pub fn assert_no_unused(path: impl AsRef<Path>, options: &Options) -> Result<()> {
+    let findings = scan_tree(path.as_ref(), options)?;
+    if findings.is_empty() {
+        Ok(())
+    } else {
+        Err(UnusedFunctions { findings }.into())
+    }
+}
+
+/// Scans a single tree for the library API.
+fn scan_tree(path: &Path, options: &Options) -> Result<Vec<Unused>> {
+    let root = path.canonicalize()?;
+    let scan_options = ScanOptions::new(options, &root)?;
+    let (files, _) = walk::files(&root, &scan_options);
+    let tree = Tree::scan(&root, files, &scan_options)?;
+    let (findings, _) = tree.findings(&scan_options)?;
+
+    findings
+        .iter()
+        .map(|finding| {
+            let function = finding.function;
+            Ok(Unused {
+                name: function.name.clone(),
+                kind: function.kind.name(),
+                path: path.join(function.location.0.strip_prefix(&root)?),
+                line: function.location.1,
+                rule: finding.rule(),
+                message: finding.message(),
+            })
+        })
+        .collect()
+}
+
+/// The files walked from a root, their declarations and the `__all__` mismatches among them. Both the
+/// command line and the library scan through this, so that they find the same things.
+pub(crate) struct Tree {
+    pub(crate) root: PathBuf,
+    /// The walked files, followed by those under the search roots.
+    pub(crate) files: Vec<SourceFile>,
+    pub(crate) declared: Declared,
+    mismatches: Vec<Function>,
+}
+
+impl Tree {
+    /// Adds the files under the search roots to the files walked from the root, and scans them all.
+    pub(crate) fn scan(
+        root: &Path,
+        mut files: Vec<SourceFile>,
+        options: &ScanOptions,
+    ) -> Result<Self> {
+        files.extend(search_root_files(
+            root,
+            &scanned_extensions(options),
+            options,
+        ));
+        let declared = declarations::collect(&files, options)?;
+        let mismatches = if options.exports {
+            export_mismatches(&files, root)?
+        } else {
+            Vec::new()
+        };
+        Ok(Self {
+            root: root.to_owned(),
+            files,
+            declared,
+            mismatches,
+        })
+    }
+
+    /// Finds everything to report, sorted, along with the reference graph if reachability mode built one.
+    pub(crate) fn findings(
+        &self,
+        options: &ScanOptions,
+    ) -> Result<(Vec<Finding<'_>>, Option<ReferenceGraph<'_>>)> {
+        let functions = &self.declared.functions;
+        let mut graph = None;
+        let mut findings = match options.mode {
+            Mode::Mentions => count::count(&self.declared, &self.files, &self.root, options)?,
+            Mode::Reachability => {
+                let built =
+                    graph.insert(ReferenceGraph::build(&self.declared.haystack, functions)?);
+                find_unreachable_functions(built, functions, options)
+            }
+        };
+        find_unimplemented_overloads(functions, &mut findings);
+        findings.extend(find_export_mismatches(&self.mismatches));
+        if options.duplicates {
+            findings.extend(find_duplicate_functions(functions));
+        }
+        findings.sort_by_key(|finding| finding.function.sort_key());
+        Ok((findings, graph))
+    }
+}
+
+/// Settings that affect how source files are scanned.
+#[derive(Debug)]
+pub struct ScanOptions {
+    strip_comments: bool,
+    scan_scripts: bool,
+    complexity: bool,
+    /// The kinds of declaration that are checked.
+    kinds: HashSet<Kind>,
+    duplicates: bool,
+    exports: bool,
+    mode: Mode,
+    /// Patterns declaring functions, paired with the file extension they apply to.
+    definitions: Vec<(String, Regex)>,
+    /// Names that frameworks call by convention, which are allowed to be unused.
+    framework_methods: Vec<String>,
+    /// Names that the presets' frameworks call by convention, which are allowed to be unused in their files.
+    preset_methods: Vec<PresetMethods>,
+    /// Files whose top-level functions are entry points.
+    entry_modules: Vec<String>,
+    /// Names of further functions that reachability analysis starts from.
+    entry_points: Vec<String>,
+    /// Decorators marking functions that are invoked by a framework.
+    exempt_decorators: Vec<String>,
+    /// Matches calls whose string arguments name functions.
+    naming_calls: Regex,
+    /// Matches names that are allowed to be unused.
+    allowed_names: Vec<Regex>,
+    /// Paths left out of the scan.
+    exclusions: Exclusions,
+    dead_modules: bool,
+    test_only: bool,
+    script_only: bool,
+    scoped_counting: bool,
+    build_graph: Option<BuildSystem>,
+    /// Paths whose mentions count for every declaration under scoped counting.
+    extra_roots: Vec<Regex>,
+    /// Directories scanned for third-party mentions only.
+    search_roots: Vec<PathBuf>,
+    origin_weights: Weights,
+    cache_dir: Option<PathBuf>,
+    /// Digest of the version and every setting, which the cache's entries are keyed by.
+    fingerprint: String,
+}
+
+impl ScanOptions {
+    /// The settings for scanning the given root, read from the configuration file the options give or the
+    /// one found in the root.
+    pub fn new(options: &Options, root: &Path) -> Result<Self> {
+        scan_options(options, &load_config(options.config.as_deref(), root)?)
+    }
+}
+
+/// How functions are judged to be dead.
+#[derive(ArgEnum, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Mode {
+    /// Flag functions that are mentioned no more often than they are defined.
+    Mentions,
+    /// Flag functions that cannot be reached by following references from the entry points.
+    Reachability,
+}
+
+/// The sorts of declaration that can be checked.
+#[derive(ArgEnum, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DeclarationKind {
+    /// Functions outside of classes.
+    Functions,
+    /// Functions defined directly inside a class. Dunder methods are allowed to be unused.
+    Methods,
+    /// Python classes. Context manager classes are checked regardless.
+    Classes,
+    /// Upper-case names assigned at module level in python.
+    Constants,
+}
+
+impl DeclarationKind {
+    fn kind(&self) -> Kind {
+        match self {
+            DeclarationKind::Functions => Kind::Function,
+            DeclarationKind::Methods => Kind::Method,
+            DeclarationKind::Classes => Kind::Class,
+            DeclarationKind::Constants => Kind::Constant,
+        }
+    }
+}
+
+/// What sort of declaration a function is.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+enum Kind {
+    Function,
+    /// Defined directly inside a class.
+    Method,
+    /// A class, only tracked when requested or when it is a context manager.
+    Class,
+    /// An upper-case name assigned at module level, only tracked if requested.
+    Constant,
+    /// A name listed in `__all__` that its module never defines.
+    Export,
+}
+
+impl Kind {
+    fn name(&self) -> &'static str {
+        match self {
+            Kind::Function => "function",
+            Kind::Method => "method",
+            Kind::Class => "class",
+            Kind::Constant => "constant",
+            Kind::Export => "export",
+        }
+    }
+
+    /// How the kind is described in text reports.
+    fn noun(&self) -> &'static str {
+        match self {
+            Kind::Function | Kind::Method => "function",
+            Kind::Class => "class",
+            Kind::Constant => "constant",
+            Kind::Export => "name",
+        }
+    }
+}
+
+/// A declaration found by scanning, whether of a function or of another kind that is checked.
+#[derive(Serialize, Deserialize, PartialEq, Eq, Hash, Debug)]
+pub struct Function {
+    name: String,
+    kind: Kind,
+    location: (PathBuf, usize),
+    /// Number of lines spanned by the declaration and its body.
+    lines: usize,
+    /// Rough cyclomatic complexity of a function or method, if requested.
+    complexity: Option<usize>,
+    /// Names of the decorators applied to the function, without arguments.
+    decorators: Vec<String>,
+    /// For a class, whether it defines `__enter__` or `__aenter__`.
+    enters: bool,
+    /// A function called by a framework or a top-level function of an entry module, which is never
+    /// reported and keeps alive whatever it references.
+    root: bool,
+    /// Defined in a module that looks functions up dynamically, so it may be called in ways we can't see.
+    dynamic: bool,
+    /// Defined in a test file, so that mentions in tests always count for it.
+    test: bool,
+    /// Digest of the normalized body, if duplicates were requested and the body is long enough to compare.
+    body: Option<String>,
+    /// Mentions in code under the `if __name__ == "__main__":` guard of the file it is defined in, or in
+    /// the bodies of its top-level functions that build an argument parser.
+    guarded_mentions: usize,
+    /// For an `@overload` with no implementation in the same scope, the line of the scope's first overload
+    /// of the name, which the group is reported at.
+    unimplemented_overload: Option<usize>,
+}
+
+impl Function {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// What sort of declaration this is, such as `function` or `method`.
+    pub fn kind(&self) -> &'static str {
+        self.kind.name()
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.location.0
+    }
+
+    /// The line of the declaration, counting from one.
+    pub fn line(&self) -> usize {
+        self.location.1
+    }
+
+    /// A total order over functions, so that reports are identical between runs.
+    fn sort_key(&self) -> (&Path, usize, &str) {
+        (&self.location.0, self.location.1, &self.name)
+    }
+
+    /// The module a function belongs to, which is shared between a `.py` file and its `.pyi` stub.
+    fn module(&self) -> PathBuf {
+        let path = &self.location.0;
+        if path.extension() == Some(OsStr::new("pyi")) {
+            path.with_extension("py")
+        } else {
+            path.clone()
+        }
+    }
+
+    /// Whether this is a class with `__enter__`, or a function decorated to become a context manager.
+    fn is_context_manager(&self) -> bool {
+        self.enters
+            || self.decorators.iter().any(|decorator| {
+                CONTEXT_MANAGER_DECORATORS
+                    .iter()
+                    .any(|name| decorators::matches(decorator, name))
+            })
+    }
+
+    /// Whether the given line of the given file falls within this function.
+    fn contains(&self, path: &Path, line: usize) -> bool {
+        self.location.0 == path && line >= self.location.1 && line < self.location.1 + self.lines
+    }
+}
+
+/// A possibly unused function, which may be defined more than once in the same module,
+/// such as in a `.pyi` stub or in each branch of a conditional.
+#[derive(Debug)]
+pub struct Finding<'a> {
+    function: &'a Function,
+    category: Category,
+    /// The other definitions of the same function, in order.
+    redefinitions: Vec<&'a Function>,
+    /// Functions elsewhere with the same body, in order.
+    copies: Vec<&'a Function>,
+    /// How often the function is mentioned, for the categories judged by counting.
+    mentions: Option<Mentions>,
+}
+
+impl<'a> Finding<'a> {
+    /// The reported definition of the function.
+    pub fn function(&self) -> &'a Function {
+        self.function
+    }
+
+    /// How the finding is described in text reports.
+    pub fn message(&self) -> String {
+        format!(
+            "{} \"{}\" {}",
+            self.function.kind.noun(),
+            self.function.name,
+            self.category.verdict()
+        )
+    }
+
+    /// The rule that produced this finding, namespaced by the language it was found in.
+    pub fn rule(&self) -> String {
+        let extension = self
+            .function
+            .location
+            .0
+            .extension()
+            .and_then(OsStr::to_str)
+            .unwrap_or_default();
+        let prefix = Language::of(extension)
+            .map(|language| language.rule_prefix())
+            .unwrap_or_default();
+        format!("{}{}", prefix, self.category.rule())
+    }
+
+    /// Every definition of the function, starting with the one reported.
+    fn definitions(&self) -> impl Iterator<Item = &'a Function> + '_ {
+        std::iter::once(self.function).chain(self.redefinitions.iter().copied())
+    }
+}
+
+/// Why a function was flagged.
+#[derive(PartialEq, Eq, Debug)]
+enum Category {
+    /// Not mentioned anywhere besides its declaration.
+    Unused,
+    /// Only mentioned in comments or strings besides its declaration.
+    CommentOnly,
+    /// Only used as a decorator, applied to functions that are themselves dead.
+    DeadDecorator,
+    /// A context manager class or `@contextmanager` function that is never used.
+    UnusedContextManager,
+    /// A module-level constant that is never referenced.
+    UnusedConstant,
+    /// A class that is never referenced besides its declaration.
+    UnusedClass,
+    /// Not reachable from any entry point.
+    Unreachable,
+    /// Has the same body as a function in another file.
+    Duplicate,
+    /// Only mentioned under the `__main__` guard of its own file, or in a function there that builds an
+    /// argument parser, besides its declaration.
+    ScriptOnly,
+    /// A module-level constant only mentioned where `ScriptOnly` functions are.
+    ScriptOnlyConstant,
+    /// Declared with `@overload`, but never implemented, so calling it fails.
+    UnimplementedOverload,
+    /// Only mentioned by modules that are never imported, besides its declaration.
+    DeadModuleOnly,
+    /// Only mentioned by test files, besides its declaration.
+    TestOnly,
+    /// Listed in `__all__`, but never defined by the module.
+    UndefinedExport,
+    /// A public function or class missing from its module's `__all__`.
+    UnlistedExport,
+}
+
+impl Category {
+    const ALL: [Category; 15] = [
+        Category::Unused,
+        Category::CommentOnly,
+        Category::DeadDecorator,
+        Category::UnusedContextManager,
+        Category::UnusedConstant,
+        Category::UnusedClass,
+        Category::Unreachable,
+        Category::Duplicate,
+        Category::ScriptOnly,
+        Category::ScriptOnlyConstant,
+        Category::UnimplementedOverload,
+        Category::DeadModuleOnly,
+        Category::TestOnly,
+        Category::UndefinedExport,
+        Category::UnlistedExport,
+    ];
+
+    /// A stable identifier for the rule that produced this category of finding.
+    fn rule(&self) -> &'static str {
+        match self {
+            Category::Unused => "unused-function",
+            Category::CommentOnly => "comment-only-function",
+            Category::DeadDecorator => "dead-decorator-function",
+            Category::UnusedContextManager => "unused-context-manager",
+            Category::UnusedConstant => "unused-constant",
+            Category::UnusedClass => "unused-class",
+            Category::Unreachable => "unreachable-function",
+            Category::Duplicate => "duplicate-function",
+            Category::ScriptOnly => "script-only-function",
+            Category::ScriptOnlyConstant => "script-only-constant",
+            Category::UnimplementedOverload => "overload-without-implementation",
+            Category::DeadModuleOnly => "dead-module-only-function",
+            Category::TestOnly => "test-only-function",
+            Category::UndefinedExport => "undefined-export",
+            Category::UnlistedExport => "unlisted-export",
+        }
+    }
+
+    /// How findings of this category are described, following the kind and name of the function.
+    fn verdict(&self) -> &'static str {
+        match self {
+            Category::Unused => "may be unused",
+            Category::CommentOnly => "is only referenced in comments or strings",
+            Category::DeadDecorator => "is only applied to dead functions",
+            Category::UnusedContextManager => "is a context manager that may be unused",
+            Category::UnusedConstant => "is only referenced where it is defined",
+            Category::UnusedClass => "may be unused",
+            Category::Unreachable => "is not reachable from any entry point",
+            Category::Duplicate => "is duplicated elsewhere",
+            Category::ScriptOnly | Category::ScriptOnlyConstant => {
+                "is only used when its module is run as a script"
+            }
+            Category::UnimplementedOverload => "is overloaded but never implemented",
+            Category::DeadModuleOnly => "is only used by modules that are never imported",
+            Category::TestOnly => "is only used by tests",
+            Category::UndefinedExport => "is listed in __all__ but never defined",
+            Category::UnlistedExport => "is public but missing from __all__",
+        }
+    }
+
+    /// The usual fix for findings of this category.
+    fn suggestion(&self) -> &'static str {
+        match self {
+            Category::ScriptOnly | Category::ScriptOnlyConstant => "make-private",
+            Category::UnimplementedOverload => "implement",
+            Category::UndefinedExport => "remove-from-__all__",
+            Category::UnlistedExport => "add-to-__all__",
+            _ => "delete",
+        }
+    }
+
+    /// Whether findings of this category are code that could be deleted, rather than code that is used
+    /// but has some other problem.
+    fn is_dead_code(&self) -> bool {
+        !matches!(
+            self,
+            Category::Duplicate
+                | Category::UnimplementedOverload
+                | Category::UndefinedExport
+                | Category::UnlistedExport
+        )
+    }
+}
+
+/// Decorators that turn a generator into a context manager.
+const CONTEXT_MANAGER_DECORATORS: [&str; 2] = ["contextmanager", "asynccontextmanager"];
+
+/// Extensions of python sources and stubs, which are split into code and prose.
+const PYTHON_EXTENSIONS: [&str; 2] = ["py", "pyi"];
+
+/// The extension of the given file, without the leading dot.
+fn extension_of(path: &Path) -> &str {
+    path.extension().and_then(OsStr::to_str).unwrap_or_default()
+}
+
+/// Whether the file is an entry module, given either by file name or by a path suffix such as `app/wsgi.py`.
+fn is_entry_module(path: &Path, entry_modules: &[String]) -> bool {
+    entry_modules.iter().any(|module| path.ends_with(module))
+}
+
+/// Tests, dunder, allowed and framework methods are allowed to be "unused."
+fn should_consider_function(name: &str, decorators: &[String], options: &ScanOptions) -> bool {
+    !options
+        .allowed_names
+        .iter()
+        .any(|pattern| pattern.is_match(name))
+        && !options
+            .framework_methods
+            .iter()
+            .any(|method| method == name)
+        && !decorators.iter().any(|decorator| {
+            options
+                .exempt_decorators
+                .iter()
+                .any(|exempt| decorators::matches(decorator, exempt))
+        })
+}
+
+/// Groups the definitions of each function within a module, and finds those mentioned in code no more
+/// often than they are defined, not counting mentions under the module's `__main__` guard if `script_only`.
+fn find_unused_functions<'a>(
+    counts: &HashMap<&'a Function, Mentions>,
+    script_only: bool,
+) -> Vec<Finding<'a>> {
+    let mut groups: HashMap<(&str, Kind, PathBuf), (Vec<&Function>, Mentions)> = HashMap::new();
+    for (function, mentions) in counts {
+        // every definition shares a name, so they share their mentions too.
+        groups
+            .entry((&function.name, function.kind, function.module()))
+            .or_insert_with(|| (Vec::new(), *mentions))
+            .0
+            .push(function);
+    }
+
+    let mut unused_functions = groups
+        .into_values()
+        .filter(|(definitions, _)| !definitions.iter().any(|function| function.root))
+        .filter(|(definitions, mentions)| {
+            if !script_only {
+                return mentions.code <= definitions.len();
+            }
+            let guarded: usize = definitions
+                .iter()
+                .map(|function| function.guarded_mentions)
+                .sum();
+            mentions.code.saturating_sub(guarded) <= definitions.len()
+        })
+        .map(|(mut definitions, mentions)| {
+            // report the implementation rather than its stub.
+            definitions.sort_by_key(|function| {
+                (
+                    function.location.0.extension() == Some(OsStr::new("pyi")),
+                    function.sort_key(),
+                )
+            });
+            let category = if mentions.code > definitions.len() {
+                if definitions[0].kind == Kind::Constant {
+                    Category::ScriptOnlyConstant
+                } else {
+                    Category::ScriptOnly
+                }
+            } else if definitions[0].kind == Kind::Constant {
+                Category::UnusedConstant
+            } else if definitions[0].is_context_manager() {
+                Category::UnusedContextManager
+            } else if definitions[0].kind == Kind::Class {
+                Category::UnusedClass
+            } else if mentions.prose > 0 {
+                Category::CommentOnly
+            } else {
+                Category::Unused
+            };
+            let function = definitions.remove(0);
+            definitions.sort_by_key(|function| function.sort_key());
+            Finding {
+                function,
+                category,
+                redefinitions: definitions,
+                copies: Vec::new(),
+                mentions: Some(mentions),
+            }
+        })
+        .collect::<Vec<_>>();
+    unused_functions.sort_by_key(|finding| finding.function.sort_key());
+
+    unused_functions
+}
+
+/// Finds decorators whose every mention in code is their definition or an application to a dead function.
+/// Repeats until no more are found, so that decorators applied only to dead decorators are found too.
+fn find_dead_decorators<'a>(
+    counts: &HashMap<&'a Function, Mentions>,
+    findings: &mut Vec<Finding<'a>>,
+) {
+    let mut definitions: HashMap<&str, usize> = HashMap::new();
+    let mut applications: HashMap<&str, Vec<&Function>> = HashMap::new();
+    for function in counts.keys() {
+        *definitions.entry(&function.name).or_default() += 1;
+        for decorator in &function.decorators {
+            // qualified decorators such as `@module.name` are applications of `name`.
+            let name = decorator.rsplit('.').next().unwrap_or_default();
+            applications.entry(name).or_default().push(function);
+        }
+    }
+
+    loop {
+        let dead: HashSet<&Function> = findings.iter().flat_map(Finding::definitions).collect();
+        let mut found: Vec<Finding> = counts
+            .iter()
+            .filter(|(function, _)| !function.root && !dead.contains(*function))
+            .filter(|(function, _)| definitions[function.name.as_str()] == 1)
+            .filter_map(|(function, mentions)| {
+                let applied_to = applications.get(function.name.as_str())?;
+                (mentions.code == applied_to.len() + 1
+                    && applied_to.iter().all(|decorated| dead.contains(decorated)))
+                .then(|| Finding {
+                    function,
+                    category: Category::DeadDecorator,
+                    redefinitions: Vec::new(),
+                    copies: Vec::new(),
+                    mentions: Some(*mentions),
+                })
+            })
+            .collect();
+
+        if found.is_empty() {
+            break;
+        }
+        findings.append(&mut found);
+    }
+
+    findings.sort_by_key(|finding| finding.function.sort_key());
+}
+
+/// Finds functions that cannot be reached from the roots, the configured entry points, or module-level code
+/// in an entry module.
+fn find_unreachable_functions<'a>(
+    graph: &ReferenceGraph<'a>,
+    functions: &'a HashSet<Function>,
+    options: &ScanOptions,
+) -> Vec<Finding<'a>> {
+    let roots = functions
+        .iter()
+        .filter(|function| function.root || options.entry_points.contains(&function.name));
+    let reachable = graph.reachable(roots, |path| is_entry_module(path, &options.entry_modules));
+
+    let mut unreachable: Vec<Finding> = functions
+        .iter()
+        .filter(|function| !reachable.contains(function))
+        .map(|function| Finding {
+            function,
+            category: Category::Unreachable,
+            redefinitions: Vec::new(),
+            copies: Vec::new(),
+            mentions: None,
+        })
+        .collect();
+    unreachable.sort_by_key(|finding| finding.function.sort_key());
+
+    unreachable
+}
+
+/// Finds functions with the same body as one in another file. Every copy is flagged, since any of them
+/// may be the one that callers moved away from.
+fn find_duplicate_functions(functions: &HashSet<Function>) -> Vec<Finding<'_>> {
+    let mut groups: HashMap<&str, Vec<&Function>> = HashMap::new();
+    for function in functions {
+        if let Some(body) = &function.body {
+            groups.entry(body).or_default().push(function);
+        }
+    }
+
+    let mut duplicates = Vec::new();
+    for mut copies in groups.into_values() {
+        let files: HashSet<&Path> = copies
+            .iter()
+            .map(|function| function.location.0.as_path())
+            .collect();
+        if files.len() < 2 {
+            continue;
+        }
+        copies.sort_by_key(|function| function.sort_key());
+        for function in copies.iter().filter(|function| !function.root) {
+            duplicates.push(Finding {
+                function,
+                category: Category::Duplicate,
+                redefinitions: Vec::new(),
+                copies: copies
+                    .iter()
+                    .copied()
+                    .filter(|copy| copy != function)
+                    .collect(),
+                mentions: None,
+            });
+        }
+    }
+    duplicates.sort_by_key(|finding| finding.function.sort_key());
+
+    duplicates
+}
+
+/// Adds the groups of `@overload` declarations without an implementation, reporting each at its first
+/// overload, in place of any findings for their declarations themselves.
+fn find_unimplemented_overloads<'a>(
+    functions: &'a HashSet<Function>,
+    findings: &mut Vec<Finding<'a>>,
+) {
+    let mut groups: HashMap<(&Path, usize), Vec<&Function>> = HashMap::new();
+    for function in functions {
+        if let Some(line) = function.unimplemented_overload {
+            groups
+                .entry((&function.location.0, line))
+                .or_default()
+                .push(function);
+        }
+    }
+
+    let mut unimplemented: Vec<Finding> = groups
+        .into_values()
+        .map(|mut overloads| {
+            overloads.sort_by_key(|function| function.sort_key());
+            let function = overloads.remove(0);
+            Finding {
+                function,
+                category: Category::UnimplementedOverload,
+                redefinitions: overloads,
+                copies: Vec::new(),
+                mentions: None,
+            }
+        })
+        .collect();
+    unimplemented.sort_by_key(|finding| finding.function.sort_key());
+
+    findings.retain(|finding| {
+        !finding
+            .definitions()
+            .any(|function| function.unimplemented_overload.is_some())
+    });
+    findings.extend(unimplemented);
+}
+
+/// Finds the mismatches between each python module's `__all__` and its definitions, in parallel.
+fn export_mismatches(files: &[SourceFile], root: &Path) -> Result<Vec<Function>> {
+    let patterns = exports::Patterns::new()?;
+    let mismatches = files
+        .par_iter()
+        .filter(|entry| entry.path().starts_with(root))
+        .map(|entry| exports::mismatches(entry, &patterns))
+        .collect::<Result<Vec<_>>>()?;
+    Ok(mismatches.into_iter().flatten().collect())
+}
+
+/// Reports the `__all__` mismatches, where the names that were never defined stand in as exports.
+fn find_export_mismatches(mismatches: &[Function]) -> Vec<Finding<'_>> {
+    mismatches
+        .iter()
+        .map(|function| Finding {
+            function,
+            category: if function.kind == Kind::Export {
+                Category::UndefinedExport
+            } else {
+                Category::UnlistedExport
+            },
+            redefinitions: Vec::new(),
+            copies: Vec::new(),
+            mentions: None,
+        })
+        .collect()
+}
+
+/// Loads the configuration from the given file, or the one found in the scanned root.
+fn load_config(path: Option<&Path>, root: &Path) -> Result<Config> {
+    match path.map(Path::to_owned).or_else(|| Config::find(root)) {
+        Some(path) => Config::load(&path),
+        None => Ok(Config::default()),
+    }
+}
+
+/// Combines the options and configuration into the settings for scanning files.
+fn scan_options(options: &Options, config: &Config) -> Result<ScanOptions> {
+    let mut definitions = Vec::new();
+    for language in &options.languages {
+        for pattern in language.definition_patterns() {
+            let pattern = Regex::new(pattern)?;
+            for &extension in language.extensions() {
+                definitions.push((extension.to_owned(), pattern.clone()));
+            }
+        }
+    }
+    definitions.extend(config.definition_patterns()?);
+
+    let mut frameworks = options.frameworks.clone();
+    for framework in options.presets.iter().flat_map(Preset::frameworks) {
+        if !frameworks.contains(framework) {
+            frameworks.push(*framework);
+        }
+    }
+
+    let framework_methods = config.framework_methods();
+    let mut entry_modules = config.entry_modules();
+    let mut exempt_decorators: Vec<String> = frameworks
+        .iter()
+        .flat_map(|framework| framework.decorators())
+        .map(|&decorator| decorator.to_owned())
+        .chain(config.decorators.iter().cloned())
+        .collect();
+    let mut excludes = config.exclude.clone();
+    for preset in &options.presets {
+        excludes.extend(preset.excludes().iter().map(|&pattern| pattern.to_owned()));
+        entry_modules.extend(
+            preset
+                .entry_modules()
+                .iter()
+                .map(|&module| module.to_owned()),
+        );
+        exempt_decorators.extend(
+            preset
+                .decorators()
+                .iter()
+                .map(|&decorator| decorator.to_owned()),
+        );
+    }
+
+    Ok(ScanOptions {
+        strip_comments: options.strip_comments,
+        scan_scripts: options.scan_scripts,
+        complexity: options.complexity,
+        kinds: options
+            .kinds
+            .iter()
+            .map(DeclarationKind::kind)
+            .chain(options.constants.then_some(Kind::Constant))
+            .collect(),
+        duplicates: options.duplicates,
+        exports: options.exports,
+        mode: options.mode,
+        definitions,
+        framework_methods,
+        preset_methods: options
+            .presets
+            .iter()
+            .filter(|preset| !preset.framework_methods().is_empty())
+            .map(|&preset| PresetMethods::new(preset))
+            .collect::<Result<_>>()?,
+        entry_modules,
+        entry_points: config.entry_points.clone(),
+        exempt_decorators,
+        naming_calls: calls::naming_pattern(&config.registration_calls)?,
+        allowed_names: config
+            .allow()
+            .iter()
+            .chain(&options.allow)
+            .map(|name| Regex::new(name).with_context(|| format!("invalid allowed name {}", name)))
+            .collect::<Result<_>>()?,
+        exclusions: Exclusions::new(
+            &[excludes.as_slice(), options.exclude.as_slice()].concat(),
+            config.respect_gitignore || options.respect_gitignore,
+        )?,
+        dead_modules: options.dead_modules,
+        test_only: options.test_only,
+        script_only: config.script_only || options.script_only,
+        scoped_counting: options.scoped_counting,
+        build_graph: options.build_graph,
+        extra_roots: config
+            .extra_roots
+            .iter()
+            .map(|pattern| owners::pattern_to_regex(pattern))
+            .collect::<Result<_>>()?,
+        search_roots: options
+            .search_roots
+            .iter()
+            .map(|root| {
+                root.canonicalize()
+                    .with_context(|| format!("invalid search root {}", root.display()))
+            })
+            .collect::<Result<_>>()?,
+        origin_weights: Weights::new(&config.origin_weights)?,
+        cache_dir: options.cache_dir.clone(),
+        fingerprint: metadata::sha256(
+            format!(
+                "{} {:?} {:?}",
+                env!("CARGO_PKG_VERSION"),
+                Options {
+                    cache_dir: None,
+                    ..options.clone()
+                },
+                config
+            )
+            .as_bytes(),
+        ),
+    })
+}
+
+/// Opens the cache, if one was requested.
+fn open_cache(options: &ScanOptions) -> Result<Option<Cache>> {
+    options
+        .cache_dir
+        .as_deref()
+        .map(|directory| Cache::open(directory, options.fingerprint.clone()))
+        .transpose()
+}
+
+/// The extensions of every file that declarations are scanned for.
+fn scanned_extensions(options: &ScanOptions) -> Vec<String> {
+    let mut extensions: Vec<String> = options
+        .definitions
+        .iter()
+        .map(|(extension, _)| extension.clone())
+        .collect();
+    extensions.sort();
+    extensions.dedup();
+    extensions
+}
@@ -0,0 +1,182 @@
+use std::io::{self, BufRead};
+
+/// The longest piece of a line handed out at once.
+pub const MAX_CHUNK: usize = 64 * 1024;
+
+/// A piece of a line. Most lines fit in a single chunk.
+#[derive(Debug)]
+pub struct Chunk {
+    pub text: String,
+    /// Whether this chunk starts a new line.
+    pub starts_line: bool,
+    /// Whether this chunk finishes its line.
+    pub ends_line: bool,
+}
+
+/// Reads lines in chunks of at most `MAX_CHUNK` bytes, so that minified or generated files with
+/// enormous lines never need to be held in memory whole. Lines may end in `\n`, `\r\n` or a lone `\r`,
+/// which are all stripped.
+pub struct ChunkedLines<R> {
+    reader: R,
+    starts_line: bool,
+    /// The start of a character split by the end of the previous chunk.
+    carry: Vec<u8>,
+    /// Whether the previous line ended in `\r`, which may be followed by a `\n` that ends it too.
+    after_cr: bool,
+}
+
+impl<R: BufRead> ChunkedLines<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            starts_line: true,
+            carry: Vec::new(),
+            after_cr: false,
+        }
+    }
+
+    fn read_chunk(&mut self) -> io::Result<Option<Chunk>> {
+        let mut bytes = std::mem::take(&mut self.carry);
+
+        loop {
+            let available = self.reader.fill_buf()?;
+            if std::mem::take(&mut self.after_cr) && available.first() == Some(&b'\n') {
+                self.reader.consume(1);
+                continue;
+            }
+            if available.is_empty() {
+                if bytes.is_empty() && self.starts_line {
+                    return Ok(None);
+                }
+                return self.finish(bytes, true).map(Some);
+            }
+
+            let wanted = (MAX_CHUNK - bytes.len()).min(available.len());
+            if let Some(end) = memchr::memchr2(b'\n', b'\r', &available[..wanted]) {
+                self.after_cr = available[end] == b'\r';
+                bytes.extend_from_slice(&available[..end]);
+                self.reader.consume(end + 1);
+                return self.finish(bytes, true).map(Some);
+            }
+
+            bytes.extend_from_slice(&available[..wanted]);
+            self.reader.consume(wanted);
+            if bytes.len() >= MAX_CHUNK {
+                return self.finish(bytes, false).map(Some);
+            }
+        }
+    }
+
+    /// Decodes a chunk, holding back any character split across the chunk boundary.
+    fn finish(&mut self, mut bytes: Vec<u8>, ends_line: bool) -> io::Result<Chunk> {
+        if let Err(err) = std::str::from_utf8(&bytes) {
+            if ends_line || err.error_len().is_some() {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, err));
+            }
+            self.carry = bytes.split_off(err.valid_up_to());
+        }
+
+        let chunk = Chunk {
+            text: String::from_utf8(bytes).expect("chunk was validated"),
+            starts_line: self.starts_line,
+            ends_line,
+        };
+        self.starts_line = ends_line;
+        Ok(chunk)
+    }
+}
+
+impl<R: BufRead> Iterator for ChunkedLines<R> {
+    type Item = io::Result<Chunk>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_chunk().transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::BufReader;
+
+    use super::*;
+
+    /// Splits the bytes into chunks, read through a buffer of the given size.
+    fn chunks(bytes: &[u8], capacity: usize) -> Vec<(String, bool, bool)> {
+        ChunkedLines::new(BufReader::with_capacity(capacity, bytes))
+            .map(|chunk| {
+                let chunk = chunk.unwrap();
+                (chunk.text, chunk.starts_line, chunk.ends_line)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn every_line_ending_is_stripped() {
+        for capacity in [1, 2, 4096] {
+            assert_eq!(
+                chunks(b"a\nb\r\nc\rd", capacity),
+                [
+                    ("a".to_owned(), true, true),
+                    ("b".to_owned(), true, true),
+                    ("c".to_owned(), true, true),
+                    ("d".to_owned(), true, true),
+                ]
+            );
+        }
+    }
+
+    #[test]
+    fn blank_lines_are_kept() {
+        assert_eq!(
+            chunks(b"\n\r\n\nx\n", 4096),
+            [
+                (String::new(), true, true),
+                (String::new(), true, true),
+                (String::new(), true, true),
+                ("x".to_owned(), true, true),
+            ]
+        );
+    }
+
+    #[test]
+    fn long_lines_are_split_into_chunks() {
+        let line = "x".repeat(MAX_CHUNK * 2 + 1);
+        let split = chunks(format!("{}\nafter\n", line).as_bytes(), 4096);
+        let shape: Vec<(usize, bool, bool)> = split
+            .iter()
+            .map(|(text, starts, ends)| (text.len(), *starts, *ends))
+            .collect();
+        assert_eq!(
+            shape,
+            [
+                (MAX_CHUNK, true, false),
+                (MAX_CHUNK, false, false),
+                (1, false, true),
+                (5, true, true),
+            ]
+        );
+    }
+
+    #[test]
+    fn characters_split_by_a_chunk_boundary_are_carried() {
+        let line = format!("{}\u{e9}tail", "x".repeat(MAX_CHUNK - 1));
+        let split = chunks(line.as_bytes(), 4096);
+        assert_eq!(split.len(), 2);
+        assert_eq!(split[0].0.len(), MAX_CHUNK - 1);
+        assert_eq!(split[1].0, "\u{e9}tail");
+        assert_eq!(
+            split
+                .iter()
+                .map(|(text, ..)| text.as_str())
+                .collect::<String>(),
+            line
+        );
+    }
+
+    #[test]
+    fn invalid_utf8_is_an_error() {
+        let mut lines = ChunkedLines::new(BufReader::new(&b"ok\n\xff\n"[..]));
+        assert_eq!(lines.next().unwrap().unwrap().text, "ok");
+        assert!(lines.next().unwrap().is_err());
+    }
+}
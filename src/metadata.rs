@@ -0,0 +1,86 @@
+use std::{
+    env,
+    io::{self, Read},
+};
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use time::{format_description::well_known::Rfc3339, OffsetDateTime};
+
+/// The engine used to find declarations and references: python declarations come from its syntax tree,
+/// everything else from patterns.
+pub const ENGINE: &str = "python-ast+regex";
+
+/// Overrides the wall clock as the time of a run, so that repeated runs over the same tree report the same.
+const SOURCE_DATE_EPOCH: &str = "SOURCE_DATE_EPOCH";
+
+/// Describes a run, so that archived reports can be audited and compared.
+#[derive(Serialize, Debug)]
+pub struct Metadata {
+    pub version: &'static str,
+    pub timestamp: String,
+    #[serde(skip)]
+    pub unix_timestamp: i64,
+    pub root: String,
+    /// SHA-256 of the configuration file, if one was used.
+    pub config_hash: Option<String>,
+    pub engine: &'static str,
+    pub files: usize,
+    /// The percentage of files scanned with `--sample`, whose findings are only estimates.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sample_percent: Option<f64>,
+}
+
+impl Metadata {
+    pub fn new(
+        root: String,
+        config_hash: Option<String>,
+        files: usize,
+        sample_percent: Option<f64>,
+    ) -> Result<Self> {
+        let now = match env::var(SOURCE_DATE_EPOCH) {
+            Ok(epoch) => epoch
+                .trim()
+                .parse()
+                .ok()
+                .and_then(|epoch| OffsetDateTime::from_unix_timestamp(epoch).ok())
+                .with_context(|| format!("invalid {} {:?}", SOURCE_DATE_EPOCH, epoch))?,
+            Err(_) => OffsetDateTime::now_utc(),
+        };
+        Ok(Self {
+            version: env!("CARGO_PKG_VERSION"),
+            timestamp: now.format(&Rfc3339).unwrap_or_default(),
+            unix_timestamp: now.unix_timestamp(),
+            root,
+            config_hash,
+            engine: ENGINE,
+            files,
+            sample_percent,
+        })
+    }
+}
+
+/// Hex-encoded SHA-256 of the given bytes.
+pub fn sha256(bytes: &[u8]) -> String {
+    hex(&Sha256::digest(bytes))
+}
+
+/// Hex-encoded SHA-256 of everything the reader holds, which is read a buffer at a time.
+pub fn sha256_of(mut reader: impl Read) -> io::Result<String> {
+    let mut hasher = Sha256::new();
+    let mut buffer = [0; 64 * 1024];
+    loop {
+        match reader.read(&mut buffer) {
+            Ok(0) => return Ok(hex(&hasher.finalize())),
+            Ok(read) => hasher.update(&buffer[..read]),
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+/// Hex-encodes a digest.
+pub fn hex(digest: &[u8]) -> String {
+    digest.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
@@ -0,0 +1,106 @@
+use std::{collections::BTreeMap, fmt::Write as _, path::Path, time::Duration};
+
+use anyhow::{Context, Result};
+
+use crate::{atomic, metadata::Metadata};
+
+/// Gauges describing a single scan, exported in the Prometheus textfile format.
+#[derive(Default, Debug)]
+pub struct Metrics {
+    pub findings: usize,
+    pub by_rule: BTreeMap<String, usize>,
+    pub by_package: BTreeMap<String, usize>,
+    pub duration: Duration,
+}
+
+impl Metrics {
+    /// Writes the metrics to the given path, replacing it atomically so collectors never see a partial file.
+    pub fn write_textfile(&self, path: &Path, metadata: &Metadata) -> Result<()> {
+        let mut text = String::new();
+
+        gauge(&mut text, "snapbug_info", "Information about the run.");
+        writeln!(
+            text,
+            "snapbug_info{{version=\"{}\",engine=\"{}\",root=\"{}\",config_hash=\"{}\"}} 1",
+            escape(metadata.version),
+            escape(metadata.engine),
+            escape(&metadata.root),
+            escape(metadata.config_hash.as_deref().unwrap_or_default())
+        )?;
+
+        gauge(
+            &mut text,
+            "snapbug_timestamp_seconds",
+            "When the run started.",
+        );
+        writeln!(
+            text,
+            "snapbug_timestamp_seconds {}",
+            metadata.unix_timestamp
+        )?;
+
+        gauge(&mut text, "snapbug_files", "Number of files scanned.");
+        writeln!(text, "snapbug_files {}", metadata.files)?;
+
+        gauge(&mut text, "snapbug_findings", "Number of findings.");
+        writeln!(text, "snapbug_findings {}", self.findings)?;
+
+        gauge(
+            &mut text,
+            "snapbug_findings_by_rule",
+            "Number of findings per rule.",
+        );
+        for (rule, count) in &self.by_rule {
+            writeln!(
+                text,
+                "snapbug_findings_by_rule{{rule=\"{}\"}} {}",
+                escape(rule),
+                count
+            )?;
+        }
+
+        gauge(
+            &mut text,
+            "snapbug_findings_by_package",
+            "Number of findings per top-level package.",
+        );
+        for (package, count) in &self.by_package {
+            writeln!(
+                text,
+                "snapbug_findings_by_package{{package=\"{}\"}} {}",
+                escape(package),
+                count
+            )?;
+        }
+
+        gauge(
+            &mut text,
+            "snapbug_scan_duration_seconds",
+            "Time taken to scan the tree.",
+        );
+        writeln!(
+            text,
+            "snapbug_scan_duration_seconds {:.3}",
+            self.duration.as_secs_f64()
+        )?;
+
+        atomic::write(path, text)
+            .with_context(|| format!("failed to write metrics to {}", path.display()))
+    }
+}
+
+/// Writes the help and type lines for a gauge.
+fn gauge(text: &mut String, name: &str, help: &str) {
+    text.push_str(&format!(
+        "# HELP {} {}\n# TYPE {} gauge\n",
+        name, help, name
+    ));
+}
+
+/// Escapes a label value.
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
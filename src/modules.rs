@@ -0,0 +1,289 @@
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Component, Path},
+};
+
+use anyhow::Result;
+use regex::Regex;
+
+use crate::{
+    baseline::normalize, extension_of, is_entry_module, lines::ChunkedLines, source,
+    strip::Stripper, testing, walk::SourceFile, ScanOptions, PYTHON_EXTENSIONS,
+};
+
+/// Collects the modules named by the import statements of a file, including those whose names span
+/// lines inside parentheses.
+struct Imports {
+    import_pattern: Regex,
+    from_pattern: Regex,
+    /// The module of an unfinished `from module import (`, and its names so far.
+    pending: Option<String>,
+    imported: HashSet<String>,
+}
+
+impl Imports {
+    fn new() -> Result<Self> {
+        Ok(Self {
+            import_pattern: Regex::new(r"^\s*import\s+(.+)$")?,
+            from_pattern: Regex::new(r"^\s*from\s+(\.*)([\w.]*)\s+import\s+(.*)$")?,
+            pending: None,
+            imported: HashSet::new(),
+        })
+    }
+
+    /// Feeds a line of code from the module with the given package, as a list of components.
+    fn observe(&mut self, code: &str, package: &[String]) {
+        if let Some(module) = self.pending.take() {
+            self.add_names(&module, code);
+            if !code.contains(')') {
+                self.pending = Some(module);
+            }
+        } else if let Some(captures) = self.from_pattern.captures(code) {
+            let level = captures[1].len();
+            let module = if level == 0 {
+                captures[2].to_owned()
+            } else {
+                // each dot past the first climbs one package up from the importing module's.
+                let mut base = package[..package.len().saturating_sub(level - 1)].to_vec();
+                base.extend(
+                    captures[2]
+                        .split('.')
+                        .filter(|part| !part.is_empty())
+                        .map(str::to_owned),
+                );
+                base.join(".")
+            };
+            let names = &captures[3];
+            self.add_module(&module);
+            self.add_names(&module, names);
+            if names.trim_start().starts_with('(') && !names.contains(')') {
+                self.pending = Some(module);
+            }
+        } else if let Some(captures) = self.import_pattern.captures(code) {
+            for name in captures[1].split(',') {
+                let module = name.split(" as ").next().unwrap_or_default().trim();
+                self.add_module(module);
+            }
+        }
+    }
+
+    /// Records a module along with the packages containing it, which are imported first.
+    fn add_module(&mut self, module: &str) {
+        let mut prefix = String::new();
+        for part in module.split('.').filter(|part| !part.is_empty()) {
+            if !prefix.is_empty() {
+                prefix.push('.');
+            }
+            prefix.push_str(part);
+            self.imported.insert(prefix.clone());
+        }
+    }
+
+    /// Records the names imported from a module, any of which may be a submodule.
+    fn add_names(&mut self, module: &str, names: &str) {
+        for name in names.split(',') {
+            let name = name
+                .split(" as ")
+                .next()
+                .unwrap_or_default()
+                .trim_matches(|c: char| c.is_whitespace() || c == '(' || c == ')' || c == '\\');
+            if !name.is_empty() && name != "*" {
+                self.imported.insert(if module.is_empty() {
+                    name.to_owned()
+                } else {
+                    format!("{}.{}", module, name)
+                });
+            }
+        }
+    }
+}
+
+/// The dotted module name of a file relative to the root, as its components. A package's `__init__.py`
+/// is the package itself.
+fn module_of(relative: &Path) -> Vec<String> {
+    let mut components: Vec<String> = relative
+        .with_extension("")
+        .components()
+        .filter_map(|component| match component {
+            Component::Normal(part) => Some(part.to_string_lossy().into_owned()),
+            _ => None,
+        })
+        .collect();
+    if components.last().map(String::as_str) == Some("__init__") {
+        components.pop();
+    }
+    components
+}
+
+/// The imports between the python modules among some files, by index into the files. Modules are matched
+/// by any trailing part of their dotted name, since the root is not necessarily where imports are resolved
+/// from.
+pub struct ImportGraph {
+    /// The modules that could be dead, which leaves out entry modules, tests and scripts with a `__main__`
+    /// guard.
+    candidates: Vec<usize>,
+    /// The other files importing each module.
+    importers: HashMap<usize, HashSet<usize>>,
+}
+
+impl ImportGraph {
+    pub fn build(files: &[SourceFile], root: &Path, options: &ScanOptions) -> Result<Self> {
+        let guard_pattern = Regex::new(r#"^if\s+__name__\s*==\s*['"]__main__['"]"#)?;
+        let mut by_name: HashMap<String, Vec<usize>> = HashMap::new();
+        let mut imports = Vec::with_capacity(files.len());
+        let mut candidates = Vec::new();
+
+        for (index, entry) in files.iter().enumerate() {
+            if !PYTHON_EXTENSIONS.contains(&extension_of(entry.path())) {
+                imports.push(HashSet::new());
+                continue;
+            }
+            let relative = entry.path().strip_prefix(root).unwrap_or(entry.path());
+            let module = module_of(relative);
+            for start in 0..module.len() {
+                by_name
+                    .entry(module[start..].join("."))
+                    .or_default()
+                    .push(index);
+            }
+
+            let is_package = relative.file_stem() == Some("__init__".as_ref());
+            let package = if is_package {
+                &module[..]
+            } else {
+                &module[..module.len().saturating_sub(1)]
+            };
+            let mut stripper = Stripper::new();
+            let mut collector = Imports::new()?;
+            let mut is_script = false;
+            for chunk in ChunkedLines::new(source::open(entry.path())?) {
+                let chunk = chunk?;
+                let (code, _) = stripper.split(&chunk.text, chunk.ends_line);
+                if chunk.starts_line {
+                    is_script |= guard_pattern.is_match(&chunk.text);
+                    collector.observe(&code, package);
+                }
+            }
+
+            // tests are run by a tool rather than imported.
+            let is_test = testing::is_test_path(relative)
+                || testing::FRAMEWORKS
+                    .iter()
+                    .any(|framework| collector.imported.contains(*framework));
+            if !is_script && !is_test && !is_entry_module(entry.path(), &options.entry_modules) {
+                candidates.push(index);
+            }
+            imports.push(collector.imported);
+        }
+
+        let mut importers: HashMap<usize, HashSet<usize>> = HashMap::new();
+        for (importer, imported) in imports.iter().enumerate() {
+            for name in imported {
+                for &module in by_name.get(name).map(Vec::as_slice).unwrap_or_default() {
+                    if module != importer {
+                        importers.entry(module).or_default().insert(importer);
+                    }
+                }
+            }
+        }
+
+        Ok(Self {
+            candidates,
+            importers,
+        })
+    }
+
+    /// Finds the modules that are never imported, or only imported by other such modules. Files in other
+    /// languages are assumed to be live.
+    pub fn dead_modules(&self) -> HashSet<usize> {
+        let mut dead = HashSet::new();
+        loop {
+            let found: Vec<usize> = self
+                .candidates
+                .iter()
+                .copied()
+                .filter(|module| !dead.contains(module))
+                .filter(|module| {
+                    self.importers.get(module).is_none_or(|importers| {
+                        importers.iter().all(|importer| dead.contains(importer))
+                    })
+                })
+                .collect();
+            if found.is_empty() {
+                break;
+            }
+            dead.extend(found);
+        }
+        dead
+    }
+
+    /// Whether the given file imports the given module.
+    fn imports(&self, importer: usize, module: usize) -> bool {
+        self.importers
+            .get(&module)
+            .is_some_and(|importers| importers.contains(&importer))
+    }
+}
+
+/// Decides which files' mentions count for which declarations.
+pub trait MentionScope: Sync {
+    /// Whether mentions in the file with the given index count for a declaration in the given file.
+    fn sees(&self, file: usize, declared_in: &Path) -> bool;
+}
+
+/// Which files' mentions count for the declarations of each python module, under scoped counting: the
+/// module itself, its importers, the other modules in its directory, and files that are visible everywhere.
+pub struct Visibility<'a> {
+    graph: &'a ImportGraph,
+    files: &'a [SourceFile],
+    /// The index of each python module, by path.
+    modules: HashMap<&'a Path, usize>,
+    /// Files in other languages, which can't import modules, and files under the configured extra roots.
+    everywhere: HashSet<usize>,
+}
+
+impl<'a> Visibility<'a> {
+    pub fn new(
+        graph: &'a ImportGraph,
+        files: &'a [SourceFile],
+        root: &Path,
+        extra_roots: &[Regex],
+    ) -> Self {
+        let mut modules = HashMap::new();
+        let mut everywhere = HashSet::new();
+        for (index, entry) in files.iter().enumerate() {
+            let relative = entry.path().strip_prefix(root).unwrap_or(entry.path());
+            let relative = normalize(relative);
+            if !PYTHON_EXTENSIONS.contains(&extension_of(entry.path()))
+                || extra_roots
+                    .iter()
+                    .any(|pattern| pattern.is_match(&relative))
+            {
+                everywhere.insert(index);
+            }
+            if PYTHON_EXTENSIONS.contains(&extension_of(entry.path())) {
+                modules.insert(entry.path(), index);
+            }
+        }
+        Self {
+            graph,
+            files,
+            modules,
+            everywhere,
+        }
+    }
+}
+
+impl MentionScope for Visibility<'_> {
+    fn sees(&self, file: usize, declared_in: &Path) -> bool {
+        match self.modules.get(declared_in) {
+            Some(&module) => {
+                file == module
+                    || self.everywhere.contains(&file)
+                    || self.graph.imports(file, module)
+                    || self.files[file].path().parent() == declared_in.parent()
+            }
+            None => true,
+        }
+    }
+}
@@ -0,0 +1,67 @@
+use std::collections::BTreeMap;
+
+use anyhow::{anyhow, Result};
+
+/// Where a mention comes from, which policies can weigh differently.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Origin {
+    /// Source files in the scanned tree.
+    FirstParty,
+    /// Test files in the scanned tree.
+    Tests,
+    /// Files under the search roots, such as a virtualenv's site-packages or plugins checked out elsewhere.
+    ThirdParty,
+    /// Automation scripts and CI configuration, scanned with `--scan-scripts`.
+    Config,
+}
+
+impl Origin {
+    const ALL: [Origin; 4] = [
+        Origin::FirstParty,
+        Origin::Tests,
+        Origin::ThirdParty,
+        Origin::Config,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Origin::FirstParty => "first-party",
+            Origin::Tests => "tests",
+            Origin::ThirdParty => "third-party",
+            Origin::Config => "config",
+        }
+    }
+}
+
+/// How much a mention from each origin counts, one for any origin not configured.
+#[derive(Clone, Debug)]
+pub struct Weights([usize; 4]);
+
+impl Default for Weights {
+    fn default() -> Self {
+        Self([1; 4])
+    }
+}
+
+impl Weights {
+    /// Reads weights by origin name, such as `{ tests = 0 }`. First-party mentions include every
+    /// declaration's own, so their weight can't be changed.
+    pub fn new(configured: &BTreeMap<String, usize>) -> Result<Self> {
+        let mut weights = Self::default();
+        for (name, &weight) in configured {
+            let origin = Origin::ALL
+                .into_iter()
+                .find(|origin| origin.name() == name)
+                .ok_or_else(|| anyhow!("unknown origin {:?} in origin_weights", name))?;
+            if origin == Origin::FirstParty {
+                return Err(anyhow!("first-party mentions can't be weighed"));
+            }
+            weights.0[origin as usize] = weight;
+        }
+        Ok(weights)
+    }
+
+    pub fn of(&self, origin: Origin) -> usize {
+        self.0[origin as usize]
+    }
+}
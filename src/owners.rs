@@ -0,0 +1,144 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use regex::Regex;
+
+/// Where GitHub looks for a CODEOWNERS file, in order of precedence.
+const CANDIDATES: [&str; 3] = [".github/CODEOWNERS", "CODEOWNERS", "docs/CODEOWNERS"];
+
+/// Ownership rules parsed from a CODEOWNERS file.
+pub struct Owners {
+    rules: Vec<(Regex, Vec<String>)>,
+}
+
+impl Owners {
+    /// Looks for a CODEOWNERS file in the usual places under the given root.
+    pub fn find(root: &Path) -> Option<PathBuf> {
+        CANDIDATES
+            .iter()
+            .map(|candidate| root.join(candidate))
+            .find(|path| path.is_file())
+    }
+
+    /// Parses the given CODEOWNERS file.
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("failed to read owners file {}", path.display()))?;
+
+        let mut rules = Vec::new();
+        for line in contents.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let mut parts = line.split_whitespace();
+            if let Some(pattern) = parts.next() {
+                let owners = parts
+                    .take_while(|part| !part.starts_with('#'))
+                    .map(str::to_owned)
+                    .collect();
+                rules.push((pattern_to_regex(pattern)?, owners));
+            }
+        }
+
+        Ok(Self { rules })
+    }
+
+    /// Returns the owners of the given path, relative to the root. The last matching rule wins.
+    pub fn owners_of(&self, relative: &Path) -> &[String] {
+        let path = relative.to_string_lossy().replace('\\', "/");
+        self.rules
+            .iter()
+            .rev()
+            .find(|(pattern, _)| pattern.is_match(&path))
+            .map(|(_, owners)| owners.as_slice())
+            .unwrap_or_default()
+    }
+}
+
+/// Translates a gitignore-style pattern, as used by CODEOWNERS, into a regex over slash-separated paths.
+pub fn pattern_to_regex(pattern: &str) -> Result<Regex> {
+    let anchored = pattern.starts_with('/') || pattern.trim_end_matches('/').contains('/');
+    let directory_only = pattern.ends_with('/');
+    let pattern = pattern.trim_matches('/');
+
+    let mut regex = String::from(if anchored { "^" } else { "^(?:.*/)?" });
+    let mut rest = pattern;
+    while !rest.is_empty() {
+        if let Some(tail) = rest.strip_prefix("**/") {
+            regex.push_str("(?:.*/)?");
+            rest = tail;
+        } else if let Some(tail) = rest.strip_prefix("**") {
+            regex.push_str(".*");
+            rest = tail;
+        } else {
+            let c = rest.chars().next().unwrap();
+            match c {
+                '*' => regex.push_str("[^/]*"),
+                '?' => regex.push_str("[^/]"),
+                _ => regex.push_str(&regex::escape(&c.to_string())),
+            }
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+    regex.push_str(if directory_only { "/.*$" } else { "(?:/.*)?$" });
+
+    Regex::new(&regex).with_context(|| format!("invalid path pattern {}", pattern))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn owners(contents: &str) -> Owners {
+        let root = tempfile::Builder::new()
+            .prefix("snapbug")
+            .tempdir()
+            .unwrap();
+        let path = root.path().join("CODEOWNERS");
+        fs::write(&path, contents).unwrap();
+        Owners::load(&path).unwrap()
+    }
+
+    #[test]
+    fn the_last_matching_rule_wins() {
+        let owners = owners("# defaults\n* @everyone\n\n/src/ @core # the core team\n*.sql @dba\n");
+        assert_eq!(owners.owners_of(Path::new("README.md")), ["@everyone"]);
+        assert_eq!(owners.owners_of(Path::new("src/lib.py")), ["@core"]);
+        assert_eq!(owners.owners_of(Path::new("src/schema.sql")), ["@dba"]);
+    }
+
+    #[test]
+    fn patterns_follow_gitignore_anchoring() {
+        let owners = owners("docs/ @writers\n/build.py @release\napps/**/views.py @web\n");
+        assert_eq!(owners.owners_of(Path::new("docs/conf.py")), ["@writers"]);
+        assert!(owners.owners_of(Path::new("docs")).is_empty());
+        assert_eq!(owners.owners_of(Path::new("build.py")), ["@release"]);
+        assert!(owners.owners_of(Path::new("tools/build.py")).is_empty());
+        assert_eq!(owners.owners_of(Path::new("apps/views.py")), ["@web"]);
+        assert_eq!(
+            owners.owners_of(Path::new("apps/shop/cart/views.py")),
+            ["@web"]
+        );
+    }
+
+    #[test]
+    fn the_github_directory_is_searched_first() {
+        let root = tempfile::Builder::new()
+            .prefix("snapbug")
+            .tempdir()
+            .unwrap();
+        assert_eq!(Owners::find(root.path()), None);
+        fs::write(root.path().join("CODEOWNERS"), "").unwrap();
+        fs::create_dir(root.path().join(".github")).unwrap();
+        fs::write(root.path().join(".github").join("CODEOWNERS"), "").unwrap();
+        assert_eq!(
+            Owners::find(root.path()),
+            Some(root.path().join(".github").join("CODEOWNERS"))
+        );
+    }
+}
@@ -0,0 +1,85 @@
+use anyhow::{anyhow, Result};
+
+/// Rewrites the start of reported paths, such as a container's `/app` to the reviewer's checkout.
+#[derive(Clone, Default, Debug)]
+pub struct PathMap {
+    mappings: Vec<(String, String)>,
+}
+
+/// Parses a mapping given as `FROM=TO`.
+pub fn parse_mapping(s: &str) -> Result<(String, String)> {
+    match s.split_once('=') {
+        Some((from, to)) if !from.is_empty() => Ok((from.to_owned(), to.to_owned())),
+        _ => Err(anyhow!("expected FROM=TO, such as /app=/home/dev/project")),
+    }
+}
+
+impl PathMap {
+    pub fn new(mappings: Vec<(String, String)>) -> Self {
+        Self { mappings }
+    }
+
+    /// Rewrites a path with the first mapping whose prefix it starts with, matching whole components, or
+    /// none if no mapping does.
+    pub fn rewrite(&self, path: &str) -> Option<String> {
+        self.mappings.iter().find_map(|(from, to)| {
+            let from = from.trim_end_matches(['/', '\\']);
+            let rest = path.strip_prefix(from)?;
+            if rest.is_empty() {
+                return Some(to.clone());
+            }
+            let rest = rest.strip_prefix(['/', '\\'])?;
+            Some(if to.is_empty() {
+                rest.to_owned()
+            } else {
+                format!("{}/{}", to.trim_end_matches(['/', '\\']), rest)
+            })
+        })
+    }
+
+    /// Rewrites a path if any mapping matches it.
+    pub fn apply(&self, path: &str) -> String {
+        self.rewrite(path).unwrap_or_else(|| path.to_owned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn map(mappings: &[&str]) -> PathMap {
+        PathMap::new(
+            mappings
+                .iter()
+                .map(|mapping| parse_mapping(mapping).unwrap())
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn mappings_match_whole_components() {
+        let map = map(&["/app/=/home/dev/project", "/app-data=data"]);
+        assert_eq!(map.apply("/app/pkg/a.py"), "/home/dev/project/pkg/a.py");
+        assert_eq!(map.apply("/app"), "/home/dev/project");
+        assert_eq!(map.apply("/application/a.py"), "/application/a.py");
+        assert_eq!(map.apply("/app-data/b.py"), "data/b.py");
+    }
+
+    #[test]
+    fn the_first_matching_mapping_is_used() {
+        let map = map(&["/app/vendor=third_party", "/app=", "/app/src=src"]);
+        assert_eq!(map.apply("/app/vendor/lib.py"), "third_party/lib.py");
+        assert_eq!(map.apply("/app/src/main.py"), "src/main.py");
+        assert_eq!(map.rewrite("/srv/main.py"), None);
+    }
+
+    #[test]
+    fn mappings_need_a_prefix() {
+        assert!(parse_mapping("=/home").is_err());
+        assert!(parse_mapping("/app").is_err());
+        assert_eq!(
+            parse_mapping("/app=/a=b").unwrap(),
+            ("/app".to_owned(), "/a=b".to_owned())
+        );
+    }
+}
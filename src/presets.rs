@@ -0,0 +1,172 @@
+use anyhow::Result;
+use clap::ArgEnum;
+use regex::Regex;
+
+use crate::{frameworks::Framework, walk::SourceFile};
+
+/// Bundles of exemptions for common kinds of project, so that a first scan is not drowned in noise.
+#[derive(ArgEnum, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Preset {
+    /// A package used by others, whose public API is whatever its `__init__.py` files define.
+    Library,
+    /// A Django project, including views, admin, signals, management commands and Celery tasks.
+    DjangoApp,
+    /// Notebooks' supporting code, such as PyTorch modules, scikit-learn estimators and pipeline tasks.
+    DataScience,
+}
+
+impl Preset {
+    /// Gitignore-style patterns of paths that are left out, such as generated code.
+    pub fn excludes(&self) -> &'static [&'static str] {
+        match self {
+            Preset::Library => &["build/", "dist/"],
+            Preset::DjangoApp => &["migrations/"],
+            Preset::DataScience => &[".ipynb_checkpoints/"],
+        }
+    }
+
+    /// Frameworks whose decorated functions are treated as used, as with `--framework`.
+    pub fn frameworks(&self) -> &'static [Framework] {
+        match self {
+            Preset::Library => &[],
+            Preset::DjangoApp => &[Framework::Django, Framework::Celery],
+            Preset::DataScience => &[Framework::Airflow, Framework::Prefect, Framework::Dagster],
+        }
+    }
+
+    /// Decorators marking functions that are invoked for us, besides those of the frameworks.
+    pub fn decorators(&self) -> &'static [&'static str] {
+        match self {
+            Preset::Library | Preset::DataScience => &[],
+            // django rest framework's views and viewset actions.
+            Preset::DjangoApp => &["api_view", "action"],
+        }
+    }
+
+    /// Methods that the framework calls by convention, never by name.
+    pub fn framework_methods(&self) -> &'static [&'static str] {
+        match self {
+            Preset::Library => &[],
+            Preset::DjangoApp => &[
+                "get",
+                "post",
+                "put",
+                "patch",
+                "delete",
+                "dispatch",
+                "get_queryset",
+                "get_context_data",
+                "get_object",
+                "get_success_url",
+                "get_absolute_url",
+                "form_valid",
+                "form_invalid",
+                "clean",
+                "handle",
+                "add_arguments",
+                "ready",
+                "process_request",
+                "process_response",
+                "process_view",
+                "process_exception",
+                "get_serializer_class",
+                "perform_create",
+                "perform_update",
+                "perform_destroy",
+                "to_representation",
+                "to_internal_value",
+                "validate",
+                "has_permission",
+                "has_object_permission",
+            ],
+            Preset::DataScience => &[
+                "forward",
+                "training_step",
+                "validation_step",
+                "test_step",
+                "predict_step",
+                "configure_optimizers",
+                "prepare_data",
+                "setup",
+                "train_dataloader",
+                "val_dataloader",
+                "test_dataloader",
+                "fit",
+                "transform",
+                "fit_transform",
+                "inverse_transform",
+                "predict",
+                "predict_proba",
+                "score",
+                "get_feature_names_out",
+            ],
+        }
+    }
+
+    /// Top-level packages whose importers are covered by the framework, and so by its methods.
+    pub fn packages(&self) -> &'static [&'static str] {
+        match self {
+            Preset::Library => &[],
+            Preset::DjangoApp => &["django", "rest_framework", "celery"],
+            Preset::DataScience => &["torch", "lightning", "pytorch_lightning", "sklearn"],
+        }
+    }
+
+    /// Files marking the directory they are in, and everything below it, as covered by the framework.
+    pub fn markers(&self) -> &'static [&'static str] {
+        match self {
+            Preset::Library | Preset::DataScience => &[],
+            // every django app has its configuration in `apps.py`.
+            Preset::DjangoApp => &["apps.py"],
+        }
+    }
+
+    /// Modules whose top-level functions are entry points.
+    pub fn entry_modules(&self) -> &'static [&'static str] {
+        match self {
+            Preset::Library => &["__init__.py"],
+            Preset::DjangoApp => &["urls.py", "apps.py", "settings.py"],
+            Preset::DataScience => &[],
+        }
+    }
+}
+
+/// A preset's framework methods, which are only allowed to be unused in the files its framework covers, as
+/// names such as `setup` or `get` are common elsewhere.
+#[derive(Debug)]
+pub(crate) struct PresetMethods {
+    methods: &'static [&'static str],
+    imports: Regex,
+    markers: &'static [&'static str],
+}
+
+impl PresetMethods {
+    pub(crate) fn new(preset: Preset) -> Result<Self> {
+        Ok(Self {
+            methods: preset.framework_methods(),
+            imports: Regex::new(&format!(
+                r"^\s*(?:import|from)\s+(?:{})\b",
+                preset.packages().join("|")
+            ))?,
+            markers: preset.markers(),
+        })
+    }
+
+    pub(crate) fn contains(&self, name: &str) -> bool {
+        self.methods.contains(&name)
+    }
+
+    /// Whether the line of code imports one of the framework's packages.
+    pub(crate) fn is_import(&self, code: &str) -> bool {
+        self.imports.is_match(code)
+    }
+
+    /// Whether the file is in a directory marked as covered by the framework, up to the root it was walked from.
+    pub(crate) fn is_marked(&self, entry: &SourceFile) -> bool {
+        entry.directories().any(|directory| {
+            self.markers
+                .iter()
+                .any(|marker| directory.join(marker).is_file())
+        })
+    }
+}
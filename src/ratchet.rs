@@ -0,0 +1,71 @@
+use std::{fs, path::Path};
+
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::atomic;
+
+/// The finding count recorded by the last successful ratchet run.
+#[derive(Serialize, Deserialize, Debug)]
+struct State {
+    findings: usize,
+}
+
+/// Fails if the number of findings has grown since the recorded state.
+/// The state is created on first use and tightened whenever the count drops.
+pub fn check(path: &Path, findings: usize) -> Result<()> {
+    let previous = if path.exists() {
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("failed to read ratchet state {}", path.display()))?;
+        let state: State = serde_json::from_str(&contents)
+            .with_context(|| format!("invalid ratchet state {}", path.display()))?;
+        Some(state.findings)
+    } else {
+        None
+    };
+
+    match previous {
+        Some(previous) if findings > previous => Err(anyhow!(
+            "findings increased from {} to {}",
+            previous,
+            findings
+        )),
+        Some(previous) if findings == previous => Ok(()),
+        _ => {
+            let state = serde_json::to_string_pretty(&State { findings })?;
+            atomic::write(path, state + "\n")
+                .with_context(|| format!("failed to write ratchet state {}", path.display()))?;
+            eprintln!("ratchet set to {} findings", findings);
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn recorded(path: &Path) -> usize {
+        let state: State = serde_json::from_str(&fs::read_to_string(path).unwrap()).unwrap();
+        state.findings
+    }
+
+    #[test]
+    fn the_count_may_only_drop() {
+        let root = tempfile::Builder::new()
+            .prefix("snapbug")
+            .tempdir()
+            .unwrap();
+        let path = root.path().join("ratchet.json");
+
+        check(&path, 3).unwrap();
+        assert_eq!(recorded(&path), 3);
+        check(&path, 3).unwrap();
+        let err = check(&path, 4).unwrap_err();
+        assert_eq!(err.to_string(), "findings increased from 3 to 4");
+        assert_eq!(recorded(&path), 3);
+        check(&path, 1).unwrap();
+        assert_eq!(recorded(&path), 1);
+        assert!(check(&path, 2).is_err());
+    }
+}
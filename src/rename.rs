@@ -0,0 +1,144 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Result;
+
+use crate::{
+    exclude::Exclusions, extension_of, lines::ChunkedLines, source, strip::Stripper, walk::walk,
+    PYTHON_EXTENSIONS,
+};
+
+/// The longest excerpt of a line that is printed.
+const MAX_EXCERPT: usize = 120;
+
+/// How an occurrence of a name is used.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+enum Reference {
+    Definition,
+    Decorator,
+    Import,
+    Call,
+    Attribute,
+    /// Mentioned in code in some other way, such as being passed as a callback.
+    Code,
+    /// Inside a comment or string literal.
+    Prose,
+    /// In a shell script, makefile, dockerfile or CI configuration.
+    Script,
+    /// Part of a longer identifier, which a rename would not touch.
+    Substring,
+}
+
+impl Reference {
+    fn describe(&self) -> &'static str {
+        match self {
+            Reference::Definition => "definitions",
+            Reference::Decorator => "decorators",
+            Reference::Import => "imports",
+            Reference::Call => "calls",
+            Reference::Attribute => "attribute accesses",
+            Reference::Code => "other code",
+            Reference::Prose => "comments and strings",
+            Reference::Script => "scripts",
+            Reference::Substring => "inside other identifiers",
+        }
+    }
+}
+
+fn is_identifier(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Classifies an occurrence of the name at the given byte offset of a line of code.
+fn classify(code: &str, offset: usize, name: &str) -> Reference {
+    let before = &code[..offset];
+    let after = &code[offset + name.len()..];
+    if before.ends_with(is_identifier) || after.starts_with(is_identifier) {
+        return Reference::Substring;
+    }
+
+    let trimmed = code.trim_start();
+    let keyword = before.trim_end();
+    if keyword.ends_with("def") || keyword.ends_with("class") {
+        Reference::Definition
+    } else if trimmed.starts_with('@') {
+        Reference::Decorator
+    } else if trimmed.starts_with("import ") || trimmed.starts_with("from ") {
+        Reference::Import
+    } else if after.trim_start().starts_with('(') {
+        Reference::Call
+    } else if before.ends_with('.') {
+        Reference::Attribute
+    } else {
+        Reference::Code
+    }
+}
+
+/// Shortens a line for display.
+fn excerpt(line: &str) -> &str {
+    let line = line.trim();
+    match line.char_indices().nth(MAX_EXCERPT) {
+        Some((end, _)) => &line[..end],
+        None => line,
+    }
+}
+
+/// Prints every occurrence of the name, grouped by how it is used, to preview what a rename or removal would touch.
+pub fn run(path: &Path, name: &str) -> Result<()> {
+    // walking "." as given would skip it as hidden.
+    let root = path.canonicalize()?;
+    let extensions: Vec<String> = PYTHON_EXTENSIONS
+        .iter()
+        .map(|&extension| extension.to_owned())
+        .collect();
+    let (files, _) = walk(&root, &extensions, true, &Exclusions::default());
+
+    let mut occurrences: BTreeMap<Reference, Vec<(PathBuf, usize, String)>> = BTreeMap::new();
+    for entry in &files {
+        let is_python = PYTHON_EXTENSIONS.contains(&extension_of(entry.path()));
+        let mut stripper = Stripper::new();
+        let mut lineno = 0;
+
+        for chunk in ChunkedLines::new(source::open(entry.path())?) {
+            let chunk = chunk?;
+            let mut found = Vec::new();
+            if is_python {
+                let (code, prose) = stripper.split(&chunk.text, chunk.ends_line);
+                found.extend(
+                    code.match_indices(name)
+                        .map(|(offset, _)| classify(&code, offset, name)),
+                );
+                found.extend(prose.matches(name).map(|_| Reference::Prose));
+            } else {
+                found.extend(chunk.text.matches(name).map(|_| Reference::Script));
+            }
+
+            for reference in found {
+                occurrences.entry(reference).or_default().push((
+                    entry.path().to_owned(),
+                    lineno + 1,
+                    excerpt(&chunk.text).to_owned(),
+                ));
+            }
+            if chunk.ends_line {
+                lineno += 1;
+            }
+        }
+    }
+
+    if occurrences.is_empty() {
+        println!("\"{}\" does not occur anywhere", name);
+        return Ok(());
+    }
+
+    for (reference, found) in occurrences {
+        println!("{} ({}):", reference.describe(), found.len());
+        for (path, line, text) in found {
+            println!("  {}:{}: {}", path.display(), line, text);
+        }
+    }
+
+    Ok(())
+}
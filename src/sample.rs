@@ -0,0 +1,83 @@
+use std::path::Path;
+
+use anyhow::{anyhow, Result};
+
+use crate::{baseline::normalize, metadata::sha256, walk::SourceFile};
+
+/// Parses a percentage such as `10%`, or `10`, of files to sample.
+pub fn parse_percent(s: &str) -> Result<f64> {
+    let percent: f64 = s
+        .trim_end_matches('%')
+        .parse()
+        .map_err(|_| anyhow!("expected a percentage such as 10%"))?;
+    if percent > 0.0 && percent <= 100.0 {
+        Ok(percent)
+    } else {
+        Err(anyhow!("the sample must be more than 0% and at most 100%"))
+    }
+}
+
+/// Keeps roughly the given percentage of files, returning those kept and those left out. Each file is kept
+/// or left out by a hash of its path relative to the root, so the same files are sampled every run, and on
+/// every machine.
+pub fn select(
+    files: Vec<SourceFile>,
+    root: &Path,
+    percent: f64,
+) -> (Vec<SourceFile>, Vec<SourceFile>) {
+    let threshold = (percent * 100.0) as u64;
+    files.into_iter().partition(|entry| {
+        let relative = entry.path().strip_prefix(root).unwrap_or(entry.path());
+        let digest = sha256(normalize(relative).as_bytes());
+        let bucket = u64::from_str_radix(&digest[..8], 16).unwrap_or_default() % 10_000;
+        bucket < threshold
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::PathBuf};
+
+    use super::*;
+    use crate::{walk, Options, ScanOptions};
+
+    /// Samples a tree of 200 files, returning the relative paths of those kept.
+    fn sampled(root: &Path, percent: f64) -> Vec<PathBuf> {
+        let options = ScanOptions::new(&Options::default(), root).unwrap();
+        let (files, _) = walk::files(root, &options);
+        let (kept, left_out) = select(files, root, percent);
+        assert_eq!(kept.len() + left_out.len(), 200);
+        kept.iter()
+            .map(|entry| entry.path().strip_prefix(root).unwrap().to_owned())
+            .collect()
+    }
+
+    #[test]
+    fn samples_are_stable_and_grow_with_the_percentage() {
+        let dir = tempfile::Builder::new()
+            .prefix("snapbug")
+            .tempdir()
+            .unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        for index in 0..200 {
+            fs::write(root.join(format!("module_{}.py", index)), "pass\n").unwrap();
+        }
+
+        let tenth = sampled(&root, 10.0);
+        let half = sampled(&root, 50.0);
+        assert_eq!(tenth, sampled(&root, 10.0));
+        assert!((5..40).contains(&tenth.len()), "{}", tenth.len());
+        assert!((70..130).contains(&half.len()), "{}", half.len());
+        assert!(tenth.iter().all(|path| half.contains(path)));
+        assert_eq!(sampled(&root, 100.0).len(), 200);
+    }
+
+    #[test]
+    fn percentages_are_more_than_nothing_and_at_most_everything() {
+        assert_eq!(parse_percent("10%").unwrap(), 10.0);
+        assert_eq!(parse_percent("2.5").unwrap(), 2.5);
+        assert!(parse_percent("0%").is_err());
+        assert!(parse_percent("150%").is_err());
+        assert!(parse_percent("ten").is_err());
+    }
+}
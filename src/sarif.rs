@@ -0,0 +1,143 @@
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+use crate::cli::JsonFinding;
+
+/// The SARIF version written, which GitHub code scanning accepts.
+const VERSION: &str = "2.1.0";
+
+const SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+/// A SARIF log with a single run, for uploading to code scanning services.
+#[derive(Serialize)]
+pub struct Log<'a> {
+    #[serde(rename = "$schema")]
+    schema: &'static str,
+    version: &'static str,
+    runs: [Run<'a>; 1],
+}
+
+#[derive(Serialize)]
+struct Run<'a> {
+    tool: Tool,
+    results: Vec<SarifResult<'a>>,
+}
+
+#[derive(Serialize)]
+struct Tool {
+    driver: Driver,
+}
+
+#[derive(Serialize)]
+struct Driver {
+    name: &'static str,
+    version: &'static str,
+    rules: Vec<Rule>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Rule {
+    id: String,
+    short_description: Message,
+}
+
+#[derive(Serialize)]
+struct Message {
+    text: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SarifResult<'a> {
+    rule_id: &'a str,
+    level: &'static str,
+    message: Message,
+    locations: [Location<'a>; 1],
+    partial_fingerprints: BTreeMap<&'static str, &'a str>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Location<'a> {
+    physical_location: PhysicalLocation<'a>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PhysicalLocation<'a> {
+    artifact_location: ArtifactLocation<'a>,
+    region: Region,
+}
+
+#[derive(Serialize)]
+struct ArtifactLocation<'a> {
+    uri: &'a str,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Region {
+    start_line: usize,
+    end_line: usize,
+}
+
+impl<'a> Log<'a> {
+    /// Describes the findings, given every rule that could have produced them with its description.
+    /// Quarantined findings are included as notes, which never block a merge.
+    pub fn new(
+        rules: Vec<(String, String)>,
+        findings: &'a [JsonFinding<'a>],
+        quarantined: &'a [JsonFinding<'a>],
+    ) -> Self {
+        let results = findings
+            .iter()
+            .map(|finding| (finding, finding.severity))
+            .chain(quarantined.iter().map(|finding| (finding, "note")))
+            .map(|(finding, level)| SarifResult {
+                rule_id: &finding.rule,
+                level,
+                message: Message {
+                    text: finding.message.clone(),
+                },
+                locations: [Location {
+                    physical_location: PhysicalLocation {
+                        artifact_location: ArtifactLocation {
+                            uri: &finding.relative,
+                        },
+                        region: Region {
+                            start_line: finding.line,
+                            end_line: finding.line + finding.lines.max(1) - 1,
+                        },
+                    },
+                }],
+                partial_fingerprints: BTreeMap::from([(
+                    "snapbug/v1",
+                    finding.fingerprint.as_str(),
+                )]),
+            })
+            .collect();
+
+        Self {
+            schema: SCHEMA,
+            version: VERSION,
+            runs: [Run {
+                tool: Tool {
+                    driver: Driver {
+                        name: "snapbug",
+                        version: env!("CARGO_PKG_VERSION"),
+                        rules: rules
+                            .into_iter()
+                            .map(|(id, description)| Rule {
+                                id,
+                                short_description: Message { text: description },
+                            })
+                            .collect(),
+                    },
+                },
+                results,
+            }],
+        }
+    }
+}
@@ -0,0 +1,119 @@
+use std::{fmt::Write as _, fs, path::Path, time::Instant};
+
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::{atomic, scan_tree, Options};
+
+/// Modules in the synthetic tree.
+const MODULES: usize = 400;
+
+/// Functions declared by each module, every other one of which is never called.
+const FUNCTIONS_PER_MODULE: usize = 10;
+
+/// How many times the tree is scanned, keeping the fastest, so that a busy machine matters less.
+const RUNS: usize = 3;
+
+/// The fraction of the baseline's throughput a run may fall to before it counts as a regression.
+const TOLERANCE: f64 = 0.5;
+
+/// The throughput of a scan of the synthetic tree, as stored in a baseline file.
+#[derive(Serialize, Deserialize, Debug)]
+struct Measurement {
+    version: String,
+    files: usize,
+    lines: usize,
+    seconds: f64,
+    lines_per_second: f64,
+}
+
+/// Scans a synthetic tree, checking that exactly its unused functions are found, and compares the
+/// throughput against the baseline if given, failing if it has fallen by more than half. With `save`,
+/// the measurement is written to the baseline instead.
+pub fn run(baseline: Option<&Path>, save: bool) -> Result<()> {
+    let tree = tempfile::Builder::new().prefix("snapbug").tempdir()?;
+    let mut lines = 0;
+    for module in 0..MODULES {
+        let source = synthetic_module(module);
+        lines += source.lines().count();
+        fs::write(tree.path().join(format!("module_{}.py", module)), source)?;
+    }
+
+    let mut fastest = f64::INFINITY;
+    for _ in 0..RUNS {
+        let started = Instant::now();
+        let findings = scan_tree(tree.path(), &Options::default())?;
+        fastest = fastest.min(started.elapsed().as_secs_f64());
+
+        let expected = MODULES * FUNCTIONS_PER_MODULE / 2;
+        if findings.len() != expected {
+            return Err(anyhow!(
+                "the synthetic tree has {} unused functions, but {} were found",
+                expected,
+                findings.len()
+            ));
+        }
+    }
+
+    let measurement = Measurement {
+        version: env!("CARGO_PKG_VERSION").to_owned(),
+        files: MODULES,
+        lines,
+        seconds: fastest,
+        lines_per_second: lines as f64 / fastest,
+    };
+    println!(
+        "scanned {} files of {} lines in {:.3}s, {:.0} lines per second",
+        measurement.files, measurement.lines, measurement.seconds, measurement.lines_per_second
+    );
+
+    let path = match baseline {
+        Some(path) => path,
+        None => return Ok(()),
+    };
+    if save {
+        atomic::write(path, serde_json::to_string_pretty(&measurement)?)
+            .with_context(|| format!("failed to write {}", path.display()))?;
+        println!("saved the measurement to {}", path.display());
+        return Ok(());
+    }
+
+    let contents = fs::read_to_string(path)
+        .with_context(|| format!("failed to read performance baseline {}", path.display()))?;
+    let stored: Measurement = serde_json::from_str(&contents)
+        .with_context(|| format!("invalid performance baseline {}", path.display()))?;
+    let ratio = measurement.lines_per_second / stored.lines_per_second;
+    println!(
+        "{:.0}% of the {:.0} lines per second measured by version {}",
+        ratio * 100.0,
+        stored.lines_per_second,
+        stored.version
+    );
+    if ratio < TOLERANCE {
+        Err(anyhow!(
+            "throughput fell to {:.0}% of the baseline",
+            ratio * 100.0
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+/// A module whose even functions are called by the next module, and whose odd ones are never called.
+/// The calls are made from a test, which is allowed to be unused.
+fn synthetic_module(module: usize) -> String {
+    let previous = (module + MODULES - 1) % MODULES;
+    let mut source = format!("\"\"\"Synthetic module {}.\"\"\"\n\n", module);
+    for function in 0..FUNCTIONS_PER_MODULE {
+        let _ = write!(
+            source,
+            "\ndef function_{}_{}(value):\n    \"\"\"Scales the value.\"\"\"\n    # keep in step with the others.\n    if value > {}:\n        return value * {}\n    return value\n\n",
+            module, function, function, function
+        );
+    }
+    source.push_str("\ndef test_calls():\n");
+    for function in (0..FUNCTIONS_PER_MODULE).step_by(2) {
+        let _ = writeln!(source, "    function_{}_{}(1)", previous, function);
+    }
+    source
+}
@@ -0,0 +1,121 @@
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, Read},
+    path::Path,
+    sync::OnceLock,
+};
+
+use anyhow::Result;
+use encoding_rs::Encoding;
+use encoding_rs_io::{DecodeReaderBytes, DecodeReaderBytesBuilder};
+use regex::bytes::Regex;
+
+/// How much of each of the first two lines is searched for an encoding declaration.
+const DECLARATION_LIMIT: u64 = 1024;
+
+/// Opens a source file for reading as UTF-8, decoding it from the encoding declared in its first two
+/// lines as described by PEP 263, or from the encoding given by its byte order mark. The byte order
+/// mark itself is stripped, so that it never sticks to the first identifier in the file.
+pub fn open(path: &Path) -> Result<BufReader<DecodeReaderBytes<File, Vec<u8>>>> {
+    let declared = declared_encoding(path)?;
+    let reader = DecodeReaderBytesBuilder::new()
+        .encoding(declared)
+        .strip_bom(true)
+        .build(File::open(path)?);
+    Ok(BufReader::new(reader))
+}
+
+/// Matches an encoding declaration such as `# -*- coding: latin-1 -*-`, compiled once for all files.
+fn declaration_pattern() -> Result<&'static Regex> {
+    static PATTERN: OnceLock<Result<Regex, regex::Error>> = OnceLock::new();
+    PATTERN
+        .get_or_init(|| Regex::new(r"^[ \t\f]*#.*?coding[:=][ \t]*([-\w.]+)"))
+        .as_ref()
+        .map_err(|err| err.clone().into())
+}
+
+/// Finds an encoding declaration in the first two lines of a file.
+fn declared_encoding(path: &Path) -> Result<Option<&'static Encoding>> {
+    let pattern = declaration_pattern()?;
+    let mut reader = BufReader::new(File::open(path)?);
+
+    for _ in 0..2 {
+        let mut line = Vec::new();
+        (&mut reader)
+            .take(DECLARATION_LIMIT)
+            .read_until(b'\n', &mut line)?;
+        let line = line.strip_prefix(b"\xef\xbb\xbf").unwrap_or(&line);
+        if let Some(label) = pattern.captures(line).and_then(|c| c.get(1)) {
+            return Ok(lookup(&String::from_utf8_lossy(label.as_bytes())));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Finds an encoding by its python name, which may differ from the WHATWG labels, e.g. `latin_1` or `utf-8-sig`.
+fn lookup(label: &str) -> Option<&'static Encoding> {
+    let label = label.to_ascii_lowercase().replace('_', "-");
+    let label = label.strip_suffix("-sig").unwrap_or(&label);
+    Encoding::for_label(label.as_bytes())
+        .or_else(|| Encoding::for_label(label.replace('-', "").as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+
+    /// Reads a file holding the given bytes as source.
+    fn try_read(bytes: &[u8]) -> std::io::Result<String> {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(bytes).unwrap();
+        let mut text = String::new();
+        open(file.path()).unwrap().read_to_string(&mut text)?;
+        Ok(text)
+    }
+
+    fn read(bytes: &[u8]) -> String {
+        try_read(bytes).unwrap()
+    }
+
+    #[test]
+    fn coding_cookies_are_honoured() {
+        assert_eq!(
+            read(b"# -*- coding: latin-1 -*-\nx = '\xe9'\n"),
+            "# -*- coding: latin-1 -*-\nx = '\u{e9}'\n"
+        );
+        assert_eq!(
+            read(b"#!/usr/bin/env python\n# vim: set fileencoding=cp1252 :\nx = '\x80'\n"),
+            "#!/usr/bin/env python\n# vim: set fileencoding=cp1252 :\nx = '\u{20ac}'\n"
+        );
+    }
+
+    #[test]
+    fn cookies_after_the_second_line_are_ignored() {
+        // so the file is read as utf-8, which it isn't.
+        assert!(try_read(b"\n\n# coding: latin-1\nx = '\xe9'\n").is_err());
+    }
+
+    #[test]
+    fn byte_order_marks_are_stripped() {
+        assert_eq!(read(b"\xef\xbb\xbfdef f():\n"), "def f():\n");
+        assert_eq!(read(b"\xff\xfex\x00 \x00=\x00 \x001\x00"), "x = 1");
+    }
+
+    #[test]
+    fn byte_order_marks_and_cookies_can_be_combined() {
+        assert_eq!(
+            read(b"\xef\xbb\xbf# coding: utf-8-sig\nname = '\xc3\xa9'\n"),
+            "# coding: utf-8-sig\nname = '\u{e9}'\n"
+        );
+    }
+
+    #[test]
+    fn python_encoding_names_are_understood() {
+        assert_eq!(lookup("latin_1"), Some(encoding_rs::WINDOWS_1252));
+        assert_eq!(lookup("UTF-8-SIG"), Some(encoding_rs::UTF_8));
+        assert_eq!(lookup("euc_jp"), Some(encoding_rs::EUC_JP));
+    }
+}
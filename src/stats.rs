@@ -0,0 +1,23 @@
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+use crate::cache::CacheStats;
+
+/// Totals describing a scan, so that dashboards don't need to count findings themselves.
+#[derive(Serialize, Default, Debug)]
+pub struct Stats {
+    pub files: usize,
+    /// Declarations considered, by kind.
+    pub definitions: BTreeMap<&'static str, usize>,
+    pub findings_by_rule: BTreeMap<String, usize>,
+    /// Findings hidden by the baseline or ignore file.
+    pub suppressed: usize,
+    /// Findings in modules that look up functions dynamically.
+    pub quarantined: usize,
+    /// Entries left out of the scan, by reason.
+    pub skipped: BTreeMap<&'static str, usize>,
+    /// How many files' scans were reused from the cache, if one was given.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cache: Option<CacheStats>,
+}
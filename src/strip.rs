@@ -0,0 +1,275 @@
+use std::{iter::Peekable, str::Chars};
+
+/// Where a piece of source text came from.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Region {
+    Code,
+    /// After a `#`, until the end of the line.
+    Comment,
+    /// Inside a string literal opened with `quote`, possibly triple-quoted.
+    String {
+        quote: char,
+        triple: bool,
+        /// An f-string, whose replacement fields hold code.
+        formatted: bool,
+    },
+    /// Inside a replacement field of an f-string, `depth` braces deep.
+    Expression {
+        quote: char,
+        triple: bool,
+        depth: usize,
+    },
+}
+
+/// Splits python source lines into code and prose (comments and string literals).
+///
+/// String state is carried between lines so that multi-line docstrings are handled. The replacement
+/// fields of f-strings are code, so that `f"{compute_total()}"` still references `compute_total`, even
+/// in f-strings nested inside them.
+pub struct Stripper {
+    region: Region,
+    /// The replacement fields enclosing the current string, innermost last.
+    outer: Vec<Region>,
+}
+
+impl Stripper {
+    pub fn new() -> Self {
+        Self {
+            region: Region::Code,
+            outer: Vec::new(),
+        }
+    }
+
+    /// Whether the next line starts inside a multi-line string.
+    pub fn in_string(&self) -> bool {
+        matches!(
+            self.region,
+            Region::String { .. } | Region::Expression { .. }
+        )
+    }
+
+    /// Returns the code and prose portions of the given line, or piece of a line if `ends_line` is false.
+    /// Stripped text is replaced with spaces so that identifiers never run together.
+    pub fn split(&mut self, chunk: &str, ends_line: bool) -> (String, String) {
+        let mut code = String::with_capacity(chunk.len());
+        let mut prose = String::new();
+        let mut chars = chunk.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match self.region {
+                Region::Comment => prose.push(c),
+                Region::Code => match c {
+                    '#' => {
+                        self.region = Region::Comment;
+                        prose.push(' ');
+                    }
+                    '"' | '\'' => self.open_string(c, &mut chars, &mut code, &mut prose),
+                    _ => code.push(c),
+                },
+                Region::String {
+                    quote,
+                    triple,
+                    formatted,
+                } => match c {
+                    '\\' => {
+                        prose.push(c);
+                        prose.extend(chars.next());
+                    }
+                    c if c == quote => {
+                        if triple {
+                            let mut lookahead = chars.clone();
+                            if lookahead.next() != Some(quote) || lookahead.next() != Some(quote) {
+                                prose.push(c);
+                                continue;
+                            }
+                            chars.next();
+                            chars.next();
+                        }
+                        self.region = self.outer.pop().unwrap_or(Region::Code);
+                        code.push(' ');
+                    }
+                    // doubled braces are literal.
+                    '{' if formatted && chars.next_if_eq(&'{').is_none() => {
+                        self.region = Region::Expression {
+                            quote,
+                            triple,
+                            depth: 1,
+                        };
+                        code.push(' ');
+                    }
+                    _ => prose.push(c),
+                },
+                Region::Expression {
+                    quote,
+                    triple,
+                    depth,
+                } => match c {
+                    '\'' | '"' => self.open_string(c, &mut chars, &mut code, &mut prose),
+                    '{' | '}' => {
+                        let depth = if c == '{' { depth + 1 } else { depth - 1 };
+                        self.region = if depth == 0 {
+                            code.push(' ');
+                            Region::String {
+                                quote,
+                                triple,
+                                formatted: true,
+                            }
+                        } else {
+                            code.push(c);
+                            Region::Expression {
+                                quote,
+                                triple,
+                                depth,
+                            }
+                        };
+                    }
+                    _ => code.push(c),
+                },
+            }
+        }
+
+        if ends_line {
+            if self.region == Region::Comment {
+                self.region = Region::Code;
+            }
+            // single-quoted strings cannot span lines without an escaped newline.
+            while !chunk.ends_with('\\')
+                && matches!(
+                    self.region,
+                    Region::String { triple: false, .. } | Region::Expression { triple: false, .. }
+                )
+            {
+                self.region = self.outer.pop().unwrap_or(Region::Code);
+            }
+        }
+
+        (code, prose)
+    }
+
+    /// Enters a string literal opened by the given quote, inside code or a replacement field.
+    fn open_string(
+        &mut self,
+        quote: char,
+        chars: &mut Peekable<Chars>,
+        code: &mut String,
+        prose: &mut String,
+    ) {
+        let triple = chars.next_if_eq(&quote).is_some();
+        if triple && chars.next_if_eq(&quote).is_none() {
+            // an empty string, not the start of a triple-quoted one.
+            code.push(' ');
+            return;
+        }
+        if self.region != Region::Code {
+            self.outer.push(self.region);
+        }
+        self.region = Region::String {
+            quote,
+            triple,
+            formatted: is_formatted(code),
+        };
+        code.push(' ');
+        prose.push(' ');
+    }
+}
+
+/// Whether the code just before an opening quote is a prefix making it an f-string, such as `f` or `rf`.
+fn is_formatted(code: &str) -> bool {
+    let prefix: Vec<char> = code
+        .chars()
+        .rev()
+        .take_while(|&c| c.is_alphanumeric() || c == '_')
+        .collect();
+    prefix.len() <= 2
+        && prefix.iter().any(|&c| c == 'f' || c == 'F')
+        && prefix.iter().all(|&c| "rRfF".contains(c))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Splits whole lines, returning their code and prose with runs of whitespace collapsed.
+    fn split(lines: &[&str]) -> (String, String) {
+        let mut stripper = Stripper::new();
+        let (mut code, mut prose) = (Vec::new(), Vec::new());
+        for line in lines {
+            let (line_code, line_prose) = stripper.split(line, true);
+            code.push(line_code);
+            prose.push(line_prose);
+        }
+        let collapse = |text: Vec<String>| {
+            text.join(" ")
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+        (collapse(code), collapse(prose))
+    }
+
+    #[test]
+    fn comments_and_strings_are_prose() {
+        assert_eq!(
+            split(&["x = 'text' # note"]),
+            ("x =".to_owned(), "text note".to_owned())
+        );
+    }
+
+    #[test]
+    fn replacement_fields_are_code() {
+        assert_eq!(
+            split(&[r#"x = f"total: {compute_total()}""#]),
+            ("x = f compute_total()".to_owned(), "total:".to_owned())
+        );
+    }
+
+    #[test]
+    fn nested_f_strings_are_code_too() {
+        let (code, prose) = split(&[r#"x = f"{fmt(f'{inner()}')} tail""#]);
+        assert_eq!(code, "x = f fmt(f inner() )");
+        assert_eq!(prose, "tail");
+        assert_eq!(
+            split(&[r#"x = f"{value:{width}}" + after()"#]).0,
+            "x = f value:{width} + after()"
+        );
+    }
+
+    #[test]
+    fn strings_inside_replacement_fields_are_prose() {
+        let (code, prose) = split(&[r#"x = f"{d['key']} {{literal}}" + after()"#]);
+        assert_eq!(code, "x = f d[ ] + after()");
+        assert!(prose.starts_with("key"), "{}", prose);
+    }
+
+    #[test]
+    fn escaped_quotes_do_not_end_strings() {
+        assert_eq!(split(&[r#"x = "it\"s" + helper()"#]).0, "x = + helper()");
+        assert_eq!(split(&[r"x = 'a\\' + helper()"]).0, "x = + helper()");
+    }
+
+    #[test]
+    fn triple_quoted_strings_span_lines() {
+        let (code, prose) = split(&[
+            r#"x = """first"#,
+            r#"helper() "" ' "#,
+            r#"last""" + after()"#,
+        ]);
+        assert_eq!(code, "x = + after()");
+        assert!(prose.contains("helper()"), "{}", prose);
+    }
+
+    #[test]
+    fn triple_quoted_f_strings_keep_fields_across_lines() {
+        let (code, _) = split(&[r#"x = f"""{first()}"#, r#"{second(f'{third()}')}""""#]);
+        assert_eq!(code, "x = f first() second(f third() )");
+    }
+
+    #[test]
+    fn unterminated_single_quoted_strings_end_with_the_line() {
+        assert_eq!(split(&["x = 'oops", "helper()"]).0, "x = helper()");
+        assert_eq!(
+            split(&["x = 'continued \\", "still'", "helper()"]).0,
+            "x = helper()"
+        );
+    }
+}
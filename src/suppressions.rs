@@ -0,0 +1,93 @@
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, Result};
+
+use crate::{
+    baseline::{normalize, Baseline},
+    cli::Args,
+    declarations, export_mismatches,
+    ignore::IgnoreList,
+    load_config, scan_options, walk,
+};
+
+/// Lists the entries of the baseline and ignore file, marking those that no longer match any declaration
+/// in the tree, or listing only those if `unused` is set. Fails if any are stale, so that the suppression
+/// files don't fill up with entries for deleted code.
+pub fn run(
+    args: &Args,
+    path: &Path,
+    baseline: Option<&PathBuf>,
+    ignore_file: Option<&PathBuf>,
+    unused: bool,
+) -> Result<()> {
+    if baseline.is_none() && ignore_file.is_none() {
+        return Err(anyhow!("give a --baseline or --ignore-file to audit"));
+    }
+
+    let root = path.canonicalize()?;
+    let config = load_config(args.config.as_deref(), &root)?;
+    let options = scan_options(&args.options(), &config)?;
+    let (files, _) = walk::files(&root, &options);
+    let functions = declarations::collect(&files, &options)?.functions;
+    // names in `__all__` that were never defined can be suppressed too.
+    let mismatches = if args.exports {
+        export_mismatches(&files, &root)?
+    } else {
+        Vec::new()
+    };
+    let declared: HashSet<(String, usize, String)> = functions
+        .iter()
+        .chain(&mismatches)
+        .map(|function| {
+            let (file, line) = &function.location;
+            (
+                normalize(file.strip_prefix(&root).unwrap_or(file)),
+                *line,
+                function.name.clone(),
+            )
+        })
+        .collect();
+    let named: HashSet<(&str, &str)> = declared
+        .iter()
+        .map(|(file, _, name)| (file.as_str(), name.as_str()))
+        .collect();
+
+    let mut entries = Vec::new();
+    if let Some(baseline_path) = baseline {
+        let baseline = Baseline::load(baseline_path)?;
+        for (file, name) in baseline.entries() {
+            let stale = !named.contains(&(file, name));
+            entries.push((baseline_path, format!("{}::{}", file, name), stale));
+        }
+    }
+    if let Some(ignore_file) = ignore_file {
+        let ignored = IgnoreList::load(ignore_file, path)?;
+        for (file, line, name) in ignored.entries() {
+            let stale = !declared.contains(&(file.to_owned(), line, name.to_owned()));
+            entries.push((ignore_file, format!("{}:{}:{}", file, line, name), stale));
+        }
+    }
+
+    let stale = entries.iter().filter(|(_, _, stale)| *stale).count();
+    for (file, entry, is_stale) in &entries {
+        if *is_stale {
+            println!("{}: {} (stale)", file.display(), entry);
+        } else if !unused {
+            println!("{}: {}", file.display(), entry);
+        }
+    }
+    eprintln!(
+        "{} of {} suppressions no longer match any definition",
+        stale,
+        entries.len()
+    );
+
+    if stale > 0 {
+        Err(anyhow!("stale suppressions were found"))
+    } else {
+        Ok(())
+    }
+}
@@ -0,0 +1,183 @@
+use std::collections::HashMap;
+
+use rustpython_parser::{
+    ast::{self, Ranged},
+    source_code::LinearLocator,
+    Parse,
+};
+
+/// The functions a python module declares, as found by parsing it, so that a `def` in a docstring or
+/// string is never taken for one.
+pub struct Declarations {
+    /// The names of the functions, methods and lambdas assigned to names declared on each zero-based line.
+    functions: HashMap<usize, Vec<String>>,
+}
+
+impl Declarations {
+    /// Parses a module, or returns `None` if it isn't valid python 3, such as python 2 code or a template.
+    pub fn parse(source: &str, path: &str) -> Option<Self> {
+        let suite = ast::Suite::parse(source, path).ok()?;
+        let mut declarations = Self {
+            functions: HashMap::new(),
+        };
+        declarations.visit(&suite, &mut LinearLocator::new(source));
+        Some(declarations)
+    }
+
+    /// The functions declared on the given zero-based line.
+    pub fn functions_at(&self, line: usize) -> &[String] {
+        self.functions
+            .get(&line)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    fn declare(&mut self, name: &str, statement: &ast::Stmt, locator: &mut LinearLocator) {
+        // a decorated declaration starts at its `def`, after the decorators.
+        let line = locator
+            .locate(statement.start())
+            .row
+            .to_zero_indexed_usize();
+        self.functions
+            .entry(line)
+            .or_default()
+            .push(name.to_owned());
+    }
+
+    /// Finds the declarations in a block, and in the blocks nested in it.
+    fn visit(&mut self, statements: &[ast::Stmt], locator: &mut LinearLocator) {
+        for statement in statements {
+            match statement {
+                ast::Stmt::FunctionDef(function) => {
+                    self.declare(&function.name, statement, locator);
+                    self.visit(&function.body, locator);
+                }
+                ast::Stmt::AsyncFunctionDef(function) => {
+                    self.declare(&function.name, statement, locator);
+                    self.visit(&function.body, locator);
+                }
+                ast::Stmt::ClassDef(class) => self.visit(&class.body, locator),
+                ast::Stmt::Assign(assign) if assign.value.is_lambda_expr() => {
+                    for target in &assign.targets {
+                        if let ast::Expr::Name(name) = target {
+                            self.declare(&name.id, statement, locator);
+                        }
+                    }
+                }
+                ast::Stmt::AnnAssign(assign)
+                    if assign
+                        .value
+                        .as_ref()
+                        .is_some_and(|value| value.is_lambda_expr()) =>
+                {
+                    if let ast::Expr::Name(name) = assign.target.as_ref() {
+                        self.declare(&name.id, statement, locator);
+                    }
+                }
+                ast::Stmt::If(ast::StmtIf { body, orelse, .. })
+                | ast::Stmt::For(ast::StmtFor { body, orelse, .. })
+                | ast::Stmt::AsyncFor(ast::StmtAsyncFor { body, orelse, .. })
+                | ast::Stmt::While(ast::StmtWhile { body, orelse, .. }) => {
+                    self.visit(body, locator);
+                    self.visit(orelse, locator);
+                }
+                ast::Stmt::With(ast::StmtWith { body, .. })
+                | ast::Stmt::AsyncWith(ast::StmtAsyncWith { body, .. }) => {
+                    self.visit(body, locator)
+                }
+                ast::Stmt::Try(ast::StmtTry {
+                    body,
+                    handlers,
+                    orelse,
+                    finalbody,
+                    ..
+                })
+                | ast::Stmt::TryStar(ast::StmtTryStar {
+                    body,
+                    handlers,
+                    orelse,
+                    finalbody,
+                    ..
+                }) => {
+                    self.visit(body, locator);
+                    for ast::ExceptHandler::ExceptHandler(handler) in handlers {
+                        self.visit(&handler.body, locator);
+                    }
+                    self.visit(orelse, locator);
+                    self.visit(finalbody, locator);
+                }
+                ast::Stmt::Match(ast::StmtMatch { cases, .. }) => {
+                    for case in cases {
+                        self.visit(&case.body, locator);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The functions declared on each line that has any.
+    fn declared(source: &str) -> Vec<(usize, Vec<String>)> {
+        let declarations = Declarations::parse(source, "<test>").unwrap();
+        let mut declared: Vec<_> = declarations.functions.into_iter().collect();
+        declared.sort();
+        declared
+    }
+
+    #[test]
+    fn functions_are_found_wherever_they_are_nested() {
+        let source = "\
+async def fetch():
+    def inner():
+        pass
+
+class Job:
+    @property
+    def run(self):
+        pass
+
+if True:
+    def maybe():
+        pass
+";
+        assert_eq!(
+            declared(source),
+            [
+                (0, vec!["fetch".to_owned()]),
+                (1, vec!["inner".to_owned()]),
+                (6, vec!["run".to_owned()]),
+                (10, vec!["maybe".to_owned()]),
+            ]
+        );
+    }
+
+    #[test]
+    fn lambdas_assigned_to_names_are_functions() {
+        assert_eq!(
+            declared("square = lambda x: x * x\ncube: Callable = lambda x: x ** 3\nvalue = 1\n"),
+            [(0, vec!["square".to_owned()]), (1, vec!["cube".to_owned()])]
+        );
+    }
+
+    #[test]
+    fn definitions_in_strings_are_not_functions() {
+        let source = "def real():\n    \"\"\"\n    def fake():\n    \"\"\"\n    return 'def other(): pass'\n";
+        assert_eq!(declared(source), [(0, vec!["real".to_owned()])]);
+    }
+
+    #[test]
+    fn declarations_spread_over_lines_start_at_their_def() {
+        let source = "@register(\n    'def fake()',\n)\ndef real(\n    argument,\n):\n    pass\n";
+        assert_eq!(declared(source), [(3, vec!["real".to_owned()])]);
+    }
+
+    #[test]
+    fn invalid_modules_are_not_parsed() {
+        assert!(Declarations::parse("print 'python 2'\n", "<test>").is_none());
+    }
+}
@@ -0,0 +1,253 @@
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use anyhow::{anyhow, Context, Result};
+use clap::ArgEnum;
+use regex::Regex;
+use serde::Deserialize;
+
+use crate::{baseline::normalize, modules::MentionScope, walk::SourceFile};
+
+/// Build systems whose dependency graph can scope the counting of mentions.
+#[derive(ArgEnum, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BuildSystem {
+    /// Queries `py_*` rules with `bazel query`.
+    Bazel,
+    /// Reads python targets with `pants peek`.
+    Pants,
+}
+
+impl BuildSystem {
+    fn program(&self) -> &'static str {
+        match self {
+            BuildSystem::Bazel => "bazel",
+            BuildSystem::Pants => "pants",
+        }
+    }
+}
+
+/// A python target, with the files it owns and the labels of the targets it depends on.
+struct Target {
+    label: String,
+    sources: Vec<PathBuf>,
+    deps: Vec<String>,
+}
+
+/// Runs the build system in the given directory, returning its standard output.
+fn run(system: BuildSystem, directory: &Path, args: &[&str]) -> Result<String> {
+    let program = system.program();
+    let output = Command::new(program)
+        .args(args)
+        .current_dir(directory)
+        .output()
+        .with_context(|| format!("failed to run {}", program))?;
+
+    if !output.status.success() {
+        return Err(anyhow!(
+            "{} {} failed: {}",
+            program,
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Queries every python rule in the Bazel workspace containing the root.
+fn bazel_targets(root: &Path) -> Result<Vec<Target>> {
+    let workspace = run(BuildSystem::Bazel, root, &["info", "workspace"])?;
+    let workspace = Path::new(workspace.trim()).canonicalize()?;
+    let xml = run(
+        BuildSystem::Bazel,
+        root,
+        &["query", "--output=xml", r#"kind("py_.* rule", //...)"#],
+    )?;
+
+    let rule_pattern = Regex::new(r#"^\s*<rule\s[^>]*\bname="([^"]+)""#)?;
+    let list_pattern = Regex::new(r#"^\s*<list\s[^>]*\bname="([^"]+)""#)?;
+    let label_pattern = Regex::new(r#"^\s*<label\s[^>]*\bvalue="([^"]+)""#)?;
+
+    let mut targets = Vec::new();
+    let mut current: Option<Target> = None;
+    let mut list = String::new();
+    for line in xml.lines() {
+        if let Some(captures) = rule_pattern.captures(line) {
+            current = Some(Target {
+                label: bazel_label(&captures[1]),
+                sources: Vec::new(),
+                deps: Vec::new(),
+            });
+        } else if let Some(captures) = list_pattern.captures(line) {
+            list = captures[1].to_owned();
+        } else if line.trim_start().starts_with("</list>") {
+            list.clear();
+        } else if line.trim_start().starts_with("</rule>") {
+            targets.extend(current.take());
+        } else if let (Some(target), Some(captures)) = (&mut current, label_pattern.captures(line))
+        {
+            let label = &captures[1];
+            match list.as_str() {
+                "srcs" => target.sources.extend(bazel_source(&workspace, label)),
+                "deps" => target.deps.push(bazel_label(label)),
+                _ => {}
+            }
+        }
+    }
+    Ok(targets)
+}
+
+/// Spells out a label's target name, so that `//pkg` and `//pkg:pkg` are the same.
+fn bazel_label(label: &str) -> String {
+    if label.contains(':') {
+        label.to_owned()
+    } else {
+        let name = label.rsplit('/').next().unwrap_or_default();
+        format!("{}:{}", label, name)
+    }
+}
+
+/// The file named by a source label in the main repository, such as `//pkg:sub/module.py`.
+fn bazel_source(workspace: &Path, label: &str) -> Option<PathBuf> {
+    let (package, name) = label.strip_prefix("//")?.split_once(':')?;
+    Some(workspace.join(package).join(name))
+}
+
+/// A target as printed by `pants peek`.
+#[derive(Deserialize)]
+struct PantsTarget {
+    address: String,
+    target_type: String,
+    /// The files it owns, relative to the build root.
+    #[serde(default)]
+    sources: Vec<String>,
+    #[serde(default)]
+    dependencies: Vec<String>,
+}
+
+/// Reads every python target in the Pants build root containing the root.
+fn pants_targets(root: &Path) -> Result<Vec<Target>> {
+    let build_root = root
+        .ancestors()
+        .find(|directory| directory.join("pants.toml").is_file())
+        .ok_or_else(|| anyhow!("no pants.toml was found above {}", root.display()))?;
+    let json = run(BuildSystem::Pants, build_root, &["peek", "::"])?;
+    let targets: Vec<PantsTarget> =
+        serde_json::from_str(&json).context("failed to read the output of pants peek")?;
+
+    Ok(targets
+        .into_iter()
+        .filter(|target| target.target_type.starts_with("python_"))
+        .map(|target| Target {
+            label: target.address,
+            sources: target
+                .sources
+                .iter()
+                .map(|source| build_root.join(source))
+                .collect(),
+            deps: target.dependencies,
+        })
+        .collect())
+}
+
+/// Which files' mentions count for the declarations of each file, when the build graph scopes counting:
+/// those owned by the same target, by targets depending on it directly or not, or by no python target.
+pub struct Targets {
+    /// The targets owning each file, by index into the files.
+    owners: Vec<Vec<usize>>,
+    /// The targets each target can use: itself and its dependencies, transitively.
+    visible: Vec<HashSet<usize>>,
+    /// The index of each file, by path.
+    files: HashMap<PathBuf, usize>,
+    /// Files under the configured extra roots.
+    everywhere: HashSet<usize>,
+}
+
+impl Targets {
+    /// Asks the build system for its python targets and which of the files each owns.
+    pub fn query(
+        system: BuildSystem,
+        files: &[SourceFile],
+        root: &Path,
+        extra_roots: &[Regex],
+    ) -> Result<Self> {
+        let targets = match system {
+            BuildSystem::Bazel => bazel_targets(root)?,
+            BuildSystem::Pants => pants_targets(root)?,
+        };
+
+        let by_label: HashMap<&str, usize> = targets
+            .iter()
+            .enumerate()
+            .map(|(index, target)| (target.label.as_str(), index))
+            .collect();
+        let mut owned_by: HashMap<&Path, Vec<usize>> = HashMap::new();
+        for (index, target) in targets.iter().enumerate() {
+            for source in &target.sources {
+                owned_by.entry(source).or_default().push(index);
+            }
+        }
+
+        let visible = (0..targets.len())
+            .map(|start| {
+                let mut seen = HashSet::from([start]);
+                let mut pending = vec![start];
+                while let Some(target) = pending.pop() {
+                    for dependency in &targets[target].deps {
+                        match by_label.get(dependency.as_str()) {
+                            Some(&dependency) if seen.insert(dependency) => {
+                                pending.push(dependency)
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+                seen
+            })
+            .collect();
+
+        let mut owners = Vec::with_capacity(files.len());
+        let mut everywhere = HashSet::new();
+        for (index, entry) in files.iter().enumerate() {
+            owners.push(owned_by.get(entry.path()).cloned().unwrap_or_default());
+            let relative = normalize(entry.path().strip_prefix(root).unwrap_or(entry.path()));
+            if extra_roots
+                .iter()
+                .any(|pattern| pattern.is_match(&relative))
+            {
+                everywhere.insert(index);
+            }
+        }
+
+        Ok(Self {
+            owners,
+            visible,
+            files: files
+                .iter()
+                .enumerate()
+                .map(|(index, entry)| (entry.path().to_owned(), index))
+                .collect(),
+            everywhere,
+        })
+    }
+}
+
+impl MentionScope for Targets {
+    fn sees(&self, file: usize, declared_in: &Path) -> bool {
+        let declared = match self.files.get(declared_in) {
+            Some(&declared) => &self.owners[declared],
+            None => return true,
+        };
+        declared.is_empty()
+            || self.owners[file].is_empty()
+            || self.everywhere.contains(&file)
+            || self.owners[file].iter().any(|&target| {
+                declared
+                    .iter()
+                    .any(|owner| self.visible[target].contains(owner))
+            })
+    }
+}
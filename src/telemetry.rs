@@ -0,0 +1,146 @@
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{Context, Result};
+use serde_json::{json, Value};
+
+use crate::metadata::Metadata;
+
+/// A finished span, ready to be exported.
+#[derive(Debug)]
+struct Span {
+    id: String,
+    name: &'static str,
+    start: SystemTime,
+    end: SystemTime,
+    attributes: Vec<(&'static str, usize)>,
+}
+
+/// A span that has been started but not yet ended.
+#[derive(Debug)]
+pub struct OpenSpan {
+    name: &'static str,
+    start: SystemTime,
+}
+
+/// Records the phases of a scan as spans under a single root span.
+#[derive(Debug)]
+pub struct Tracer {
+    trace_id: String,
+    root: OpenSpan,
+    spans: Vec<Span>,
+}
+
+impl Tracer {
+    pub fn new() -> Self {
+        Self {
+            trace_id: random_hex(16),
+            root: Self::start("snapbug"),
+            spans: Vec::new(),
+        }
+    }
+
+    /// Starts timing a phase.
+    pub fn start(name: &'static str) -> OpenSpan {
+        OpenSpan {
+            name,
+            start: SystemTime::now(),
+        }
+    }
+
+    /// Finishes timing a phase, attaching the given counts as attributes.
+    pub fn end(&mut self, span: OpenSpan, attributes: &[(&'static str, usize)]) {
+        self.spans.push(Span {
+            id: random_hex(8),
+            name: span.name,
+            start: span.start,
+            end: SystemTime::now(),
+            attributes: attributes.to_vec(),
+        });
+    }
+
+    /// Ends the root span and sends every span to an OTLP/HTTP collector as JSON.
+    pub fn export(self, endpoint: &str, metadata: &Metadata) -> Result<()> {
+        let root = Span {
+            id: random_hex(8),
+            name: self.root.name,
+            start: self.root.start,
+            end: SystemTime::now(),
+            attributes: vec![("files", metadata.files)],
+        };
+
+        let mut spans = vec![span_json(&self.trace_id, &root, None)];
+        spans.extend(
+            self.spans
+                .iter()
+                .map(|span| span_json(&self.trace_id, span, Some(&root.id))),
+        );
+
+        let body = json!({
+            "resourceSpans": [{
+                "resource": {
+                    "attributes": [
+                        {"key": "service.name", "value": {"stringValue": "snapbug"}},
+                        {"key": "service.version", "value": {"stringValue": metadata.version}},
+                        {"key": "snapbug.root", "value": {"stringValue": metadata.root}},
+                        {"key": "snapbug.engine", "value": {"stringValue": metadata.engine}},
+                        {"key": "snapbug.config_hash", "value": {"stringValue": metadata.config_hash.as_deref().unwrap_or_default()}},
+                    ],
+                },
+                "scopeSpans": [{
+                    "scope": {"name": "snapbug", "version": env!("CARGO_PKG_VERSION")},
+                    "spans": spans,
+                }],
+            }],
+        });
+
+        let url = format!("{}/v1/traces", endpoint.trim_end_matches('/'));
+        ureq::post(&url)
+            .content_type("application/json")
+            .send(body.to_string())
+            .with_context(|| format!("failed to export traces to {}", url))?;
+
+        Ok(())
+    }
+}
+
+/// Encodes a span in the OTLP JSON format.
+fn span_json(trace_id: &str, span: &Span, parent: Option<&str>) -> Value {
+    let attributes: Vec<Value> = span
+        .attributes
+        .iter()
+        .map(|(key, value)| json!({"key": key, "value": {"intValue": value.to_string()}}))
+        .collect();
+
+    json!({
+        "traceId": trace_id,
+        "spanId": span.id,
+        "parentSpanId": parent.unwrap_or_default(),
+        "name": span.name,
+        "kind": 1,
+        "startTimeUnixNano": unix_nanos(span.start).to_string(),
+        "endTimeUnixNano": unix_nanos(span.end).to_string(),
+        "attributes": attributes,
+    })
+}
+
+fn unix_nanos(time: SystemTime) -> u128 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_nanos())
+        .unwrap_or_default()
+}
+
+/// Produces a random identifier of the given number of bytes, hex encoded.
+fn random_hex(bytes: usize) -> String {
+    let mut id = String::with_capacity(bytes * 2);
+    while id.len() < bytes * 2 {
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_u128(unix_nanos(SystemTime::now()));
+        id.push_str(&format!("{:016x}", hasher.finish()));
+    }
+    id.truncate(bytes * 2);
+    id
+}
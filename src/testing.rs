@@ -0,0 +1,40 @@
+use std::path::{Component, Path};
+
+/// Matches a line importing pytest or unittest, which marks a file as a test.
+pub const IMPORT_PATTERN: &str = r"^\s*(?:import|from)\s+(?:pytest|unittest)\b";
+
+/// Modules whose import marks a file as a test.
+pub const FRAMEWORKS: [&str; 2] = ["pytest", "unittest"];
+
+/// Whether a file is a test by where it is or what it is called, given its path relative to the root: under
+/// a `tests` or `test` directory, or named `test_*.py`, `*_test.py`, `*_tests.py`, `tests.py` or
+/// `conftest.py`. Files importing a test framework are tests too, which the caller checks.
+pub fn is_test_path(relative: &Path) -> bool {
+    let in_test_directory = relative
+        .parent()
+        .into_iter()
+        .flat_map(Path::components)
+        .any(|component| {
+            matches!(component, Component::Normal(name) if name == "tests" || name == "test")
+        });
+    let stem = relative
+        .file_stem()
+        .map(|stem| stem.to_string_lossy())
+        .unwrap_or_default();
+    in_test_directory
+        || stem.starts_with("test_")
+        || stem.ends_with("_test")
+        || stem.ends_with("_tests")
+        || stem == "tests"
+        || stem == "conftest"
+}
+
+/// Whether a declaration in a test file is collected by the test runner, which calls it for us: functions
+/// and methods named `test*`, and classes named `Test*`.
+pub fn is_collected(name: &str, is_class: bool) -> bool {
+    if is_class {
+        name.starts_with("Test")
+    } else {
+        name.starts_with("test")
+    }
+}
@@ -0,0 +1,129 @@
+use std::{
+    collections::{HashMap, HashSet},
+    io::BufRead,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Result;
+
+use crate::{git::Git, hg::Mercurial};
+
+/// Starts each revision's entry in a log of patches, followed by the revision's unix timestamp.
+pub const LOG_MARKER: &str = "snapbug-revision ";
+
+/// Who last changed a line, and when.
+#[derive(Clone, Debug)]
+pub struct Blame {
+    pub author: String,
+    /// A unix timestamp.
+    pub timestamp: i64,
+}
+
+/// The version control operations that archaeology, escalation, `--since` and `--blame` need, so that they
+/// work in any repository.
+pub trait Vcs {
+    /// The name of the tool, for messages.
+    fn name(&self) -> &'static str;
+
+    /// The root of the working copy, or an error if the tool is missing or this is not a repository.
+    fn root(&self) -> Result<String>;
+
+    /// The revision checked out in the working copy.
+    fn head(&self) -> &'static str;
+
+    /// Counts mentions of the name in python files as of the given revision, or the lines mentioning it
+    /// if the tool can't count each one.
+    fn mentions_at(&self, revision: &str, name: &str) -> usize;
+
+    /// Describes a revision on a single line.
+    fn describe(&self, revision: &str) -> Result<String>;
+
+    /// The revisions that added or removed lines of python files mentioning the name, newest first.
+    fn changes(&self, name: &str) -> Result<Vec<String>>;
+
+    /// The first parent of a revision, unless it is a root.
+    fn parent(&self, revision: &str) -> Option<String>;
+
+    /// When lines of python files mentioning each name were last added or removed, as unix timestamps, from
+    /// a single pass over history. Names that aren't in history are left out.
+    fn last_changed(&self, names: &[&str]) -> Result<HashMap<String, i64>>;
+
+    /// The files added or modified since the given revision, including uncommitted changes and new files,
+    /// relative to the root of the working copy.
+    fn changed_files(&self, since: &str) -> Result<Vec<PathBuf>>;
+
+    /// Who last changed the given one-based line of a file, or `None` if the change isn't committed yet.
+    fn blame(&self, path: &Path, line: usize) -> Result<Option<Blame>>;
+}
+
+/// Picks the version control system of the repository containing the given directory, by the nearest
+/// `.hg` or `.git` above it. Git is assumed when there is neither.
+pub fn detect(directory: &Path) -> Box<dyn Vcs> {
+    let directory = directory.to_owned();
+    for ancestor in directory.ancestors() {
+        if ancestor.join(".hg").is_dir() {
+            return Box::new(Mercurial::new(directory));
+        }
+        if ancestor.join(".git").exists() {
+            break;
+        }
+    }
+    Box::new(Git::new(directory))
+}
+
+/// Reads a log of patches, newest first, in which each revision starts with `LOG_MARKER` and its timestamp,
+/// finding the newest revision adding or removing a line that mentions each name as a whole identifier.
+/// Stops reading once every name is found.
+pub fn last_changed(log: impl BufRead, names: &[&str]) -> Result<HashMap<String, i64>> {
+    let mut missing: HashSet<&str> = names.iter().copied().collect();
+    let mut changed = HashMap::new();
+    let mut timestamp = None;
+    for line in log.split(b'\n') {
+        if missing.is_empty() {
+            break;
+        }
+        let line = String::from_utf8_lossy(&line?).into_owned();
+        if let Some(revision) = line.strip_prefix(LOG_MARKER) {
+            // anything after the timestamp, such as a timezone's offset, is left out.
+            timestamp = Some(
+                revision
+                    .split_whitespace()
+                    .next()
+                    .unwrap_or_default()
+                    .parse()?,
+            );
+            continue;
+        }
+        let timestamp = match timestamp {
+            Some(timestamp) => timestamp,
+            None => continue,
+        };
+        let changed_line = (line.starts_with('+') && !line.starts_with("+++"))
+            || (line.starts_with('-') && !line.starts_with("---"));
+        if !changed_line {
+            continue;
+        }
+        for identifier in line[1..].split(|c: char| !(c.is_alphanumeric() || c == '_')) {
+            if missing.remove(identifier) {
+                changed.insert(identifier.to_owned(), timestamp);
+            }
+        }
+    }
+    Ok(changed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_are_dated_by_the_newest_revision_changing_them() {
+        let log = "snapbug-revision 30\n\ndiff --git a/lib.py b/lib.py\n--- a/lib.py\n+++ b/lib.py\n@@ -1 +1 @@\n+get_user()\n snapbug()\nsnapbug-revision 20 -3600\n-get()\n+def get_user():\nsnapbug-revision 10\n+def get():\n";
+        let changed =
+            last_changed(log.as_bytes(), &["get", "get_user", "snapbug", "other"]).unwrap();
+        assert_eq!(
+            changed,
+            HashMap::from([("get".to_owned(), 20), ("get_user".to_owned(), 30)])
+        );
+    }
+}
@@ -0,0 +1,194 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use walkdir::{DirEntry, WalkDir};
+
+use crate::{exclude::Exclusions, extension_of, scanned_extensions, ScanOptions};
+
+/// Hidden entries that hold CI configuration.
+const CI_ENTRIES: [&str; 3] = [".github", ".gitlab-ci.yml", ".circleci"];
+
+/// Files larger than this many bytes are skipped, as they are almost always generated or minified.
+pub(crate) const MAX_FILE_SIZE: u64 = 16 * 1024 * 1024;
+
+/// Extensions of scripts that may invoke python functions by name.
+const SCRIPT_EXTENSIONS: [&str; 6] = ["sh", "bash", "mk", "yml", "yaml", "dockerfile"];
+
+/// We only consider non-hidden entries, except for CI configuration when scanning scripts.
+pub(crate) fn is_useful(entry: &DirEntry, scan_scripts: bool) -> bool {
+    entry
+        .file_name()
+        .to_str()
+        .map(|s| !s.starts_with('.') || (scan_scripts && CI_ENTRIES.contains(&s)))
+        .unwrap_or(false)
+}
+
+/// Assumes anything with one of the given extensions is a source file worth scanning.
+fn is_source_file(entry: &DirEntry, extensions: &[String]) -> bool {
+    entry.file_type().is_file()
+        && extensions
+            .iter()
+            .any(|ext| ext == extension_of(entry.path()))
+}
+
+/// Recognises automation scripts that are scanned for references but never for definitions.
+fn is_script_file(entry: &DirEntry) -> bool {
+    let name = entry.file_name().to_string_lossy();
+    entry.file_type().is_file()
+        && (SCRIPT_EXTENSIONS.contains(&extension_of(entry.path()))
+            || name.eq_ignore_ascii_case("makefile")
+            || name == "GNUmakefile"
+            || name.starts_with("Dockerfile"))
+}
+
+/// A file found by walking a tree, to be scanned.
+#[derive(Clone, Debug)]
+pub struct SourceFile {
+    path: PathBuf,
+    /// How many components of the path are below the root it was walked from.
+    depth: usize,
+    /// The size in bytes when it was walked.
+    size: u64,
+    /// Whether it is an automation script, which is only scanned for references.
+    script: bool,
+}
+
+impl SourceFile {
+    fn new(entry: DirEntry, script: bool) -> Self {
+        Self {
+            depth: entry.depth(),
+            size: entry.metadata().map_or(0, |metadata| metadata.len()),
+            script,
+            path: entry.into_path(),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub(crate) fn size(&self) -> u64 {
+        self.size
+    }
+
+    pub(crate) fn is_script(&self) -> bool {
+        self.script
+    }
+
+    /// The path relative to the root it was walked from, which is its last `depth` components.
+    pub(crate) fn relative_path(&self) -> PathBuf {
+        let components: Vec<_> = self.path.components().collect();
+        components[components.len().saturating_sub(self.depth)..]
+            .iter()
+            .collect()
+    }
+
+    /// The directories the file is in, from its own up to the root it was walked from.
+    pub(crate) fn directories(&self) -> impl Iterator<Item = &Path> {
+        self.path.ancestors().skip(1).take(self.depth)
+    }
+}
+
+/// An entry left out of the scan, and why.
+#[derive(Debug)]
+pub struct Skipped {
+    pub path: PathBuf,
+    pub reason: &'static str,
+}
+
+impl Skipped {
+    pub(crate) fn new(path: &Path, reason: &'static str) -> Self {
+        Self {
+            path: path.to_owned(),
+            reason,
+        }
+    }
+}
+
+/// Counts skipped entries by reason.
+pub fn count_skipped(skipped: &[Skipped]) -> BTreeMap<&'static str, usize> {
+    let mut counts = BTreeMap::new();
+    for entry in skipped {
+        *counts.entry(entry.reason).or_default() += 1;
+    }
+    counts
+}
+
+/// Returns the source files under the root that the options scan, plus automation scripts if they scan
+/// those, and the entries that were left out, with why.
+pub fn files(root: &Path, options: &ScanOptions) -> (Vec<SourceFile>, Vec<Skipped>) {
+    walk(
+        root,
+        &scanned_extensions(options),
+        options.scan_scripts,
+        &options.exclusions,
+    )
+}
+
+/// Returns all source files with the given extensions in the given path, plus automation scripts
+/// if requested, leaving out the excluded ones. Also returns the entries that were skipped, with why.
+pub(crate) fn walk(
+    path: &Path,
+    extensions: &[String],
+    scan_scripts: bool,
+    exclusions: &Exclusions,
+) -> (Vec<SourceFile>, Vec<Skipped>) {
+    let mut files = Vec::new();
+    let mut skipped = Vec::new();
+    let mut checker = exclusions.checker(path);
+
+    let mut walker = WalkDir::new(path).sort_by_file_name().into_iter();
+    while let Some(entry) = walker.next() {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(err) => {
+                skipped.push(Skipped::new(err.path().unwrap_or(path), "unreadable"));
+                continue;
+            }
+        };
+
+        if !is_useful(&entry, scan_scripts) {
+            if entry.file_type().is_dir() {
+                walker.skip_current_dir();
+            }
+            skipped.push(Skipped::new(entry.path(), "hidden"));
+        } else if let Some(reason) = checker.check(&entry) {
+            if entry.file_type().is_dir() {
+                walker.skip_current_dir();
+            }
+            skipped.push(Skipped::new(entry.path(), reason));
+        } else if entry.path_is_symlink() {
+            skipped.push(Skipped::new(entry.path(), "symlink"));
+        } else if is_source_file(&entry, extensions) || (scan_scripts && is_script_file(&entry)) {
+            let script = is_script_file(&entry);
+            let file = SourceFile::new(entry, script);
+            if file.size > MAX_FILE_SIZE {
+                skipped.push(Skipped::new(file.path(), "too large"));
+            } else {
+                files.push(file);
+            }
+        } else if entry.file_type().is_file() {
+            skipped.push(Skipped::new(entry.path(), "unsupported"));
+        }
+    }
+
+    (files, skipped)
+}
+
+/// The source files under the search roots that aren't under the root already, whose mentions are counted
+/// but whose declarations aren't reported. What they leave out isn't reported either, as there is usually
+/// plenty.
+pub(crate) fn search_root_files(
+    root: &Path,
+    extensions: &[String],
+    options: &ScanOptions,
+) -> Vec<SourceFile> {
+    options
+        .search_roots
+        .iter()
+        .filter(|search_root| !search_root.starts_with(root))
+        .flat_map(|search_root| walk(search_root, extensions, false, &Exclusions::default()).0)
+        .collect()
+}
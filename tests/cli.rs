@@ -0,0 +1,884 @@
+use std::{
+    fs,
+    io::{BufRead, BufReader, Write},
+    net::TcpListener,
+    path::Path,
+    process::Command,
+    thread,
+};
+
+/// Runs git in the given repository, as a committer named snapbug.
+fn git(repository: &Path, args: &[&str]) {
+    let status = Command::new("git")
+        .args([
+            "-c",
+            "user.name=snapbug",
+            "-c",
+            "user.email=snapbug@localhost",
+        ])
+        .args(args)
+        .current_dir(repository)
+        .status()
+        .unwrap();
+    assert!(status.success());
+}
+
+#[test]
+fn rename_check_searches_the_current_directory_by_default() {
+    let root = tempfile::Builder::new()
+        .prefix("snapbug")
+        .tempdir()
+        .unwrap();
+    fs::write(
+        root.path().join("a.py"),
+        "def get():\n    pass\n\n\nget()\n",
+    )
+    .unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_snapbug"))
+        .args(["rename-check", "get"])
+        .current_dir(root.path())
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(output.status.success());
+    assert!(stdout.contains("definitions (1):"), "{}", stdout);
+    assert!(stdout.contains("calls (1):"), "{}", stdout);
+}
+
+#[test]
+fn reachability_ignores_names_inside_longer_identifiers() {
+    let root = tempfile::Builder::new()
+        .prefix("snapbug")
+        .tempdir()
+        .unwrap();
+    fs::write(
+        root.path().join("__main__.py"),
+        "from lib import run\n\nrun()\n",
+    )
+    .unwrap();
+    let lib = "def run():\n    return get_user()\n\n\ndef get_user():\n    return 1\n\n\ndef get():\n    return 2\n";
+    fs::write(root.path().join("lib.py"), lib).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_snapbug"))
+        .args(["--mode", "reachability", "--output-format", "json"])
+        .arg(root.path())
+        .output()
+        .unwrap();
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let names: Vec<&str> = report["findings"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|finding| finding["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(names, ["get"]);
+}
+
+#[test]
+fn archaeology_ignores_names_inside_longer_identifiers() {
+    let root = tempfile::Builder::new()
+        .prefix("snapbug")
+        .tempdir()
+        .unwrap();
+    let source = "def get():\n    pass\n\n\ndef get_user():\n    pass\n\n\nget_user()\n";
+    fs::write(root.path().join("lib.py"), source).unwrap();
+    let git = |args: &[&str]| git(root.path(), args);
+    git(&["init", "-q"]);
+    git(&["add", "lib.py"]);
+    git(&["commit", "-q", "-m", "add lib"]);
+
+    let output = Command::new(env!("CARGO_BIN_EXE_snapbug"))
+        .args(["archaeology", "get"])
+        .arg(root.path())
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(
+        stdout.contains("has never been referenced besides its declaration"),
+        "{}",
+        stdout
+    );
+}
+
+#[test]
+fn suppressions_takes_its_own_baseline() {
+    let root = tempfile::Builder::new()
+        .prefix("snapbug")
+        .tempdir()
+        .unwrap();
+    fs::write(root.path().join("a.py"), "def dead():\n    pass\n").unwrap();
+    let baseline = "[[suppress]]\npath = \"a.py\"\nname = \"dead\"\n\n[[suppress]]\npath = \"gone.py\"\nname = \"x\"\n";
+    fs::write(root.path().join("baseline.toml"), baseline).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_snapbug"))
+        .args([
+            "suppressions",
+            "--unused",
+            ".",
+            "--baseline",
+            "baseline.toml",
+        ])
+        .current_dir(root.path())
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(!output.status.success());
+    assert_eq!(stdout, "baseline.toml: gone.py::x (stale)\n");
+}
+
+#[test]
+fn since_reports_changed_files_and_blame_names_their_authors() {
+    let root = tempfile::Builder::new()
+        .prefix("snapbug")
+        .tempdir()
+        .unwrap();
+    let git = |args: &[&str]| git(root.path(), args);
+    git(&["init", "-q"]);
+    fs::write(root.path().join("old.py"), "def dead_old():\n    pass\n").unwrap();
+    git(&["add", "old.py"]);
+    git(&["commit", "-q", "-m", "add old"]);
+    fs::write(
+        root.path().join("changed.py"),
+        "def dead_changed():\n    pass\n",
+    )
+    .unwrap();
+    git(&["add", "changed.py"]);
+    git(&["commit", "-q", "-m", "add changed"]);
+    fs::write(root.path().join("new.py"), "def dead_new():\n    pass\n").unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_snapbug"))
+        .args(["--since", "HEAD~1", "--blame", "--output-format", "json"])
+        .arg(root.path())
+        .output()
+        .unwrap();
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let found: Vec<(&str, Option<&str>)> = report["findings"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|finding| {
+            (
+                finding["name"].as_str().unwrap(),
+                finding["blame"]["author"].as_str(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        found,
+        [("dead_changed", Some("snapbug")), ("dead_new", None)]
+    );
+}
+
+#[test]
+fn functions_only_mentioned_in_comments_are_suggested_for_deletion() {
+    let root = tempfile::Builder::new()
+        .prefix("snapbug")
+        .tempdir()
+        .unwrap();
+    fs::write(
+        root.path().join("a.py"),
+        "def _old():\n    pass\n\n\n# _old is kept for reference\n",
+    )
+    .unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_snapbug"))
+        .args(["--strip-comments", "--output-format", "json"])
+        .arg(root.path())
+        .output()
+        .unwrap();
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let finding = &report["findings"][0];
+    assert_eq!(finding["rule"], "comment-only-function");
+    assert_eq!(finding["suggestion"], "delete");
+}
+
+#[test]
+fn complexity_is_only_estimated_for_functions() {
+    let root = tempfile::Builder::new()
+        .prefix("snapbug")
+        .tempdir()
+        .unwrap();
+    let source =
+        "class _Box:\n    pass\n\n\ndef _check(x):\n    if x:\n        return 1\n    return 2\n";
+    fs::write(root.path().join("a.py"), source).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_snapbug"))
+        .args([
+            "--complexity",
+            "--kinds",
+            "functions,classes",
+            "--output-format",
+            "json",
+        ])
+        .arg(root.path())
+        .output()
+        .unwrap();
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let complexities: Vec<(&str, &serde_json::Value)> = report["findings"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|finding| (finding["name"].as_str().unwrap(), &finding["complexity"]))
+        .collect();
+    assert_eq!(
+        complexities,
+        [("_Box", &serde_json::Value::Null), ("_check", &2.into())]
+    );
+}
+
+#[test]
+fn escalation_fails_the_build_only_for_long_unreferenced_findings() {
+    let root = tempfile::Builder::new()
+        .prefix("snapbug")
+        .tempdir()
+        .unwrap();
+    fs::write(
+        root.path().join("snapbug.toml"),
+        "escalate_after = \"30d\"\n",
+    )
+    .unwrap();
+    let run = || {
+        Command::new(env!("CARGO_BIN_EXE_snapbug"))
+            .arg(root.path())
+            .output()
+            .unwrap()
+    };
+
+    // outside a repository, the findings' ages can't be known, so the scan fails rather than passing.
+    fs::write(root.path().join("old.py"), "def _old():\n    pass\n").unwrap();
+    let output = run();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("failed to read history"));
+
+    let git = |args: &[&str]| git(root.path(), args);
+    git(&["init", "-q"]);
+    git(&["add", "old.py"]);
+    let status = Command::new("git")
+        .args([
+            "-c",
+            "user.name=snapbug",
+            "-c",
+            "user.email=snapbug@localhost",
+        ])
+        .args(["commit", "-q", "-m", "add old"])
+        .env("GIT_COMMITTER_DATE", "2000-01-01T00:00:00Z")
+        .current_dir(root.path())
+        .status()
+        .unwrap();
+    assert!(status.success());
+    assert!(!run().status.success());
+
+    fs::remove_file(root.path().join("old.py")).unwrap();
+    fs::write(root.path().join("new.py"), "def _new():\n    pass\n").unwrap();
+    git(&["add", "-A"]);
+    git(&["commit", "-q", "-m", "replace old"]);
+    let output = run();
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("\"_new\" may be unused"));
+}
+
+#[test]
+fn reports_are_identical_across_runs_at_a_source_date() {
+    let root = tempfile::Builder::new()
+        .prefix("snapbug")
+        .tempdir()
+        .unwrap();
+    for name in ["a", "b", "c", "d"] {
+        let source = format!(
+            "def _{}():\n    pass\n\n\nclass _{}Box:\n    pass\n",
+            name, name
+        );
+        fs::write(root.path().join(format!("{}.py", name)), source).unwrap();
+    }
+    let run = || {
+        Command::new(env!("CARGO_BIN_EXE_snapbug"))
+            .args(["--kinds", "functions,classes", "--output-format", "json"])
+            .arg(root.path())
+            .env("SOURCE_DATE_EPOCH", "1000000000")
+            .output()
+            .unwrap()
+            .stdout
+    };
+
+    let first = run();
+    assert_eq!(first, run());
+    let report: serde_json::Value = serde_json::from_slice(&first).unwrap();
+    assert_eq!(report["metadata"]["timestamp"], "2001-09-09T01:46:40Z");
+}
+
+#[test]
+fn metrics_count_findings_by_rule_and_package() {
+    let root = tempfile::Builder::new()
+        .prefix("snapbug")
+        .tempdir()
+        .unwrap();
+    fs::create_dir_all(root.path().join("src").join("shop")).unwrap();
+    fs::write(
+        root.path().join("src").join("shop").join("cart.py"),
+        "def total():\n    pass\n\n\ndef discount():\n    pass\n",
+    )
+    .unwrap();
+    fs::write(root.path().join("tool.py"), "def run():\n    pass\n").unwrap();
+    let metrics = root.path().join("snapbug.prom");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_snapbug"))
+        .arg("--metrics-textfile")
+        .arg(&metrics)
+        .arg(root.path())
+        .env("SOURCE_DATE_EPOCH", "1000000000")
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let text = fs::read_to_string(metrics).unwrap();
+    for line in [
+        "# TYPE snapbug_findings gauge",
+        "snapbug_timestamp_seconds 1000000000",
+        "snapbug_files 2",
+        "snapbug_findings 3",
+        "snapbug_findings_by_rule{rule=\"unused-function\"} 3",
+        "snapbug_findings_by_package{package=\".\"} 1",
+        "snapbug_findings_by_package{package=\"shop\"} 2",
+    ] {
+        assert!(text.lines().any(|other| other == line), "{}", text);
+    }
+}
+
+#[test]
+fn quiet_paths_count_mentions_but_report_nothing() {
+    let root = tempfile::Builder::new()
+        .prefix("snapbug")
+        .tempdir()
+        .unwrap();
+    fs::write(root.path().join("app.py"), "def helper():\n    pass\n").unwrap();
+    fs::create_dir(root.path().join("legacy")).unwrap();
+    fs::write(
+        root.path().join("legacy").join("old.py"),
+        "from app import helper\n\n\ndef migrate():\n    helper()\n",
+    )
+    .unwrap();
+    let names = |flag: &str| {
+        let output = Command::new(env!("CARGO_BIN_EXE_snapbug"))
+            .args([flag, "legacy/", "--output-format", "json"])
+            .arg(root.path())
+            .output()
+            .unwrap();
+        let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        report["findings"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|finding| finding["name"].as_str().unwrap().to_owned())
+            .collect::<Vec<_>>()
+    };
+
+    assert!(names("--quiet-path").is_empty());
+    assert_eq!(names("--exclude"), ["helper"]);
+}
+
+#[test]
+fn sarif_reports_locate_each_finding_by_relative_path() {
+    let root = tempfile::Builder::new()
+        .prefix("snapbug")
+        .tempdir()
+        .unwrap();
+    fs::create_dir(root.path().join("pkg")).unwrap();
+    fs::write(
+        root.path().join("pkg").join("a.py"),
+        "def helper():\n    x = 1\n    return x\n",
+    )
+    .unwrap();
+    let reports = tempfile::Builder::new()
+        .prefix("snapbug")
+        .tempdir()
+        .unwrap();
+    let (json, sarif) = (
+        reports.path().join("report.json"),
+        reports.path().join("report.sarif"),
+    );
+
+    let output = Command::new(env!("CARGO_BIN_EXE_snapbug"))
+        .args(["--output-format", "json", "--output-format", "sarif"])
+        .arg("--output")
+        .arg(&json)
+        .arg("--output")
+        .arg(&sarif)
+        .arg(root.path())
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let json: serde_json::Value = serde_json::from_slice(&fs::read(json).unwrap()).unwrap();
+    assert_eq!(json["findings"][0]["name"], "helper");
+    let log: serde_json::Value = serde_json::from_slice(&fs::read(sarif).unwrap()).unwrap();
+    assert_eq!(log["version"], "2.1.0");
+    let run = &log["runs"][0];
+    assert!(run["tool"]["driver"]["rules"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .any(|rule| rule["id"] == "unused-function"));
+    let results = run["results"].as_array().unwrap();
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0]["ruleId"], "unused-function");
+    assert_eq!(results[0]["level"], "error");
+    let location = &results[0]["locations"][0]["physicalLocation"];
+    assert_eq!(location["artifactLocation"]["uri"], "pkg/a.py");
+    assert_eq!(location["region"]["startLine"], 1);
+    assert_eq!(location["region"]["endLine"], 3);
+    assert_eq!(
+        results[0]["partialFingerprints"]["snapbug/v1"],
+        "pkg/a.py::helper"
+    );
+}
+
+#[test]
+fn findings_are_grouped_by_top_level_package() {
+    let root = tempfile::Builder::new()
+        .prefix("snapbug")
+        .tempdir()
+        .unwrap();
+    for (path, source) in [
+        ("src/shop/cart.py", "def total():\n    pass\n"),
+        (
+            "billing/pay.py",
+            "def charge():\n    pass\n\n\ndef refund():\n    pass\n",
+        ),
+        ("tool.py", "def build():\n    pass\n"),
+    ] {
+        let path = root.path().join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, source).unwrap();
+    }
+
+    let output = Command::new(env!("CARGO_BIN_EXE_snapbug"))
+        .args(["--group-by", "package", "."])
+        .current_dir(root.path())
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    let groups: Vec<&str> = stderr
+        .lines()
+        .filter(|line| line.ends_with(" findings)"))
+        .collect();
+    assert_eq!(
+        groups,
+        [
+            ". (1 findings)",
+            "billing (2 findings)",
+            "shop (1 findings)"
+        ],
+        "{}",
+        stderr
+    );
+    let shop = stderr.find("shop (1 findings)").unwrap();
+    assert!(stderr[shop..].contains("\n  ./src/shop/cart.py:1 - function \"total\""));
+}
+
+#[test]
+fn the_headline_is_the_only_line_on_stdout() {
+    let root = tempfile::Builder::new()
+        .prefix("snapbug")
+        .tempdir()
+        .unwrap();
+    fs::write(root.path().join("a.py"), "def helper():\n    pass\n").unwrap();
+    fs::write(root.path().join("b.py"), "pass\n").unwrap();
+    let run = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_snapbug"))
+            .args(args)
+            .arg(root.path())
+            .output()
+            .unwrap()
+    };
+
+    let output = run(&[]);
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "snapbug: 1 findings in 2 files\n"
+    );
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .contains("function \"helper\" may be unused"));
+    // a report on stdout isn't followed by the headline.
+    let output = run(&["--output-format", "json"]);
+    assert!(serde_json::from_slice::<serde_json::Value>(&output.stdout).is_ok());
+}
+
+#[test]
+fn configuration_can_be_fetched_from_a_url() {
+    let root = tempfile::Builder::new()
+        .prefix("snapbug")
+        .tempdir()
+        .unwrap();
+    fs::write(root.path().join("a.py"), "def helper():\n    pass\n").unwrap();
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/snapbug.toml", listener.local_addr().unwrap());
+    let server = thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut request = BufReader::new(stream.try_clone().unwrap());
+        let mut line = String::new();
+        while request.read_line(&mut line).unwrap() > 2 {
+            line.clear();
+        }
+        let config = "allow = [\"^helper$\"]\n";
+        write!(
+            stream,
+            "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            config.len(),
+            config
+        )
+        .unwrap();
+    });
+
+    let output = Command::new(env!("CARGO_BIN_EXE_snapbug"))
+        .args(["--config", &url])
+        .arg(root.path())
+        .output()
+        .unwrap();
+    server.join().unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(output.stdout, b"snapbug: 0 findings in 1 files\n");
+}
+
+#[test]
+fn dry_runs_estimate_each_directory_without_failing() {
+    let root = tempfile::Builder::new()
+        .prefix("snapbug")
+        .tempdir()
+        .unwrap();
+    fs::create_dir(root.path().join("pkg")).unwrap();
+    fs::create_dir(root.path().join(".venv")).unwrap();
+    fs::write(
+        root.path().join("pkg").join("jobs.py"),
+        "def run():\n    pass\n\n\nclass Job:\n    def start(self):\n        pass\n",
+    )
+    .unwrap();
+    fs::write(root.path().join("tool.py"), "def main():\n    pass\n").unwrap();
+    fs::write(
+        root.path().join(".venv").join("site.py"),
+        "def x():\n    pass\n",
+    )
+    .unwrap();
+    fs::write(root.path().join("notes.txt"), "def y():\n").unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_snapbug"))
+        .arg("--dry-run")
+        .arg(root.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let lines: Vec<Vec<&str>> = stdout
+        .lines()
+        .map(|line| line.split_whitespace().collect())
+        .collect();
+    assert_eq!(
+        lines,
+        [
+            vec!["files", "definitions", "directory"],
+            vec!["1", "1", "."],
+            vec!["1", "2", "pkg"],
+            vec!["2", "3", "total"],
+            vec!["skipped", "1", "hidden", "entries"],
+            vec!["skipped", "1", "unsupported", "entries"],
+        ]
+    );
+}
+
+#[test]
+fn skipped_entries_are_listed_with_their_reasons() {
+    let root = tempfile::Builder::new()
+        .prefix("snapbug")
+        .tempdir()
+        .unwrap();
+    fs::create_dir(root.path().join(".hidden")).unwrap();
+    fs::create_dir(root.path().join("migrations")).unwrap();
+    fs::write(root.path().join("a.py"), "pass\n").unwrap();
+    fs::write(root.path().join("b.py"), "\0\0").unwrap();
+    fs::write(root.path().join("notes.txt"), "pass\n").unwrap();
+    let run = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_snapbug"))
+            .args(["--exclude", "migrations/"])
+            .args(args)
+            .arg(root.path())
+            .output()
+            .unwrap()
+    };
+
+    let report: serde_json::Value =
+        serde_json::from_slice(&run(&["--output-format", "json"]).stdout).unwrap();
+    let skipped: Vec<(&str, &str)> = report["skipped"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|entry| {
+            (
+                entry["path"].as_str().unwrap(),
+                entry["reason"].as_str().unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        skipped,
+        [
+            (".hidden", "hidden"),
+            ("migrations", "excluded"),
+            ("notes.txt", "unsupported"),
+            ("b.py", "binary")
+        ]
+    );
+    assert_eq!(report["stats"]["files"], 1);
+    assert_eq!(report["stats"]["skipped"]["binary"], 1);
+
+    let stderr = String::from_utf8(run(&["--verbose"]).stderr).unwrap();
+    assert!(
+        stderr.contains("skipped migrations (excluded)\n"),
+        "{}",
+        stderr
+    );
+    assert!(stderr.contains("skipped b.py (binary)\n"), "{}", stderr);
+}
+
+#[test]
+fn selfcheck_fails_when_throughput_falls_below_the_baseline() {
+    let root = tempfile::Builder::new()
+        .prefix("snapbug")
+        .tempdir()
+        .unwrap();
+    let baseline = root.path().join("perf.json");
+    let selfcheck = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_snapbug"))
+            .arg("selfcheck")
+            .arg("--baseline")
+            .arg(&baseline)
+            .args(args)
+            .output()
+            .unwrap()
+    };
+
+    assert!(selfcheck(&["--save"]).status.success());
+    let mut measurement: serde_json::Value =
+        serde_json::from_slice(&fs::read(&baseline).unwrap()).unwrap();
+    assert_eq!(measurement["files"], 400);
+
+    measurement["lines_per_second"] = 1e12.into();
+    fs::write(&baseline, measurement.to_string()).unwrap();
+    let output = selfcheck(&[]);
+    assert!(!output.status.success());
+    assert!(
+        String::from_utf8_lossy(&output.stderr).contains("throughput fell to 0% of the baseline")
+    );
+}
+
+#[test]
+fn each_finding_suggests_its_usual_fix() {
+    let root = tempfile::Builder::new()
+        .prefix("snapbug")
+        .tempdir()
+        .unwrap();
+    let api = "__all__ = [\"gone\"]\n\n\ndef public():\n    pass\n\n\npublic()\n";
+    fs::write(root.path().join("api.py"), api).unwrap();
+    let tool = "def main():\n    pass\n\n\nif __name__ == \"__main__\":\n    main()\n";
+    fs::write(root.path().join("tool.py"), tool).unwrap();
+    let plugins = "def _load(name):\n    return eval(name)\n";
+    fs::write(root.path().join("plugins.py"), plugins).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_snapbug"))
+        .args(["--exports", "--script-only", "--output-format", "json"])
+        .arg(root.path())
+        .output()
+        .unwrap();
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let suggestions: Vec<(&str, &str)> = report["findings"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .chain(report["quarantined"].as_array().unwrap())
+        .map(|finding| {
+            (
+                finding["name"].as_str().unwrap(),
+                finding["suggestion"].as_str().unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        suggestions,
+        [
+            ("gone", "remove-from-__all__"),
+            ("public", "add-to-__all__"),
+            ("main", "make-private"),
+            ("_load", "add-suppression")
+        ]
+    );
+}
+
+#[test]
+fn jsonl_reports_hold_one_whole_finding_per_line() {
+    let root = tempfile::Builder::new()
+        .prefix("snapbug")
+        .tempdir()
+        .unwrap();
+    fs::write(
+        root.path().join("a.py"),
+        "def first():\n    pass\n\n\ndef second():\n    pass\n",
+    )
+    .unwrap();
+    fs::write(
+        root.path().join("b.py"),
+        "import importlib\n\n\ndef third():\n    pass\n",
+    )
+    .unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_snapbug"))
+        .args(["--output-format", "jsonl"])
+        .arg(root.path())
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let findings: Vec<(String, bool)> = stdout
+        .lines()
+        .map(|line| {
+            let finding: serde_json::Value = serde_json::from_str(line).unwrap();
+            (
+                finding["fingerprint"].as_str().unwrap().to_owned(),
+                finding["quarantined"].as_bool().unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        findings,
+        [
+            ("a.py::first".to_owned(), false),
+            ("a.py::second".to_owned(), false),
+            ("b.py::third".to_owned(), true)
+        ]
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn the_build_graph_scopes_mentions_to_dependent_targets() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let root = tempfile::Builder::new()
+        .prefix("snapbug")
+        .tempdir()
+        .unwrap();
+    let workspace = root.path().join("workspace");
+    for (path, source) in [
+        (
+            "lib/core.py",
+            "def for_app():\n    pass\n\n\ndef for_other():\n    pass\n",
+        ),
+        ("app/main.py", "from lib.core import for_app\n\nfor_app()\n"),
+        ("other/guess.py", "for_other()\n"),
+    ] {
+        let path = workspace.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, source).unwrap();
+    }
+    // a stand-in for bazel, answering the queries snapbug makes.
+    let query = r#"<?xml version="1.1" encoding="UTF-8" standalone="no"?>
+<query version="2">
+    <rule class="py_library" location="lib/BUILD:1:11" name="//lib:lib">
+        <list name="srcs">
+            <label value="//lib:core.py"/>
+        </list>
+    </rule>
+    <rule class="py_binary" location="app/BUILD:1:10" name="//app:app">
+        <list name="srcs">
+            <label value="//app:main.py"/>
+        </list>
+        <list name="deps">
+            <label value="//lib"/>
+        </list>
+    </rule>
+    <rule class="py_binary" location="other/BUILD:1:10" name="//other:other">
+        <list name="srcs">
+            <label value="//other:guess.py"/>
+        </list>
+    </rule>
+</query>"#;
+    let bin = root.path().join("bin");
+    fs::create_dir(&bin).unwrap();
+    fs::write(root.path().join("query.xml"), query).unwrap();
+    let script = format!(
+        "#!/bin/sh\ncase \"$1\" in\n    info) echo {} ;;\n    query) cat {} ;;\n    *) exit 2 ;;\nesac\n",
+        workspace.display(),
+        root.path().join("query.xml").display()
+    );
+    fs::write(bin.join("bazel"), script).unwrap();
+    fs::set_permissions(bin.join("bazel"), fs::Permissions::from_mode(0o755)).unwrap();
+    let path = format!("{}:{}", bin.display(), std::env::var("PATH").unwrap());
+
+    let names = |args: &[&str]| {
+        let output = Command::new(env!("CARGO_BIN_EXE_snapbug"))
+            .args(args)
+            .args(["--output-format", "json"])
+            .arg(&workspace)
+            .env("PATH", &path)
+            .output()
+            .unwrap();
+        let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        report["findings"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|finding| finding["name"].as_str().unwrap().to_owned())
+            .collect::<Vec<_>>()
+    };
+
+    assert!(names(&[]).is_empty());
+    assert_eq!(names(&["--build-graph", "bazel"]), ["for_other"]);
+}
+
+#[test]
+fn the_cache_reuses_scans_of_unchanged_files_in_any_checkout() {
+    let root = tempfile::Builder::new()
+        .prefix("snapbug")
+        .tempdir()
+        .unwrap();
+    let (first, second, cache) = (
+        root.path().join("first"),
+        root.path().join("second"),
+        root.path().join("cache"),
+    );
+    for checkout in [&first, &second] {
+        fs::create_dir(checkout).unwrap();
+        fs::write(checkout.join("a.py"), "def helper():\n    pass\n").unwrap();
+        fs::write(checkout.join("b.py"), "pass\n").unwrap();
+    }
+    let scan = |checkout: &Path| {
+        let output = Command::new(env!("CARGO_BIN_EXE_snapbug"))
+            .arg("--cache-dir")
+            .arg(&cache)
+            .args(["--output-format", "json"])
+            .arg(checkout)
+            .output()
+            .unwrap();
+        let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        let stats = &report["stats"]["cache"];
+        let paths: Vec<String> = report["findings"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|finding| finding["path"].as_str().unwrap().to_owned())
+            .collect();
+        (
+            stats["hits"].as_u64().unwrap(),
+            stats["misses"].as_u64().unwrap(),
+            paths,
+        )
+    };
+
+    let helper = |checkout: &Path| vec![checkout.join("a.py").display().to_string()];
+    assert_eq!(scan(&first), (0, 2, helper(&first)));
+    assert_eq!(scan(&first), (2, 0, helper(&first)));
+    // findings reused from the cache are reported where they are now.
+    assert_eq!(scan(&second), (2, 0, helper(&second)));
+    fs::write(second.join("b.py"), "from a import helper\n\nhelper()\n").unwrap();
+    assert_eq!(scan(&second), (1, 1, Vec::new()));
+}
@@ -0,0 +1,99 @@
+use std::fs;
+
+use snapbug::{assert_no_unused, Options, UnusedFunctions};
+use tempfile::TempDir;
+
+/// Writes the given modules to a fresh tree. The directory must not start with a dot, or it would be skipped.
+fn tree(modules: &[(&str, &str)]) -> TempDir {
+    let root = tempfile::Builder::new()
+        .prefix("snapbug")
+        .tempdir()
+        .unwrap();
+    for (name, source) in modules {
+        fs::write(root.path().join(name), source).unwrap();
+    }
+    root
+}
+
+/// The names and rules of everything flagged in the tree.
+fn flagged(modules: &[(&str, &str)]) -> Vec<(String, String)> {
+    let root = tree(modules);
+    match assert_no_unused(root.path(), &Options::default()) {
+        Ok(()) => Vec::new(),
+        Err(err) => err
+            .downcast::<UnusedFunctions>()
+            .unwrap()
+            .findings
+            .into_iter()
+            .map(|finding| (finding.name, finding.rule))
+            .collect(),
+    }
+}
+
+const HELPER: &str = "def my_helper(func):\n    return func\n\n";
+
+#[test]
+fn decorating_a_used_function_is_usage() {
+    let source = format!("{}@my_helper\ndef run():\n    pass\n\nrun()\n", HELPER);
+    assert_eq!(flagged(&[("a.py", &source)]), []);
+}
+
+#[test]
+fn decorating_with_arguments_is_usage() {
+    let source = format!(
+        "{}@my_helper(\n    retries=3,\n)\ndef run():\n    pass\n\nrun()\n",
+        HELPER
+    );
+    assert_eq!(flagged(&[("a.py", &source)]), []);
+}
+
+#[test]
+fn decorating_through_a_module_is_usage() {
+    let caller = "import a\n\n@a.my_helper\ndef run():\n    pass\n\nrun()\n";
+    assert_eq!(flagged(&[("a.py", HELPER), ("b.py", caller)]), []);
+}
+
+#[test]
+fn decorating_a_method_is_usage() {
+    let source = format!(
+        "{}class Job:\n    @my_helper\n    def run(self):\n        pass\n\nJob().run()\n",
+        HELPER
+    );
+    assert_eq!(flagged(&[("a.py", &source)]), []);
+}
+
+#[test]
+fn decorating_only_dead_functions_is_a_dead_decorator() {
+    let source = format!("{}@my_helper\ndef run():\n    pass\n", HELPER);
+    assert_eq!(
+        flagged(&[("a.py", &source)]),
+        [
+            ("my_helper".to_owned(), "dead-decorator-function".to_owned()),
+            ("run".to_owned(), "unused-function".to_owned()),
+        ]
+    );
+}
+
+#[test]
+fn decorating_dead_functions_through_a_module_is_a_dead_decorator() {
+    let caller = "import a\n\n@a.my_helper\ndef run():\n    pass\n";
+    assert_eq!(
+        flagged(&[("a.py", HELPER), ("b.py", caller)]),
+        [
+            ("my_helper".to_owned(), "dead-decorator-function".to_owned()),
+            ("run".to_owned(), "unused-function".to_owned()),
+        ]
+    );
+}
+
+#[test]
+fn decorating_dead_functions_after_a_space_is_a_dead_decorator() {
+    let source = format!("{}@ my_helper\ndef run():\n    pass\n", HELPER);
+    assert_eq!(
+        flagged(&[("a.py", &source)]),
+        [
+            ("my_helper".to_owned(), "dead-decorator-function".to_owned()),
+            ("run".to_owned(), "unused-function".to_owned()),
+        ]
+    );
+}
@@ -0,0 +1,93 @@
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    fs,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use snapbug::{Options, Scanner};
+
+/// Tracks the most memory held at once, so that a scan can be checked not to hold whole files.
+struct Peak;
+
+static HELD: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+impl Peak {
+    fn grow(size: usize) {
+        let held = HELD.fetch_add(size, Ordering::Relaxed) + size;
+        PEAK.fetch_max(held, Ordering::Relaxed);
+    }
+}
+
+unsafe impl GlobalAlloc for Peak {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        Self::grow(layout.size());
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        HELD.fetch_sub(layout.size(), Ordering::Relaxed);
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        if new_size > layout.size() {
+            Self::grow(new_size - layout.size());
+        } else {
+            HELD.fetch_sub(layout.size() - new_size, Ordering::Relaxed);
+        }
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: Peak = Peak;
+
+/// Writes a python file of about the given size to a fresh tree, calling a helper on nearly every line.
+fn tree(size: usize) -> tempfile::TempDir {
+    let root = tempfile::Builder::new()
+        .prefix("snapbug")
+        .tempdir()
+        .unwrap();
+    let mut source = String::from("def helper():\n    pass\n\n\ndef main():\n");
+    while source.len() < size {
+        source.push_str("    if helper():  # the helper is called again and again\n");
+    }
+    source.push_str("\n\nif __name__ == \"__main__\":\n    main()\n");
+    fs::write(root.path().join("big.py"), &source).unwrap();
+    root
+}
+
+/// The most memory held at once while scanning the tree, beyond what was held before.
+fn peak(scanner: &Scanner, root: &tempfile::TempDir) -> usize {
+    let before = HELD.load(Ordering::Relaxed);
+    PEAK.store(before, Ordering::Relaxed);
+    let findings = scanner.scan(&[root.path()]).unwrap();
+    assert!(findings.is_empty());
+    PEAK.load(Ordering::Relaxed) - before
+}
+
+#[test]
+fn scanning_a_large_file_holds_a_bounded_part_of_it() {
+    let scanner = Scanner::new(Options {
+        strip_comments: true,
+        complexity: true,
+        duplicates: true,
+        ..Options::default()
+    });
+    let small = tree(64 * 1024);
+    let size = 12 * 1024 * 1024;
+    let large = tree(size);
+
+    // the first scan also pays for compiling patterns.
+    peak(&scanner, &small);
+    let baseline = peak(&scanner, &small);
+    let held = peak(&scanner, &large);
+    assert!(
+        held < baseline + size / 8,
+        "held {} bytes at once scanning a {} byte file, and {} for a small one",
+        held,
+        size,
+        baseline
+    );
+}
@@ -0,0 +1,502 @@
+use std::fs;
+
+use snapbug::{assert_no_unused, DeclarationKind, Mode, Options, Preset, Scanner, UnusedFunctions};
+use tempfile::TempDir;
+
+/// Writes the given files to a fresh tree.
+fn tree(files: &[(&str, &str)]) -> TempDir {
+    let root = tempfile::Builder::new()
+        .prefix("snapbug")
+        .tempdir()
+        .unwrap();
+    for (name, contents) in files {
+        let path = root.path().join(name);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+    root
+}
+
+/// The names flagged in the tree, in order.
+fn flagged(root: &TempDir, options: Options) -> Vec<String> {
+    Scanner::new(options)
+        .scan(&[root.path()])
+        .unwrap()
+        .into_iter()
+        .map(|finding| finding.name)
+        .collect()
+}
+
+#[test]
+fn scanning_returns_each_finding() {
+    let root = tempfile::Builder::new()
+        .prefix("snapbug")
+        .tempdir()
+        .unwrap();
+    let source = "def used():\n    pass\n\n\ndef unused():\n    pass\n\n\nused()\n";
+    fs::write(root.path().join("a.py"), source).unwrap();
+
+    let findings = Scanner::new(Options::default())
+        .scan(&[root.path()])
+        .unwrap();
+    assert_eq!(findings.len(), 1);
+    assert_eq!(findings[0].name, "unused");
+    assert_eq!(findings[0].line, 5);
+    assert_eq!(findings[0].path, root.path().join("a.py"));
+    assert_eq!(findings[0].message, "function \"unused\" may be unused");
+}
+
+#[test]
+fn functions_only_tests_use_are_flagged() {
+    let root = tempfile::Builder::new()
+        .prefix("snapbug")
+        .tempdir()
+        .unwrap();
+    fs::create_dir(root.path().join("tests")).unwrap();
+    fs::write(root.path().join("a.py"), "def tested():\n    pass\n").unwrap();
+    let test = "from a import tested\n\n\ndef testTested():\n    tested()\n";
+    fs::write(root.path().join("tests").join("check_a.py"), test).unwrap();
+
+    let findings = Scanner::new(Options {
+        test_only: true,
+        ..Options::default()
+    })
+    .scan(&[root.path()])
+    .unwrap();
+    assert_eq!(findings.len(), 1);
+    assert_eq!(findings[0].name, "tested");
+    assert_eq!(
+        findings[0].message,
+        "function \"tested\" is only used by tests"
+    );
+}
+
+#[test]
+fn mentions_under_a_main_guard_count_unless_script_only() {
+    let root = tempfile::Builder::new()
+        .prefix("snapbug")
+        .tempdir()
+        .unwrap();
+    let source = "def main():\n    pass\n\n\nif __name__ == \"__main__\":\n    main()\n";
+    fs::write(root.path().join("a.py"), source).unwrap();
+
+    let findings = Scanner::new(Options::default())
+        .scan(&[root.path()])
+        .unwrap();
+    assert!(findings.is_empty());
+
+    let findings = Scanner::new(Options {
+        script_only: true,
+        ..Options::default()
+    })
+    .scan(&[root.path()])
+    .unwrap();
+    assert_eq!(findings.len(), 1);
+    assert_eq!(findings[0].name, "main");
+    assert_eq!(findings[0].rule, "script-only-function");
+}
+
+#[test]
+fn findings_of_every_kind_are_reported_in_order() {
+    let root = tempfile::Builder::new()
+        .prefix("snapbug")
+        .tempdir()
+        .unwrap();
+    let source = "from typing import overload\n\n__all__ = [\"missing\"]\n\n\n@overload\ndef _stub(x: int) -> int: ...\n\n\ndef _unused():\n    pass\n";
+    fs::write(root.path().join("a.py"), source).unwrap();
+
+    let findings = Scanner::new(Options {
+        exports: true,
+        ..Options::default()
+    })
+    .scan(&[root.path()])
+    .unwrap();
+    let found: Vec<(usize, &str)> = findings
+        .iter()
+        .map(|finding| (finding.line, finding.rule.as_str()))
+        .collect();
+    assert_eq!(
+        found,
+        [
+            (3, "undefined-export"),
+            (7, "overload-without-implementation"),
+            (10, "unused-function"),
+        ]
+    );
+}
+
+#[test]
+fn overloads_missing_from_all_are_reported_once() {
+    let root = tempfile::Builder::new()
+        .prefix("snapbug")
+        .tempdir()
+        .unwrap();
+    let source = "from typing import overload\n\n__all__ = []\n\n\n@overload\ndef lonely(x: int) -> int: ...\n\n\n@overload\ndef lonely(x: str) -> str: ...\n\n\ndef lonely(x):\n    return x\n";
+    fs::write(root.path().join("m.py"), source).unwrap();
+
+    let findings = Scanner::new(Options {
+        exports: true,
+        ..Options::default()
+    })
+    .scan(&[root.path()])
+    .unwrap();
+    let unlisted: Vec<usize> = findings
+        .iter()
+        .filter(|finding| finding.rule == "unlisted-export")
+        .map(|finding| finding.line)
+        .collect();
+    assert_eq!(unlisted, [7]);
+}
+
+#[test]
+fn definitions_in_strings_are_not_declared() {
+    let root = tempfile::Builder::new()
+        .prefix("snapbug")
+        .tempdir()
+        .unwrap();
+    let source = "def _used():\n    \"\"\"\n    def _in_docstring():\n    \"\"\"\n    return \"\"\"\ndef _in_string():\n\"\"\"\n\n\n@register(\n    \"\"\"\ndef _in_decorator():\n    \"\"\",\n)\nasync def _decorated():\n    pass\n\n\n_used()\n";
+    fs::write(root.path().join("a.py"), source).unwrap();
+
+    let findings = Scanner::new(Options::default())
+        .scan(&[root.path()])
+        .unwrap();
+    let found: Vec<(usize, &str)> = findings
+        .iter()
+        .map(|finding| (finding.line, finding.name.as_str()))
+        .collect();
+    assert_eq!(found, [(15, "_decorated")]);
+}
+
+#[test]
+fn modules_that_do_not_parse_fall_back_to_patterns() {
+    let root = tempfile::Builder::new()
+        .prefix("snapbug")
+        .tempdir()
+        .unwrap();
+    let source = "print 'python 2'\n\n\ndef _unused():\n    pass\n";
+    fs::write(root.path().join("a.py"), source).unwrap();
+
+    let findings = Scanner::new(Options::default())
+        .scan(&[root.path()])
+        .unwrap();
+    assert_eq!(findings.len(), 1);
+    assert_eq!(findings[0].name, "_unused");
+    assert_eq!(findings[0].line, 4);
+}
+
+#[test]
+fn origin_weights_leave_a_files_own_mentions_alone() {
+    for weight in [0, 3] {
+        let root = tempfile::Builder::new()
+            .prefix("snapbug")
+            .tempdir()
+            .unwrap();
+        fs::create_dir(root.path().join("tests")).unwrap();
+        let config = format!("origin_weights = {{ tests = {} }}\n", weight);
+        fs::write(root.path().join("snapbug.toml"), config).unwrap();
+        let test = "def used_helper():\n    pass\n\n\ndef dead_helper():\n    pass\n\n\ndef test_x():\n    used_helper()\n";
+        fs::write(root.path().join("tests").join("test_a.py"), test).unwrap();
+
+        let findings = Scanner::new(Options::default())
+            .scan(&[root.path()])
+            .unwrap();
+        let names: Vec<&str> = findings
+            .iter()
+            .map(|finding| finding.name.as_str())
+            .collect();
+        assert_eq!(names, ["dead_helper"], "tests = {}", weight);
+    }
+}
+
+#[test]
+fn the_django_preset_enables_its_frameworks_and_skips_migrations() {
+    let root = tempfile::Builder::new()
+        .prefix("snapbug")
+        .tempdir()
+        .unwrap();
+    let source = "from celery import shared_task\n\n\n@shared_task\ndef send():\n    pass\n";
+    fs::write(root.path().join("tasks.py"), source).unwrap();
+    fs::create_dir(root.path().join("migrations")).unwrap();
+    fs::write(
+        root.path().join("migrations").join("0001_initial.py"),
+        "def forwards(apps, schema_editor):\n    pass\n",
+    )
+    .unwrap();
+
+    let findings = Scanner::new(Options {
+        presets: vec![Preset::DjangoApp],
+        ..Options::default()
+    })
+    .scan(&[root.path()])
+    .unwrap();
+    assert!(findings.is_empty());
+}
+
+#[test]
+fn preset_methods_are_only_exempt_in_their_frameworks_files() {
+    let root = tree(&[
+        (
+            "model.py",
+            "import torch\n\n\nclass Net:\n    def forward(self):\n        pass\n",
+        ),
+        ("shop/apps.py", ""),
+        (
+            "shop/views/home.py",
+            "class Home:\n    def get(self):\n        pass\n",
+        ),
+        (
+            "tools/build.py",
+            "def setup():\n    pass\n\n\ndef handle():\n    pass\n",
+        ),
+    ]);
+
+    let findings = Scanner::new(Options {
+        presets: vec![Preset::DjangoApp, Preset::DataScience],
+        ..Options::default()
+    })
+    .scan(&[root.path()])
+    .unwrap();
+    let mut flagged: Vec<(String, String)> = findings
+        .into_iter()
+        .map(|finding| {
+            let file = finding
+                .path
+                .file_name()
+                .unwrap()
+                .to_string_lossy()
+                .into_owned();
+            (file, finding.name)
+        })
+        .collect();
+    flagged.sort();
+    assert_eq!(
+        flagged,
+        [
+            ("build.py".to_owned(), "handle".to_owned()),
+            ("build.py".to_owned(), "setup".to_owned())
+        ]
+    );
+}
+
+#[test]
+fn reachability_flags_functions_only_dead_code_calls() {
+    let root = tempfile::Builder::new()
+        .prefix("snapbug")
+        .tempdir()
+        .unwrap();
+    let source = "def ping():\n    pong()\n\n\ndef pong():\n    ping()\n";
+    fs::write(root.path().join("a.py"), source).unwrap();
+    let scan = |mode| {
+        let findings = Scanner::new(Options {
+            mode,
+            ..Options::default()
+        })
+        .scan(&[root.path()])
+        .unwrap();
+        findings
+            .into_iter()
+            .map(|finding| (finding.name, finding.rule))
+            .collect::<Vec<_>>()
+    };
+
+    assert!(scan(Mode::Mentions).is_empty());
+    assert_eq!(
+        scan(Mode::Reachability),
+        [
+            ("ping".to_owned(), "unreachable-function".to_owned()),
+            ("pong".to_owned(), "unreachable-function".to_owned())
+        ]
+    );
+}
+
+#[test]
+fn names_exported_lazily_are_used() {
+    let getattr = "import importlib\n\n_LAZY = {\"compute\": \".impl\"}\n\n\ndef __getattr__(name):\n    return getattr(importlib.import_module(_LAZY[name], __name__), name)\n";
+    let attach = "import lazy_loader as lazy\n\n__getattr__, __dir__, __all__ = lazy.attach(\n    __name__, submod_attrs={\"impl\": [\"smooth\"]}\n)\n";
+    let root = tree(&[
+        ("eager/__init__.py", "NAMES = [\"orphan\"]\n"),
+        ("eager/impl.py", "def orphan():\n    pass\n"),
+        ("getattr/__init__.py", getattr),
+        ("getattr/impl.py", "def compute():\n    pass\n"),
+        ("attach/__init__.py", attach),
+        ("attach/impl.py", "def smooth():\n    pass\n"),
+    ]);
+
+    let options = Options {
+        strip_comments: true,
+        ..Options::default()
+    };
+    assert_eq!(flagged(&root, options), ["orphan"]);
+}
+
+#[test]
+fn asserting_no_unused_lists_every_finding() {
+    let root = tree(&[
+        ("pkg/a.py", "def used():\n    pass\n\n\nused()\n"),
+        ("pkg/b.py", "class Job:\n    pass\n"),
+    ]);
+    let package = root.path().join("pkg");
+    assert!(assert_no_unused(&package, &Options::default()).is_ok());
+
+    let err = assert_no_unused(
+        &package,
+        &Options {
+            kinds: vec![DeclarationKind::Classes],
+            ..Options::default()
+        },
+    )
+    .unwrap_err();
+    let unused = err.downcast_ref::<UnusedFunctions>().unwrap();
+    assert_eq!(unused.findings.len(), 1);
+    assert_eq!(
+        err.to_string(),
+        format!(
+            "1 possible unused functions were found\n  {}:1 - class \"Job\" (unused-class)",
+            package.join("b.py").display()
+        )
+    );
+}
+
+#[test]
+fn pyproject_configures_exclusions_and_allowed_names() {
+    let pyproject = "[project]\nname = \"shop\"\n\n[tool.snapbug]\nexclude = [\"vendor/\"]\nallow = [\"^on_\"]\n";
+    let root = tree(&[
+        ("pyproject.toml", pyproject),
+        ("vendor/lib.py", "def patched():\n    pass\n"),
+        (
+            "shop.py",
+            "def on_start():\n    pass\n\n\ndef test_total():\n    pass\n\n\ndef _cache():\n    pass\n",
+        ),
+    ]);
+
+    // the configured names replace the built-in `test_` and `__`.
+    assert_eq!(flagged(&root, Options::default()), ["test_total", "_cache"]);
+    let options = Options {
+        allow: vec!["^_".to_owned()],
+        ..Options::default()
+    };
+    assert_eq!(flagged(&root, options), ["test_total"]);
+}
+
+#[test]
+fn mentions_from_dead_modules_do_not_count() {
+    let root = tree(&[
+        ("__main__.py", "from lib import serve\n\nserve()\n"),
+        (
+            "lib.py",
+            "def serve():\n    pass\n\n\ndef helper():\n    pass\n\n\ndef legacy():\n    pass\n",
+        ),
+        (
+            "old.py",
+            "import older\nfrom lib import helper\n\nhelper()\n",
+        ),
+        ("older.py", "from lib import legacy\n\nlegacy()\n"),
+    ]);
+
+    assert!(flagged(&root, Options::default()).is_empty());
+    let findings = Scanner::new(Options {
+        dead_modules: true,
+        ..Options::default()
+    })
+    .scan(&[root.path()])
+    .unwrap();
+    let flagged: Vec<(&str, &str)> = findings
+        .iter()
+        .map(|finding| (finding.name.as_str(), finding.rule.as_str()))
+        .collect();
+    assert_eq!(
+        flagged,
+        [
+            ("helper", "dead-module-only-function"),
+            ("legacy", "dead-module-only-function")
+        ]
+    );
+}
+
+#[test]
+fn suppressed_exported_and_decorated_functions_are_used() {
+    let source = "from web import app\n\n__all__ = [\"exported\"]\n\n\ndef exported():\n    pass\n\n\ndef looked_up():  # snapbug: ignore\n    pass\n\n\n@app.route(\"/\")\ndef index():\n    pass\n\n\ndef orphan():\n    pass\n";
+    let root = tree(&[
+        ("snapbug.toml", "decorators = [\"app.route\"]\n"),
+        ("views.py", source),
+        ("web.py", "app = object()\n"),
+    ]);
+
+    // names in `__all__` are only in strings, which don't usually count.
+    let options = Options {
+        strip_comments: true,
+        ..Options::default()
+    };
+    assert_eq!(flagged(&root, options), ["orphan"]);
+}
+
+#[test]
+fn exports_must_match_the_modules_definitions() {
+    let source = "__all__ = [\"listed\", \"missing\"]\n\n\ndef listed():\n    pass\n\n\ndef unlisted():\n    pass\n\n\ndef _private():\n    pass\n\n\nunlisted()\n_private()\n";
+    let root = tree(&[("pkg/api.py", source)]);
+
+    let findings = Scanner::new(Options {
+        exports: true,
+        ..Options::default()
+    })
+    .scan(&[root.path()])
+    .unwrap();
+    let flagged: Vec<(&str, &str, usize)> = findings
+        .iter()
+        .map(|finding| (finding.name.as_str(), finding.rule.as_str(), finding.line))
+        .collect();
+    assert_eq!(
+        flagged,
+        [
+            ("missing", "undefined-export", 1),
+            ("unlisted", "unlisted-export", 8)
+        ]
+    );
+}
+
+#[test]
+fn scoped_counting_only_counts_files_that_could_see_a_declaration() {
+    let core = "def parse():\n    pass\n\n\ndef render():\n    pass\n\n\ndef emit():\n    pass\n\n\ndef lost():\n    pass\n";
+    let root = tree(&[
+        ("snapbug.toml", "extra_roots = [\"scripts/\"]\n"),
+        ("lib/core.py", core),
+        ("lib/util.py", "render()\n"),
+        ("app/main.py", "from lib.core import parse\n\nparse()\n"),
+        ("scripts/run.py", "emit()\n"),
+        ("other/guess.py", "lost()\n"),
+    ]);
+
+    assert!(flagged(&root, Options::default()).is_empty());
+    let options = Options {
+        scoped_counting: true,
+        ..Options::default()
+    };
+    assert_eq!(flagged(&root, options), ["lost"]);
+}
+
+#[test]
+fn declarations_only_script_code_uses_are_script_only() {
+    let source = "import argparse\n\nDEFAULT_PORT = 8000\nTIMEOUT = 5\n\n\ndef to_port(text):\n    return int(text)\n\n\ndef serve(port):\n    return port, TIMEOUT\n\n\ndef build_parser():\n    parser = argparse.ArgumentParser()\n    parser.add_argument(\"--port\", type=to_port, default=DEFAULT_PORT)\n    return parser\n\n\nif __name__ == \"__main__\":\n    serve(build_parser().parse_args().port)\n";
+    let root = tree(&[("server.py", source)]);
+
+    let findings = Scanner::new(Options {
+        script_only: true,
+        constants: true,
+        ..Options::default()
+    })
+    .scan(&[root.path()])
+    .unwrap();
+    let flagged: Vec<(&str, &str)> = findings
+        .iter()
+        .map(|finding| (finding.name.as_str(), finding.rule.as_str()))
+        .collect();
+    assert_eq!(
+        flagged,
+        [
+            ("DEFAULT_PORT", "script-only-constant"),
+            ("to_port", "script-only-function"),
+            ("serve", "script-only-function"),
+            ("build_parser", "script-only-function")
+        ]
+    );
+}
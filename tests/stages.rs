@@ -0,0 +1,145 @@
+use std::{fs, path::Path};
+
+use snapbug::{count, declarations, walk, Options, ScanOptions};
+use tempfile::TempDir;
+
+/// Writes the given files to a fresh tree. The directory must not start with a dot, or it would be skipped.
+fn tree(files: &[(&str, &str)]) -> TempDir {
+    let root = tempfile::Builder::new()
+        .prefix("snapbug")
+        .tempdir()
+        .unwrap();
+    for (name, contents) in files {
+        let path = root.path().join(name);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+    root
+}
+
+/// The path of the entry relative to the root, with forward slashes.
+fn relative(path: &Path, root: &Path) -> String {
+    path.strip_prefix(root)
+        .unwrap()
+        .to_string_lossy()
+        .replace('\\', "/")
+}
+
+#[test]
+fn walking_finds_sources_and_skips_the_rest() {
+    let dir = tree(&[
+        ("a.py", "pass\n"),
+        ("pkg/b.pyi", "pass\n"),
+        ("notes.txt", "pass\n"),
+        (".venv/c.py", "pass\n"),
+    ]);
+    let root = dir.path().canonicalize().unwrap();
+    let options = ScanOptions::new(&Options::default(), &root).unwrap();
+
+    let (files, skipped) = walk::files(&root, &options);
+    let files: Vec<String> = files
+        .iter()
+        .map(|entry| relative(entry.path(), &root))
+        .collect();
+    assert_eq!(files, ["a.py", "pkg/b.pyi"]);
+    assert_eq!(walk::count_skipped(&skipped)["hidden"], 1);
+    let skipped: Vec<(String, &str)> = skipped
+        .iter()
+        .map(|entry| (relative(&entry.path, &root), entry.reason))
+        .collect();
+    assert_eq!(
+        skipped,
+        [
+            (".venv".to_owned(), "hidden"),
+            ("notes.txt".to_owned(), "unsupported")
+        ]
+    );
+}
+
+#[test]
+fn collecting_finds_each_declaration() {
+    let source = "class Job:\n    def run(self):\n        pass\n\n\ndef helper():\n    pass\n";
+    let dir = tree(&[("a.py", source), ("b.py", "\0")]);
+    let root = dir.path().canonicalize().unwrap();
+    let options = ScanOptions::new(&Options::default(), &root).unwrap();
+    let (files, _) = walk::files(&root, &options);
+
+    let declared = declarations::collect(&files, &options).unwrap();
+    let mut functions: Vec<(&str, &str, usize)> = declared
+        .functions
+        .iter()
+        .map(|function| (function.name(), function.kind(), function.line()))
+        .collect();
+    functions.sort();
+    assert_eq!(functions, [("helper", "function", 6), ("run", "method", 2)]);
+    assert!(declared
+        .functions
+        .iter()
+        .all(|function| function.path() == root.join("a.py")));
+    let skipped: Vec<(String, &str)> = declared
+        .skipped
+        .iter()
+        .map(|entry| (relative(&entry.path, &root), entry.reason))
+        .collect();
+    assert_eq!(skipped, [("b.py".to_owned(), "binary")]);
+}
+
+#[test]
+fn counting_flags_declarations_mentioned_only_once() {
+    let dir = tree(&[
+        (
+            "a.py",
+            "def used():\n    pass\n\n\ndef unused():\n    pass\n",
+        ),
+        ("b.py", "from a import used\n\nused()\n"),
+    ]);
+    let root = dir.path().canonicalize().unwrap();
+    let options = ScanOptions::new(&Options::default(), &root).unwrap();
+    let (files, _) = walk::files(&root, &options);
+    let declared = declarations::collect(&files, &options).unwrap();
+
+    let findings = count::count(&declared, &files, &root, &options).unwrap();
+    assert_eq!(findings.len(), 1);
+    assert_eq!(findings[0].function().name(), "unused");
+    assert_eq!(findings[0].rule(), "unused-function");
+    assert_eq!(findings[0].message(), "function \"unused\" may be unused");
+}
+
+#[test]
+fn snapbugignore_files_apply_below_their_directory() {
+    let dir = tree(&[
+        (".snapbugignore", "generated/\n*_pb2.py\n"),
+        ("a.py", "pass\n"),
+        ("a_pb2.py", "pass\n"),
+        ("generated/b.py", "pass\n"),
+        ("pkg/.snapbugignore", "*.py\n!keep.py\n"),
+        ("pkg/drop.py", "pass\n"),
+        ("pkg/keep.py", "pass\n"),
+        ("other/drop.py", "pass\n"),
+        (".gitignore", "other/\n"),
+    ]);
+    let root = dir.path().canonicalize().unwrap();
+    let scan = |respect_gitignore| {
+        let options = ScanOptions::new(
+            &Options {
+                respect_gitignore,
+                ..Options::default()
+            },
+            &root,
+        )
+        .unwrap();
+        let (files, skipped) = walk::files(&root, &options);
+        let files: Vec<String> = files
+            .iter()
+            .map(|entry| relative(entry.path(), &root))
+            .collect();
+        (files, walk::count_skipped(&skipped))
+    };
+
+    let (files, skipped) = scan(false);
+    assert_eq!(files, ["a.py", "other/drop.py", "pkg/keep.py"]);
+    assert_eq!(skipped["snapbugignored"], 3);
+    let (files, skipped) = scan(true);
+    assert_eq!(files, ["a.py", "pkg/keep.py"]);
+    assert_eq!(skipped["gitignored"], 1);
+}